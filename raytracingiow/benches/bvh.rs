@@ -0,0 +1,56 @@
+extern crate criterion;
+extern crate rand;
+extern crate raytracing_iow;
+
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::BenchmarkId;
+use criterion::Criterion;
+use rand::rngs::StdRng;
+use rand::Rng;
+use rand::SeedableRng;
+use raytracing_iow::math::Ray;
+use raytracing_iow::math::Vec3;
+use raytracing_iow::render::*;
+
+fn random_spheres(n: usize) -> Vec<Sphere> {
+    let mut rng = StdRng::seed_from_u64(1);
+    return (0..n)
+        .map(|_| {
+            let center = Vec3::new(rng.gen_range(-20.0, 20.0), rng.gen_range(-20.0, 20.0), rng.gen_range(-40.0, -5.0));
+            Sphere::new(center, rng.gen_range(0.1, 0.5), Box::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5))))
+        })
+        .collect();
+}
+
+fn random_rays(n: usize) -> Vec<Ray> {
+    let mut rng = StdRng::seed_from_u64(2);
+    return (0..n)
+        .map(|_| {
+            let direction = Vec3::new(rng.gen_range(-0.5, 0.5), rng.gen_range(-0.5, 0.5), -1.0);
+            Ray::new(Vec3::new(0.0, 0.0, 0.0), direction)
+        })
+        .collect();
+}
+
+fn trace(world: &dyn Renderable, rays: &[Ray]) -> usize {
+    return rays.iter().filter(|ray| world.hit(ray, 0.001, f64::MAX).is_some()).count();
+}
+
+fn bench_world(c: &mut Criterion) {
+    let rays = random_rays(1000);
+    let mut group = c.benchmark_group("1000 rays");
+
+    for &n in &[10, 100, 1000, 10000] {
+        let linear = random_spheres(n);
+        let bvh = Bvh::new(random_spheres(n));
+
+        group.bench_with_input(BenchmarkId::new("vec", n), &n, |b, _| b.iter(|| trace(&linear, &rays)));
+        group.bench_with_input(BenchmarkId::new("bvh", n), &n, |b, _| b.iter(|| trace(&bvh, &rays)));
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_world);
+criterion_main!(benches);
@@ -0,0 +1,55 @@
+use crate::math::Vec3;
+
+#[allow(clippy::upper_case_acronyms)]
+pub trait RGB {
+    fn to_u32_rgb(&self) -> u32;
+    fn gamma_2_correct(&self) -> Self;
+}
+
+impl RGB for Vec3 {
+    //channels outside [0, 1] are clamped
+    fn to_u32_rgb(&self) -> u32 {
+        let ir = (255.99 * self.r().clamp(0.0, 1.0)) as u8;
+        let ig = (255.99 * self.g().clamp(0.0, 1.0)) as u8;
+        let ib = (255.99 * self.b().clamp(0.0, 1.0)) as u8;
+
+        let (r, g, b) = (ir as u32, ig as u32, ib as u32);
+        return (r << 16) | (g << 8) | b;
+    }
+
+    fn gamma_2_correct(&self) -> Vec3 {
+        return Vec3::new(self.r().sqrt(), self.g().sqrt(), self.b().sqrt());
+    }
+}
+
+pub trait RGBu32 {
+    fn get_r(&self) -> u8;
+    fn get_g(&self) -> u8;
+    fn get_b(&self) -> u8;
+}
+
+impl RGBu32 for u32 {
+    fn get_r(&self) -> u8 {
+        return ((self >> 16) & 0xFF) as u8;
+    }
+    fn get_g(&self) -> u8 {
+        return ((self >> 8) & 0xFF) as u8;
+    }
+    fn get_b(&self) -> u8 {
+        return (self & 0xFF) as u8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::color::{RGBu32, RGB};
+    use crate::math::Vec3;
+
+    #[test]
+    fn test_to_u32_rgb_round_trip() {
+        let rgb = Vec3::new(1.0, 0.5, 0.0).to_u32_rgb();
+        assert_eq!(rgb.get_r(), 255);
+        assert_eq!(rgb.get_g(), 127);
+        assert_eq!(rgb.get_b(), 0);
+    }
+}
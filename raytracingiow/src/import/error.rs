@@ -0,0 +1,32 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum ImportError {
+    //a model or one of the files it refers to could not be read
+    Io(String, io::Error),
+    //a line of a text format could not be understood
+    Syntax { file: String, line: usize, message: String },
+    //the file parsed but its contents do not fit together
+    Invalid(String),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            ImportError::Io(path, e) => write!(f, "could not read {}: {}", path, e),
+            ImportError::Syntax { file, line, message } => write!(f, "{} line {}: {}", file, line, message),
+            ImportError::Invalid(message) => write!(f, "{}", message),
+        };
+    }
+}
+
+impl Error for ImportError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        return match self {
+            ImportError::Io(_, e) => Some(e),
+            _ => None,
+        };
+    }
+}
@@ -0,0 +1,1086 @@
+use crate::import::error::ImportError;
+use crate::math::Aabb;
+use crate::math::Mat4;
+use crate::math::Vec3;
+use crate::output::read_image;
+use crate::output::Image;
+use crate::output::TransferFunction;
+use crate::render::*;
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+//mirrors the parts of the glTF 2.0 schema the importer reads, anything else is ignored
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Document {
+    scene: Option<usize>,
+    #[serde(default)]
+    scenes: Vec<SceneDef>,
+    #[serde(default)]
+    nodes: Vec<Node>,
+    #[serde(default)]
+    meshes: Vec<MeshDef>,
+    #[serde(default)]
+    accessors: Vec<Accessor>,
+    #[serde(default)]
+    buffer_views: Vec<BufferView>,
+    #[serde(default)]
+    buffers: Vec<BufferDef>,
+    #[serde(default)]
+    materials: Vec<MaterialDef>,
+    #[serde(default)]
+    textures: Vec<TextureDef>,
+    #[serde(default)]
+    images: Vec<ImageDef>,
+    #[serde(default)]
+    samplers: Vec<SamplerDef>,
+    #[serde(default)]
+    cameras: Vec<CameraDef>,
+    #[serde(default)]
+    extensions: DocumentExtensions,
+    #[serde(default)]
+    extensions_required: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct SceneDef {
+    #[serde(default)]
+    nodes: Vec<usize>,
+}
+
+#[derive(Deserialize)]
+struct Node {
+    #[serde(default)]
+    children: Vec<usize>,
+    mesh: Option<usize>,
+    camera: Option<usize>,
+    matrix: Option<[f64; 16]>,
+    translation: Option<[f64; 3]>,
+    //quaternion as x, y, z, w
+    rotation: Option<[f64; 4]>,
+    scale: Option<[f64; 3]>,
+    #[serde(default)]
+    extensions: NodeExtensions,
+}
+
+#[derive(Deserialize, Default)]
+struct NodeExtensions {
+    #[serde(rename = "KHR_lights_punctual")]
+    light: Option<NodeLight>,
+}
+
+#[derive(Deserialize)]
+struct NodeLight {
+    light: usize,
+}
+
+#[derive(Deserialize)]
+struct MeshDef {
+    primitives: Vec<Primitive>,
+}
+
+#[derive(Deserialize)]
+struct Primitive {
+    attributes: HashMap<String, usize>,
+    indices: Option<usize>,
+    material: Option<usize>,
+    #[serde(default = "default_mode")]
+    mode: u32,
+}
+
+const MODE_TRIANGLES: u32 = 4;
+const MODE_TRIANGLE_STRIP: u32 = 5;
+const MODE_TRIANGLE_FAN: u32 = 6;
+
+fn default_mode() -> u32 {
+    return MODE_TRIANGLES;
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Accessor {
+    buffer_view: Option<usize>,
+    #[serde(default)]
+    byte_offset: usize,
+    component_type: u32,
+    #[serde(default)]
+    normalized: bool,
+    count: usize,
+    #[serde(rename = "type")]
+    kind: String,
+    sparse: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BufferView {
+    buffer: usize,
+    #[serde(default)]
+    byte_offset: usize,
+    byte_length: usize,
+    byte_stride: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BufferDef {
+    uri: Option<String>,
+    byte_length: usize,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct MaterialDef {
+    #[serde(default)]
+    pbr_metallic_roughness: Pbr,
+    emissive_texture: Option<TextureRef>,
+    #[serde(default)]
+    emissive_factor: [f64; 3],
+    #[serde(default)]
+    extensions: MaterialExtensions,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Pbr {
+    #[serde(default = "default_base_color")]
+    base_color_factor: [f64; 4],
+    base_color_texture: Option<TextureRef>,
+    #[serde(default = "default_one")]
+    metallic_factor: f64,
+    #[serde(default = "default_one")]
+    roughness_factor: f64,
+    //roughness in green, metallic in blue
+    metallic_roughness_texture: Option<TextureRef>,
+}
+
+impl Default for Pbr {
+    fn default() -> Self {
+        Self {
+            base_color_factor: default_base_color(),
+            base_color_texture: None,
+            metallic_factor: 1.0,
+            roughness_factor: 1.0,
+            metallic_roughness_texture: None,
+        }
+    }
+}
+
+fn default_base_color() -> [f64; 4] {
+    return [1.0, 1.0, 1.0, 1.0];
+}
+
+fn default_one() -> f64 {
+    return 1.0;
+}
+
+#[derive(Deserialize)]
+struct TextureRef {
+    index: usize,
+}
+
+#[derive(Deserialize, Default)]
+struct MaterialExtensions {
+    #[serde(rename = "KHR_materials_emissive_strength")]
+    emissive_strength: Option<EmissiveStrength>,
+    #[serde(rename = "KHR_materials_ior")]
+    ior: Option<Ior>,
+    #[serde(rename = "KHR_materials_transmission")]
+    transmission: Option<Transmission>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EmissiveStrength {
+    #[serde(default = "default_one")]
+    emissive_strength: f64,
+}
+
+#[derive(Deserialize)]
+struct Ior {
+    #[serde(default = "default_ior")]
+    ior: f64,
+}
+
+fn default_ior() -> f64 {
+    return 1.5;
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Transmission {
+    #[serde(default)]
+    transmission_factor: f64,
+}
+
+#[derive(Deserialize)]
+struct TextureDef {
+    source: Option<usize>,
+    sampler: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ImageDef {
+    uri: Option<String>,
+    buffer_view: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SamplerDef {
+    mag_filter: Option<u32>,
+    #[serde(default = "default_wrap")]
+    wrap_s: u32,
+}
+
+const FILTER_NEAREST: u32 = 9728;
+const WRAP_CLAMP: u32 = 33071;
+const WRAP_MIRROR: u32 = 33648;
+
+fn default_wrap() -> u32 {
+    return 10497;
+}
+
+#[derive(Deserialize)]
+struct CameraDef {
+    #[serde(rename = "type")]
+    kind: String,
+    perspective: Option<Perspective>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Perspective {
+    //radians
+    yfov: f64,
+    aspect_ratio: Option<f64>,
+}
+
+#[derive(Deserialize, Default)]
+struct DocumentExtensions {
+    #[serde(rename = "KHR_lights_punctual")]
+    lights: Option<LightsDef>,
+}
+
+#[derive(Deserialize)]
+struct LightsDef {
+    #[serde(default)]
+    lights: Vec<LightDef>,
+}
+
+#[derive(Deserialize)]
+struct LightDef {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default = "default_light_color")]
+    color: [f64; 3],
+    #[serde(default = "default_one")]
+    intensity: f64,
+}
+
+fn default_light_color() -> [f64; 3] {
+    return [1.0, 1.0, 1.0];
+}
+
+//extensions that change how a file must be read, anything else can be ignored safely
+const SUPPORTED_EXTENSIONS: [&str; 4] = [
+    "KHR_lights_punctual",
+    "KHR_materials_emissive_strength",
+    "KHR_materials_ior",
+    "KHR_materials_transmission",
+];
+
+//angular radius of the sphere standing in for a directional light, that of the sun
+const SUN_ANGULAR_RADIUS: f64 = 0.00465;
+
+//a perspective camera of the file, placed in the world
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GltfCamera {
+    pub look_from: Vec3,
+    pub look_at: Vec3,
+    pub vup: Vec3,
+    //degrees
+    pub vfov: f64,
+    pub aspect_ratio: Option<f64>,
+}
+
+//a .gltf or .glb file with its buffers loaded
+pub struct GltfDocument {
+    document: Document,
+    buffers: Vec<Vec<u8>>,
+    //external images are relative to this
+    base_dir: PathBuf,
+    file: String,
+}
+
+struct Instance<'a> {
+    node: &'a Node,
+    world: Mat4,
+}
+
+impl GltfDocument {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ImportError> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).map_err(|e| ImportError::Io(path.display().to_string(), e))?;
+        let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+        return GltfDocument::parse(&bytes, &path.display().to_string(), base_dir);
+    }
+
+    //a binary .glb or the json of a .gltf. file is only used in error messages,
+    //external buffers and images are read from base_dir
+    pub fn parse(bytes: &[u8], file: &str, base_dir: &Path) -> Result<Self, ImportError> {
+        let invalid = |message: String| ImportError::Invalid(format!("{}: {}", file, message));
+
+        let (json, binary) = if bytes.starts_with(b"glTF") {
+            split_glb(bytes).map_err(invalid)?
+        } else {
+            (bytes, None)
+        };
+        let document: Document = serde_json::from_slice(json).map_err(|e| invalid(format!("invalid glTF: {}", e)))?;
+
+        for extension in &document.extensions_required {
+            if !SUPPORTED_EXTENSIONS.contains(&extension.as_str()) {
+                return Err(invalid(format!("requires the unsupported extension {}", extension)));
+            }
+        }
+
+        let mut buffers = Vec::new();
+        for (i, buffer) in document.buffers.iter().enumerate() {
+            let data = match (&buffer.uri, i, binary) {
+                (Some(uri), _, _) => read_uri(uri, base_dir)?,
+                (None, 0, Some(binary)) => binary.to_vec(),
+                (None, _, _) => return Err(invalid(format!("buffer {} has no uri and is not the glb binary chunk", i))),
+            };
+            if data.len() < buffer.byte_length {
+                return Err(invalid(format!("buffer {} holds {} bytes, {} expected", i, data.len(), buffer.byte_length)));
+            }
+            buffers.push(data);
+        }
+
+        return Ok(Self {
+            document,
+            buffers,
+            base_dir: base_dir.to_path_buf(),
+            file: file.to_string(),
+        });
+    }
+
+    fn invalid(&self, message: String) -> ImportError {
+        return ImportError::Invalid(format!("{}: {}", self.file, message));
+    }
+
+    //every node of the default scene with its world transform, parents before children
+    fn instances(&self) -> Result<Vec<Instance<'_>>, ImportError> {
+        let document = &self.document;
+        let roots: Vec<usize> = match document.scene.or(if document.scenes.is_empty() { None } else { Some(0) }) {
+            Some(scene) => match document.scenes.get(scene) {
+                Some(scene) => scene.nodes.clone(),
+                None => return Err(self.invalid(format!("scene {} does not exist", scene))),
+            },
+            //no scenes at all, every node that is nobody's child is a root
+            None => {
+                let children: Vec<usize> = document.nodes.iter().flat_map(|n| n.children.iter().copied()).collect();
+                (0..document.nodes.len()).filter(|i| !children.contains(i)).collect()
+            }
+        };
+
+        let mut instances = Vec::new();
+        let mut stack: Vec<(usize, Mat4, usize)> = roots.iter().rev().map(|&root| (root, Mat4::identity(), 0)).collect();
+        while let Some((index, parent, depth)) = stack.pop() {
+            let node = match document.nodes.get(index) {
+                Some(node) => node,
+                None => return Err(self.invalid(format!("node {} does not exist", index))),
+            };
+            //a valid hierarchy is a forest, deeper than the node count means a cycle
+            if depth > document.nodes.len() {
+                return Err(self.invalid("the node hierarchy contains a cycle".to_string()));
+            }
+
+            let world = parent * local_transform(node);
+            for &child in node.children.iter().rev() {
+                stack.push((child, world, depth + 1));
+            }
+            instances.push(Instance { node, world });
+        }
+        return Ok(instances);
+    }
+
+    //the perspective cameras in the order their nodes are visited, orthographic ones are skipped
+    pub fn cameras(&self) -> Result<Vec<GltfCamera>, ImportError> {
+        let mut cameras = Vec::new();
+        for instance in self.instances()? {
+            let index = match instance.node.camera {
+                Some(index) => index,
+                None => continue,
+            };
+            let camera = match self.document.cameras.get(index) {
+                Some(camera) => camera,
+                None => return Err(self.invalid(format!("camera {} does not exist", index))),
+            };
+            let perspective = match (&camera.perspective, camera.kind.as_str()) {
+                (Some(perspective), "perspective") => perspective,
+                _ => continue,
+            };
+
+            //cameras look down their -z axis with +y up
+            let look_from = instance.world.transform_point(Vec3::new(0.0, 0.0, 0.0));
+            let forward = instance.world.transform_vector(Vec3::new(0.0, 0.0, -1.0)).unit_vector();
+            cameras.push(GltfCamera {
+                look_from,
+                look_at: look_from + forward,
+                vup: instance.world.transform_vector(Vec3::new(0.0, 1.0, 0.0)).unit_vector(),
+                vfov: perspective.yfov.to_degrees(),
+                aspect_ratio: perspective.aspect_ratio,
+            });
+        }
+        return Ok(cameras);
+    }
+
+    //a mesh per primitive with the transform baked in, and a small emissive sphere per punctual light.
+    //light_radius defaults to a 200th of the size of the geometry
+    pub fn build(&self, images: &mut ImageCache, light_radius: Option<f64>) -> Result<Vec<Box<dyn Renderable>>, ImportError> {
+        let instances = self.instances()?;
+        let mut textures = TextureLoader {
+            document: self,
+            images,
+            embedded: HashMap::new(),
+        };
+
+        let mut objects: Vec<Box<dyn Renderable>> = Vec::new();
+        let mut bounds: Option<Aabb> = None;
+        for instance in &instances {
+            let index = match instance.node.mesh {
+                Some(index) => index,
+                None => continue,
+            };
+            let mesh = match self.document.meshes.get(index) {
+                Some(mesh) => mesh,
+                None => return Err(self.invalid(format!("mesh {} does not exist", index))),
+            };
+
+            for (i, primitive) in mesh.primitives.iter().enumerate() {
+                let buffers = match self.primitive_buffers(primitive, &instance.world) {
+                    Ok(Some(buffers)) => buffers,
+                    Ok(None) => continue,
+                    Err(message) => return Err(self.invalid(format!("mesh {} primitive {}: {}", index, i, message))),
+                };
+                let material = self.material(primitive.material, &mut textures)?;
+                let mesh = TriangleMesh::new(buffers, material).map_err(|e| self.invalid(e))?;
+                if let Some(bbox) = mesh.bounding_box() {
+                    bounds = Some(bounds.map_or(bbox, |b| b.surrounding(&bbox)));
+                }
+                objects.push(Box::new(mesh));
+            }
+        }
+
+        let bounds = bounds.unwrap_or_else(|| Aabb::new(Vec3::new(-0.5, -0.5, -0.5), Vec3::new(0.5, 0.5, 0.5)));
+        let radius = light_radius.unwrap_or(0.005 * (bounds.max() - bounds.min()).length().max(1e-6));
+        for instance in &instances {
+            if let Some(light) = &instance.node.extensions.light {
+                objects.push(self.light(light.light, &instance.world, radius, &bounds)?);
+            }
+        }
+
+        return Ok(objects);
+    }
+
+    //point and spot lights become a sphere of the given radius with the same intensity, spot cones are
+    //ignored. directional lights become a sphere the size of the sun far beyond the geometry
+    fn light(&self, index: usize, world: &Mat4, radius: f64, bounds: &Aabb) -> Result<Box<dyn Renderable>, ImportError> {
+        let light = self
+            .document
+            .extensions
+            .lights
+            .as_ref()
+            .and_then(|lights| lights.lights.get(index))
+            .ok_or_else(|| self.invalid(format!("light {} does not exist", index)))?;
+        let color = Vec3::new(light.color[0], light.color[1], light.color[2]) * light.intensity;
+
+        let (center, radius, radiance) = match light.kind.as_str() {
+            //intensity = radiance * projected area
+            "point" | "spot" => (world.transform_point(Vec3::new(0.0, 0.0, 0.0)), radius, color / (PI * radius * radius)),
+            //illuminance = radiance * solid angle, for a small disc
+            "directional" => {
+                let towards_light = -world.transform_vector(Vec3::new(0.0, 0.0, -1.0)).unit_vector();
+                let distance = 1e4 * (bounds.max() - bounds.min()).length().max(1_f64);
+                let sin = SUN_ANGULAR_RADIUS.sin();
+                (bounds.centroid() + distance * towards_light, distance * sin, color / (PI * sin * sin))
+            }
+            other => return Err(self.invalid(format!("light {} has the unknown type '{}'", index, other))),
+        };
+        return Ok(Box::new(Sphere::new(center, radius, Box::new(DiffuseLight::new(radiance)))));
+    }
+
+    //metallic materials become ggx conductors with the base color as reflectance, transmissive ones glass and
+    //emissive ones lights. a metallic texture can't switch between metal and lambertian, only the factor decides
+    fn material(&self, index: Option<usize>, textures: &mut TextureLoader) -> Result<Box<dyn Material>, ImportError> {
+        let default = MaterialDef::default();
+        let material = match index {
+            Some(index) => self
+                .document
+                .materials
+                .get(index)
+                .ok_or_else(|| self.invalid(format!("material {} does not exist", index)))?,
+            None => &default,
+        };
+        let pbr = &material.pbr_metallic_roughness;
+
+        let strength = material.extensions.emissive_strength.as_ref().map_or(1_f64, |e| e.emissive_strength);
+        let emissive = strength * Vec3::new(material.emissive_factor[0], material.emissive_factor[1], material.emissive_factor[2]);
+        if emissive.x().max(emissive.y()).max(emissive.z()) > 0_f64 {
+            let emit = textures.factor(&material.emissive_texture, TransferFunction::Srgb, emissive)?;
+            return Ok(Box::new(DiffuseLight::textured(emit)));
+        }
+
+        if material.extensions.transmission.as_ref().is_some_and(|t| t.transmission_factor > 0_f64) {
+            let ior = material.extensions.ior.as_ref().map_or(1.5, |i| i.ior);
+            return Ok(Box::new(Dielectric::new(ior)));
+        }
+
+        let base_color = Vec3::new(pbr.base_color_factor[0], pbr.base_color_factor[1], pbr.base_color_factor[2]);
+        let albedo = textures.factor(&pbr.base_color_texture, TransferFunction::Srgb, base_color)?;
+        if pbr.metallic_factor >= 0.5 {
+            //the image is cached, so asking for it twice is cheap
+            let mut roughness = || -> Result<Box<dyn Texture>, ImportError> {
+                return Ok(match &pbr.metallic_roughness_texture {
+                    Some(texture) => {
+                        let packed = textures.texture(texture.index, TransferFunction::Linear)?;
+                        Box::new(ScaledTexture::new(Box::new(ChannelTexture::new(packed, 1)), Vec3::new(1.0, 1.0, 1.0) * pbr.roughness_factor))
+                    }
+                    None => Box::new(ConstantTexture::scalar(pbr.roughness_factor)),
+                });
+            };
+            return Ok(Box::new(Conductor::reflective(albedo, roughness()?, roughness()?)));
+        }
+        return Ok(Box::new(Lambertian::textured(albedo)));
+    }
+
+    //None for primitives that are not made of triangles, such as lines and points
+    fn primitive_buffers(&self, primitive: &Primitive, world: &Mat4) -> Result<Option<MeshBuffers>, String> {
+        if ![MODE_TRIANGLES, MODE_TRIANGLE_STRIP, MODE_TRIANGLE_FAN].contains(&primitive.mode) {
+            return Ok(None);
+        }
+
+        let position_accessor = *primitive.attributes.get("POSITION").ok_or("has no POSITION attribute")?;
+        let positions = self.read_accessor(position_accessor, "VEC3")?;
+        let count = positions.len() / 3;
+        let normals = match primitive.attributes.get("NORMAL") {
+            Some(&accessor) => Some(self.read_accessor(accessor, "VEC3")?),
+            None => None,
+        };
+        let uvs = match primitive.attributes.get("TEXCOORD_0") {
+            Some(&accessor) => Some(self.read_accessor(accessor, "VEC2")?),
+            None => None,
+        };
+        if normals.as_ref().is_some_and(|n| n.len() / 3 != count) || uvs.as_ref().is_some_and(|t| t.len() / 2 != count) {
+            return Err("attributes have different vertex counts".to_string());
+        }
+
+        let indices: Vec<usize> = match primitive.indices {
+            Some(accessor) => self.read_accessor(accessor, "SCALAR")?.iter().map(|&i| i as usize).collect(),
+            None => (0..count).collect(),
+        };
+        if let Some(&bad) = indices.iter().find(|&&i| i >= count) {
+            return Err(format!("index {} is past the {} vertices", bad, count));
+        }
+
+        //a mirroring transform turns counter clockwise faces clockwise
+        let mirrored = world.determinant3() < 0_f64;
+        let normal_matrix = world.inverse().ok_or("the node transform is singular")?.transpose();
+        let mut faces = Vec::new();
+        for [a, b, c] in triangles(&indices, primitive.mode) {
+            let corners = if mirrored { [a, c, b] } else { [a, b, c] };
+            let mut face = MeshFace::new(corners);
+            if normals.is_some() {
+                face = face.with_normals(corners);
+            }
+            if uvs.is_some() {
+                face = face.with_uvs(corners);
+            }
+            faces.push(face);
+        }
+
+        let vec3_at = |values: &[f64], i: usize| Vec3::new(values[3 * i], values[3 * i + 1], values[3 * i + 2]);
+        return Ok(Some(MeshBuffers {
+            positions: (0..count).map(|i| world.transform_point(vec3_at(&positions, i))).collect(),
+            normals: match &normals {
+                Some(normals) => (0..count).map(|i| normal_matrix.transform_vector(vec3_at(normals, i)).unit_vector()).collect(),
+                None => Vec::new(),
+            },
+            //glTF puts v = 0 at the top of an image, the renderer at the bottom
+            uvs: match &uvs {
+                Some(uvs) => (0..count).map(|i| (uvs[2 * i], 1_f64 - uvs[2 * i + 1])).collect(),
+                None => Vec::new(),
+            },
+            faces,
+        }));
+    }
+
+    //the components of every element of an accessor, flattened and converted to f64
+    fn read_accessor(&self, index: usize, expected_kind: &str) -> Result<Vec<f64>, String> {
+        let accessor = self.document.accessors.get(index).ok_or(format!("accessor {} does not exist", index))?;
+        if accessor.kind != expected_kind {
+            return Err(format!("accessor {} is a {}, expected a {}", index, accessor.kind, expected_kind));
+        }
+        if accessor.sparse.is_some() {
+            return Err(format!("accessor {} is sparse, which is not supported", index));
+        }
+
+        let components = match accessor.kind.as_str() {
+            "SCALAR" => 1,
+            "VEC2" => 2,
+            _ => 3,
+        };
+        let size = match accessor.component_type {
+            5120 | 5121 => 1,
+            5122 | 5123 => 2,
+            5125 | 5126 => 4,
+            other => return Err(format!("accessor {} has the unknown component type {}", index, other)),
+        };
+
+        //an accessor without a buffer view is all zeros
+        let view = match accessor.buffer_view {
+            Some(view) => self.document.buffer_views.get(view).ok_or(format!("buffer view {} does not exist", view))?,
+            None => {
+                let count = accessor.count.checked_mul(components).ok_or(format!("accessor {} has too many elements", index))?;
+                return Ok(vec![0_f64; count]);
+            }
+        };
+        let buffer = self.buffers.get(view.buffer).ok_or(format!("buffer {} does not exist", view.buffer))?;
+        let element = components * size;
+        let stride = view.byte_stride.unwrap_or(element);
+        if stride < element {
+            return Err(format!("accessor {} has a byte stride of {}, less than its {} byte elements", index, stride, element));
+        }
+
+        //the counts come straight from the file, so none of this may overflow
+        let past_end = || format!("accessor {} reads past the end of its buffer view", index);
+        let start = view.byte_offset.checked_add(accessor.byte_offset).ok_or_else(past_end)?;
+        let end = match accessor.count {
+            0 => Some(start),
+            count => (count - 1).checked_mul(stride).and_then(|n| n.checked_add(element)).and_then(|n| n.checked_add(start)),
+        };
+        let end = end.ok_or_else(past_end)?;
+        let view_end = view.byte_offset.checked_add(view.byte_length).ok_or_else(past_end)?;
+        if end > view_end || end > buffer.len() {
+            return Err(past_end());
+        }
+
+        let mut values = Vec::with_capacity(accessor.count * components);
+        for element in 0..accessor.count {
+            for component in 0..components {
+                let at = start + element * stride + component * size;
+                let bytes = &buffer[at..at + size];
+                values.push(component_value(bytes, accessor.component_type, accessor.normalized));
+            }
+        }
+        return Ok(values);
+    }
+}
+
+fn component_value(bytes: &[u8], component_type: u32, normalized: bool) -> f64 {
+    let (value, max) = match component_type {
+        5120 => (bytes[0] as i8 as f64, 127_f64),
+        5121 => (bytes[0] as f64, 255_f64),
+        5122 => (i16::from_le_bytes([bytes[0], bytes[1]]) as f64, 32767_f64),
+        5123 => (u16::from_le_bytes([bytes[0], bytes[1]]) as f64, 65535_f64),
+        5125 => (u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64, 1_f64),
+        _ => (f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64, 1_f64),
+    };
+    if normalized {
+        return (value / max).max(-1_f64);
+    }
+    return value;
+}
+
+//triangles of a list, strip or fan, counter clockwise like the first one
+fn triangles(indices: &[usize], mode: u32) -> Vec<[usize; 3]> {
+    return match mode {
+        MODE_TRIANGLE_STRIP => (2..indices.len())
+            .map(|i| {
+                if i % 2 == 0 {
+                    [indices[i - 2], indices[i - 1], indices[i]]
+                } else {
+                    [indices[i - 1], indices[i - 2], indices[i]]
+                }
+            })
+            .collect(),
+        MODE_TRIANGLE_FAN => (2..indices.len()).map(|i| [indices[0], indices[i - 1], indices[i]]).collect(),
+        _ => indices.chunks_exact(3).map(|c| [c[0], c[1], c[2]]).collect(),
+    };
+}
+
+fn local_transform(node: &Node) -> Mat4 {
+    if let Some(matrix) = node.matrix {
+        return Mat4::from_column_major(matrix);
+    }
+    let translation = node.translation.map_or(Vec3::new(0.0, 0.0, 0.0), |t| Vec3::new(t[0], t[1], t[2]));
+    let rotation = node.rotation.unwrap_or([0.0, 0.0, 0.0, 1.0]);
+    let scale = node.scale.map_or(Vec3::new(1.0, 1.0, 1.0), |s| Vec3::new(s[0], s[1], s[2]));
+    return Mat4::translation(translation) * Mat4::from_quaternion(rotation) * Mat4::scaling(scale);
+}
+
+//the json and binary chunks of a glb container
+fn split_glb(bytes: &[u8]) -> Result<(&[u8], Option<&[u8]>), String> {
+    let word = |at: usize| -> Result<usize, String> {
+        return match bytes.get(at..at + 4) {
+            Some(b) => Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize),
+            None => Err("the glb file is truncated".to_string()),
+        };
+    };
+
+    if word(4)? != 2 {
+        return Err(format!("glb version {} is not supported, only 2", word(4)?));
+    }
+    let length = word(8)?.min(bytes.len());
+
+    let mut json = None;
+    let mut binary = None;
+    let mut at = 12;
+    while at + 8 <= length {
+        let chunk_length = word(at)?;
+        let kind = word(at + 4)?;
+        let data = bytes.get(at + 8..at + 8 + chunk_length).ok_or("the glb file is truncated")?;
+        match kind {
+            0x4e4f534a => json = json.or(Some(data)),
+            0x004e4942 => binary = binary.or(Some(data)),
+            _ => {}
+        }
+        at += 8 + chunk_length;
+    }
+
+    return match json {
+        Some(json) => Ok((json, binary)),
+        None => Err("the glb file has no json chunk".to_string()),
+    };
+}
+
+//a data uri with base64 contents, or a path relative to base_dir
+fn read_uri(uri: &str, base_dir: &Path) -> Result<Vec<u8>, ImportError> {
+    if let Some(data) = uri.strip_prefix("data:") {
+        let (header, contents) = data.split_once(',').unwrap_or((data, ""));
+        if !header.ends_with(";base64") {
+            return Err(ImportError::Invalid(format!("data uri '{}...' is not base64", &uri[..uri.len().min(40)])));
+        }
+        return decode_base64(contents).map_err(ImportError::Invalid);
+    }
+
+    let path = base_dir.join(percent_decode(uri));
+    return fs::read(&path).map_err(|e| ImportError::Io(path.display().to_string(), e));
+}
+
+fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok()).and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(value)) => {
+                decoded.push(value);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    return String::from_utf8_lossy(&decoded).into_owned();
+}
+
+fn decode_base64(text: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let mut bits = 0_u32;
+    let mut count = 0;
+    for c in text.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => break,
+            b' ' | b'\n' | b'\r' | b'\t' => continue,
+            _ => return Err(format!("'{}' is not a base64 character", c as char)),
+        };
+        bits = (bits << 6) | value as u32;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            bytes.push((bits >> count) as u8);
+        }
+    }
+    return Ok(bytes);
+}
+
+//builds the textures of a document, decoding each image once per encoding
+struct TextureLoader<'a> {
+    document: &'a GltfDocument,
+    //external files
+    images: &'a mut ImageCache,
+    //images stored in buffers or data uris, by index and encoding
+    embedded: HashMap<(usize, TransferFunction), Arc<Image>>,
+}
+
+impl<'a> TextureLoader<'a> {
+    //the texture multiplied by factor, or just the factor without one
+    fn factor(&mut self, texture: &Option<TextureRef>, encoding: TransferFunction, factor: Vec3) -> Result<Box<dyn Texture>, ImportError> {
+        return match texture {
+            Some(texture) => Ok(Box::new(ScaledTexture::new(self.texture(texture.index, encoding)?, factor))),
+            None => Ok(Box::new(ConstantTexture::new(factor))),
+        };
+    }
+
+    fn texture(&mut self, index: usize, encoding: TransferFunction) -> Result<Box<dyn Texture>, ImportError> {
+        let document = self.document;
+        let texture = document
+            .document
+            .textures
+            .get(index)
+            .ok_or_else(|| document.invalid(format!("texture {} does not exist", index)))?;
+        let source = texture.source.ok_or_else(|| document.invalid(format!("texture {} has no image", index)))?;
+        let image = self.image(source, encoding)?;
+
+        let mut result = ImageTexture::new(image);
+        if let Some(sampler) = texture.sampler {
+            let sampler = document
+                .document
+                .samplers
+                .get(sampler)
+                .ok_or_else(|| document.invalid(format!("sampler {} does not exist", sampler)))?;
+            if sampler.mag_filter == Some(FILTER_NEAREST) {
+                result = result.with_filter(TextureFilter::Nearest);
+            }
+            result = result.with_address(match sampler.wrap_s {
+                WRAP_CLAMP => AddressMode::Clamp,
+                WRAP_MIRROR => AddressMode::Mirror,
+                _ => AddressMode::Wrap,
+            });
+        }
+        return Ok(Box::new(result));
+    }
+
+    fn image(&mut self, index: usize, encoding: TransferFunction) -> Result<Arc<Image>, ImportError> {
+        let document = self.document;
+        let image = document
+            .document
+            .images
+            .get(index)
+            .ok_or_else(|| document.invalid(format!("image {} does not exist", index)))?;
+
+        let bytes = match (&image.uri, image.buffer_view) {
+            (Some(uri), _) if !uri.starts_with("data:") => {
+                let path = document.base_dir.join(percent_decode(uri));
+                return self.images.load(&path, encoding).map_err(|e| ImportError::Io(path.display().to_string(), e));
+            }
+            _ if self.embedded.contains_key(&(index, encoding)) => return Ok(self.embedded[&(index, encoding)].clone()),
+            (Some(uri), _) => read_uri(uri, &document.base_dir)?,
+            (None, Some(view)) => {
+                let view = document
+                    .document
+                    .buffer_views
+                    .get(view)
+                    .ok_or_else(|| document.invalid(format!("buffer view {} does not exist", view)))?;
+                let buffer = document
+                    .buffers
+                    .get(view.buffer)
+                    .ok_or_else(|| document.invalid(format!("buffer {} does not exist", view.buffer)))?;
+                view.byte_offset
+                    .checked_add(view.byte_length)
+                    .and_then(|end| buffer.get(view.byte_offset..end))
+                    .ok_or_else(|| document.invalid(format!("image {} reads past the end of its buffer", index)))?
+                    .to_vec()
+            }
+            (None, None) => return Err(document.invalid(format!("image {} has neither a uri nor a buffer view", index))),
+        };
+
+        let mut decoded = read_image(&bytes).map_err(|e| document.invalid(format!("image {}: {}", index, e)))?;
+        decode_pixels(&mut decoded, encoding);
+        let decoded = Arc::new(decoded);
+        self.embedded.insert((index, encoding), decoded.clone());
+        return Ok(decoded);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::import::*;
+    use crate::math::Ray;
+    use crate::math::Vec3;
+    use crate::render::*;
+
+    use std::path::Path;
+
+    const EPS: f64 = 1e-6;
+
+    //a triangle (0 0 0) (1 0 0) (0 1 0) as floats followed by its u16 indices
+    const BUFFER: &str = "AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAAAAABAAIAAAA=";
+
+    fn document(buffer_uri: &str) -> String {
+        return r#"{
+  "asset": { "version": "2.0" },
+  "scene": 0,
+  "scenes": [{ "nodes": [0, 3, 4] }],
+  "nodes": [
+    { "translation": [0, 0, -5], "scale": [2, 2, 2], "children": [1, 2] },
+    { "mesh": 0, "rotation": [0, 1, 0, 0] },
+    { "camera": 0, "translation": [0, 0, 5] },
+    { "translation": [0, 3, 0], "extensions": { "KHR_lights_punctual": { "light": 0 } } },
+    { "mesh": 1, "translation": [0, 0, -10], "scale": [-1, 1, 1] }
+  ],
+  "meshes": [
+    { "primitives": [{ "attributes": { "POSITION": 0 }, "indices": 1, "material": 0 }] },
+    { "primitives": [{ "attributes": { "POSITION": 0 }, "material": 1 }, { "attributes": { "POSITION": 0 }, "mode": 1 }] }
+  ],
+  "materials": [
+    { "pbrMetallicRoughness": { "baseColorFactor": [0.5, 0.2, 0.2, 1], "metallicFactor": 0 } },
+    { "pbrMetallicRoughness": { "roughnessFactor": 0.1 } }
+  ],
+  "accessors": [
+    { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3" },
+    { "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" }
+  ],
+  "bufferViews": [
+    { "buffer": 0, "byteLength": 36 },
+    { "buffer": 0, "byteOffset": 36, "byteLength": 6 }
+  ],
+  "buffers": [{ BUFFER_URI "byteLength": 44 }],
+  "cameras": [{ "type": "perspective", "perspective": { "yfov": 0.7853981634, "aspectRatio": 1.5, "znear": 0.1 } }],
+  "extensions": { "KHR_lights_punctual": { "lights": [{ "type": "point", "intensity": 10 }] } }
+}"#
+        .replace("BUFFER_URI", buffer_uri);
+    }
+
+    fn embedded() -> String {
+        return document(&format!(r#""uri": "data:application/octet-stream;base64,{}","#, BUFFER));
+    }
+
+    //the same document as a glb with the buffer in its binary chunk
+    fn glb() -> Vec<u8> {
+        let mut json = document("").into_bytes();
+        while !json.len().is_multiple_of(4) {
+            json.push(b' ');
+        }
+        let mut binary = Vec::new();
+        for value in [0_f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0].iter() {
+            binary.extend_from_slice(&value.to_le_bytes());
+        }
+        for index in [0_u16, 1, 2, 0].iter() {
+            binary.extend_from_slice(&index.to_le_bytes());
+        }
+
+        let mut glb = Vec::new();
+        glb.extend_from_slice(b"glTF");
+        glb.extend_from_slice(&2_u32.to_le_bytes());
+        glb.extend_from_slice(&((12 + 8 + json.len() + 8 + binary.len()) as u32).to_le_bytes());
+        glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        glb.extend_from_slice(&0x4e4f534a_u32.to_le_bytes());
+        glb.extend_from_slice(&json);
+        glb.extend_from_slice(&(binary.len() as u32).to_le_bytes());
+        glb.extend_from_slice(&0x004e4942_u32.to_le_bytes());
+        glb.extend_from_slice(&binary);
+        return glb;
+    }
+
+    fn check_world(objects: Vec<Box<dyn Renderable>>) {
+        //two triangle meshes, the line primitive is skipped, and the light
+        assert_eq!(objects.len(), 3);
+        let world = Bvh::new(objects);
+
+        //turned around y, scaled by 2 and moved back: (0 0 -5) (-2 0 -5) (0 2 -5) facing -z
+        let hit = world.hit(&Ray::new(Vec3::new(-0.5, 0.5, 0.0), Vec3::new(0.0, 0.0, -1.0)), 0.0, f64::MAX).unwrap();
+        assert!((hit.t - 5.0).abs() < EPS);
+        assert!((hit.normal - Vec3::new(0.0, 0.0, -1.0)).length() < EPS);
+        assert!(world.hit(&Ray::new(Vec3::new(0.5, 0.5, 0.0), Vec3::new(0.0, 0.0, -1.0)), 0.0, 4.9).is_none());
+
+        //mirrored in x, the winding is flipped back so it still faces +z
+        let hit = world.hit(&Ray::new(Vec3::new(-0.2, 0.2, -7.0), Vec3::new(0.0, 0.0, -1.0)), 0.0, f64::MAX).unwrap();
+        assert!((hit.t - 3.0).abs() < EPS);
+        assert!((hit.normal - Vec3::new(0.0, 0.0, 1.0)).length() < EPS);
+        assert!(!hit.material.is_emissive());
+
+        //the point light is the only emitter
+        let hit = world.hit(&Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)), 0.0, f64::MAX).unwrap();
+        assert!(hit.material.is_emissive() && (hit.t - 3.0).abs() < 0.1);
+        assert_eq!(LightList::new(&world).len(), 1);
+    }
+
+    #[test]
+    fn test_node_hierarchy() {
+        let document = GltfDocument::parse(embedded().as_bytes(), "test.gltf", Path::new("")).unwrap();
+        check_world(document.build(&mut ImageCache::new(), None).unwrap());
+    }
+
+    #[test]
+    fn test_camera() {
+        let document = GltfDocument::parse(embedded().as_bytes(), "test.gltf", Path::new("")).unwrap();
+        let cameras = document.cameras().unwrap();
+        assert_eq!(cameras.len(), 1);
+        let camera = cameras[0];
+        //the parent scale moves the camera but does not stretch the view
+        assert!((camera.look_from - Vec3::new(0.0, 0.0, 5.0)).length() < EPS);
+        assert!((camera.look_at - Vec3::new(0.0, 0.0, 4.0)).length() < EPS);
+        assert!((camera.vup - Vec3::new(0.0, 1.0, 0.0)).length() < EPS);
+        assert!((camera.vfov - 45.0).abs() < EPS);
+        assert_eq!(camera.aspect_ratio, Some(1.5));
+    }
+
+    #[test]
+    fn test_glb() {
+        let document = GltfDocument::parse(&glb(), "test.glb", Path::new("")).unwrap();
+        check_world(document.build(&mut ImageCache::new(), Some(0.1)).unwrap());
+
+        let mut truncated = glb();
+        truncated.truncate(30);
+        let message = GltfDocument::parse(&truncated, "test.glb", Path::new("")).err().unwrap().to_string();
+        assert!(message.contains("test.glb") && message.contains("truncated"), "{}", message);
+    }
+
+    #[test]
+    fn test_errors() {
+        let parse = |json: String| match GltfDocument::parse(json.as_bytes(), "bad.gltf", Path::new("")) {
+            Ok(document) => document.build(&mut ImageCache::new(), None).err().unwrap().to_string(),
+            Err(e) => e.to_string(),
+        };
+
+        let message = parse(embedded().replace(r#""scene": 0,"#, r#""extensionsRequired": ["KHR_draco_mesh_compression"],"#));
+        assert!(message.contains("bad.gltf") && message.contains("KHR_draco_mesh_compression"), "{}", message);
+
+        let message = parse(embedded().replace(r#""count": 3, "type": "VEC3""#, r#""count": 4, "type": "VEC3""#));
+        assert!(message.contains("mesh 0 primitive 0") && message.contains("past the end"), "{}", message);
+
+        let message = parse(embedded().replace(r#""count": 3, "type": "VEC3""#, r#""count": 18446744073709551615, "type": "VEC3""#));
+        assert!(message.contains("mesh 0 primitive 0") && message.contains("past the end"), "{}", message);
+
+        let message = parse(embedded().replace(r#""byteLength": 36 }"#, r#""byteLength": 36, "byteStride": 8 }"#));
+        assert!(message.contains("byte stride of 8"), "{}", message);
+
+        let message = parse(embedded().replace(r#""material": 0"#, r#""material": 7"#));
+        assert!(message.contains("material 7"), "{}", message);
+
+        let textured = embedded()
+            .replace(r#""metallicFactor": 0 }"#, r#""metallicFactor": 0, "baseColorTexture": { "index": 0 } }"#)
+            .replace(r#""byteLength": 6 }"#, r#""byteLength": 6 }, { "buffer": 3, "byteLength": 4 }"#)
+            .replace(r#""scene": 0,"#, r#""scene": 0, "textures": [{ "source": 0 }], "images": [{ "bufferView": 2, "mimeType": "image/png" }],"#);
+        let message = parse(textured);
+        assert!(message.contains("buffer 3 does not exist"), "{}", message);
+
+        let message = parse(embedded().replace("base64,", "base64,!"));
+        assert!(message.contains("base64"), "{}", message);
+
+        let message = parse("{ \"nodes\": [ { \"mesh\": ".to_string());
+        assert!(message.contains("invalid glTF"), "{}", message);
+
+        let message = GltfDocument::load("nothing.gltf").err().unwrap().to_string();
+        assert!(message.contains("nothing.gltf"), "{}", message);
+    }
+}
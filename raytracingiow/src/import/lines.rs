@@ -0,0 +1,105 @@
+use crate::import::error::ImportError;
+use crate::math::Vec3;
+
+//a statement of an obj or mtl file, comments and line continuations already dealt with
+pub struct Statement<'a> {
+    //1-based line the statement starts on
+    pub line: usize,
+    pub keyword: &'a str,
+    pub args: Vec<&'a str>,
+    //everything after the keyword, for file names that contain spaces
+    pub rest: &'a str,
+}
+
+//joins lines ending in a backslash and drops comments and blank lines
+pub fn logical_lines(source: &str) -> Vec<(usize, String)> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    let mut start = 0;
+
+    for (i, raw) in source.lines().enumerate() {
+        let content = match raw.find('#') {
+            Some(comment) => &raw[..comment],
+            None => raw,
+        };
+        if current.is_empty() {
+            start = i + 1;
+        }
+
+        let trimmed = content.trim_end();
+        if let Some(joined) = trimmed.strip_suffix('\\') {
+            current.push_str(joined);
+            current.push(' ');
+            continue;
+        }
+
+        current.push_str(trimmed);
+        if !current.trim().is_empty() {
+            lines.push((start, current.clone()));
+        }
+        current.clear();
+    }
+
+    if !current.trim().is_empty() {
+        lines.push((start, current));
+    }
+    return lines;
+}
+
+pub fn statement(line: usize, content: &str) -> Statement<'_> {
+    let content = content.trim();
+    let (keyword, rest) = match content.find(char::is_whitespace) {
+        Some(split) => (&content[..split], content[split..].trim()),
+        None => (content, ""),
+    };
+    return Statement {
+        line,
+        keyword,
+        args: rest.split_whitespace().collect(),
+        rest,
+    };
+}
+
+impl<'a> Statement<'a> {
+    pub fn error(&self, file: &str, message: String) -> ImportError {
+        return ImportError::Syntax {
+            file: file.to_string(),
+            line: self.line,
+            message,
+        };
+    }
+
+    //every argument as a number, between min and max of them
+    pub fn numbers(&self, min: usize, max: usize) -> Result<Vec<f64>, String> {
+        if self.args.len() < min || self.args.len() > max {
+            let expected = if min == max { format!("{}", min) } else { format!("{} to {}", min, max) };
+            return Err(format!("'{}' takes {} numbers, found {}", self.keyword, expected, self.args.len()));
+        }
+
+        return self
+            .args
+            .iter()
+            .map(|arg| {
+                arg.parse::<f64>()
+                    .map_err(|_| format!("expected a number after '{}', found '{}'", self.keyword, arg))
+            })
+            .collect();
+    }
+
+    pub fn number(&self) -> Result<f64, String> {
+        return Ok(self.numbers(1, 1)?[0]);
+    }
+
+    //an rgb color, a single value means grey
+    pub fn color(&self) -> Result<Vec3, String> {
+        if self.args.first() == Some(&"spectral") || self.args.first() == Some(&"xyz") {
+            return Err(format!("only rgb values are supported for '{}'", self.keyword));
+        }
+        let values = self.numbers(1, 3)?;
+        return match values.len() {
+            1 => Ok(Vec3::new(values[0], values[0], values[0])),
+            3 => Ok(Vec3::new(values[0], values[1], values[2])),
+            _ => Err(format!("'{}' takes 1 or 3 numbers, found 2", self.keyword)),
+        };
+    }
+}
@@ -0,0 +1,12 @@
+mod error;
+mod gltf;
+mod lines;
+mod mtl;
+mod obj;
+mod voxel;
+
+pub use self::error::*;
+pub use self::gltf::*;
+pub use self::mtl::*;
+pub use self::obj::*;
+pub use self::voxel::*;
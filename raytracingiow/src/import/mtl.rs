@@ -0,0 +1,223 @@
+use crate::import::error::ImportError;
+use crate::import::lines::logical_lines;
+use crate::import::lines::statement;
+use crate::math::Vec3;
+use crate::output::TransferFunction;
+use crate::render::*;
+
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+//a material of an mtl file with the values the renderer understands
+#[derive(Clone, Debug, PartialEq)]
+pub struct MtlMaterial {
+    pub name: String,
+    //Kd
+    pub diffuse: Vec3,
+    //Ks
+    pub specular: Vec3,
+    //Ke
+    pub emission: Vec3,
+    //Ns, the phong exponent
+    pub shininess: f64,
+    //Ni
+    pub refraction_index: Option<f64>,
+    //d, or 1 - Tr
+    pub dissolve: f64,
+    pub illum: u32,
+    //map_Kd, already joined to the directory of the mtl file
+    pub diffuse_map: Option<PathBuf>,
+}
+
+impl MtlMaterial {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            diffuse: Vec3::new(0.8, 0.8, 0.8),
+            specular: Vec3::new(0.0, 0.0, 0.0),
+            emission: Vec3::new(0.0, 0.0, 0.0),
+            shininess: 0.0,
+            refraction_index: None,
+            dissolve: 1.0,
+            illum: 2,
+            diffuse_map: None,
+        }
+    }
+
+    //emissive materials become lights, see through ones glass, ones with a specular color brighter than
+    //their diffuse color (or illum 3) a conductor with a roughness from the phong exponent, and the rest lambertian
+    pub fn to_material(&self, images: &mut ImageCache) -> Result<Box<dyn Material>, ImportError> {
+        if max_component(self.emission) > 0_f64 {
+            return Ok(Box::new(DiffuseLight::new(self.emission)));
+        }
+
+        if self.dissolve < 1_f64 || [4, 6, 7, 9].contains(&self.illum) {
+            return Ok(Box::new(Dielectric::new(self.refraction_index.unwrap_or(1.5))));
+        }
+
+        if self.illum == 3 || max_component(self.specular) > max_component(self.diffuse) {
+            //the roughness of a microfacet lobe as wide as the phong one
+            let roughness = (2_f64 / (self.shininess.max(0_f64) + 2_f64)).sqrt();
+            return Ok(Box::new(Conductor::reflective(
+                Box::new(ConstantTexture::new(self.specular)),
+                Box::new(ConstantTexture::scalar(roughness)),
+                Box::new(ConstantTexture::scalar(roughness)),
+            )));
+        }
+
+        return match &self.diffuse_map {
+            Some(path) => {
+                let image = images
+                    .load(path, TransferFunction::Srgb)
+                    .map_err(|e| ImportError::Io(path.display().to_string(), e))?;
+                Ok(Box::new(Lambertian::textured(Box::new(ImageTexture::new(image)))))
+            }
+            None => Ok(Box::new(Lambertian::new(self.diffuse))),
+        };
+    }
+}
+
+fn max_component(v: Vec3) -> f64 {
+    return v.x().max(v.y()).max(v.z());
+}
+
+pub fn load_mtl(path: impl AsRef<Path>) -> Result<Vec<MtlMaterial>, ImportError> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path).map_err(|e| ImportError::Io(path.display().to_string(), e))?;
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+    return parse_mtl(&source, &path.display().to_string(), base_dir);
+}
+
+//file is only used in error messages, texture paths start at base_dir
+pub fn parse_mtl(source: &str, file: &str, base_dir: &Path) -> Result<Vec<MtlMaterial>, ImportError> {
+    let mut materials: Vec<MtlMaterial> = Vec::new();
+
+    for (line, content) in logical_lines(source) {
+        let statement = statement(line, &content);
+        let keyword = statement.keyword;
+
+        if keyword == "newmtl" {
+            if statement.rest.is_empty() {
+                return Err(statement.error(file, "'newmtl' needs a name".to_string()));
+            }
+            materials.push(MtlMaterial::new(statement.rest));
+            continue;
+        }
+
+        let material = match materials.last_mut() {
+            Some(material) => material,
+            None => return Err(statement.error(file, format!("'{}' before the first 'newmtl'", keyword))),
+        };
+
+        let result = match keyword {
+            "Kd" => statement.color().map(|c| material.diffuse = c),
+            "Ks" => statement.color().map(|c| material.specular = c),
+            "Ke" => statement.color().map(|c| material.emission = c),
+            "Ns" => statement.number().map(|n| material.shininess = n),
+            "Ni" => statement.number().map(|n| material.refraction_index = Some(n)),
+            "d" if statement.args.first() == Some(&"-halo") => Err("'d -halo' is not supported".to_string()),
+            "d" => statement.number().map(|n| material.dissolve = n),
+            "Tr" => statement.number().map(|n| material.dissolve = 1_f64 - n),
+            "illum" => statement.number().map(|n| material.illum = n.max(0_f64) as u32),
+            "map_Kd" => texture_path(&statement.args).map(|name| material.diffuse_map = Some(base_dir.join(name))),
+            //ambient color, other texture maps and extensions the renderer has no use for
+            _ => Ok(()),
+        };
+        result.map_err(|message| statement.error(file, message))?;
+    }
+
+    return Ok(materials);
+}
+
+//the file name at the end of a texture statement, after any options
+fn texture_path(args: &[&str]) -> Result<String, String> {
+    let mut i = 0;
+    while i < args.len() && args[i].starts_with('-') {
+        //options take up to three numbers, or a single word
+        let option = args[i];
+        i += 1;
+        match option {
+            "-o" | "-s" | "-t" => {
+                let mut taken = 0;
+                while taken < 3 && i < args.len() && args[i].parse::<f64>().is_ok() {
+                    i += 1;
+                    taken += 1;
+                }
+            }
+            "-mm" => i += 2,
+            _ => i += 1,
+        }
+    }
+
+    if i >= args.len() {
+        return Err("texture statement without a file name".to_string());
+    }
+    return Ok(args[i..].join(" "));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::import::*;
+    use crate::math::Vec3;
+
+    use std::path::Path;
+
+    const MTL: &str = "
+# exported materials
+newmtl red
+Kd 0.8 0.1 0.1
+Ks 0.1 0.1 0.1
+Ns 10
+
+newmtl gold
+Kd 0.1 0.1 0.1
+Ks 1.0 0.8 0.3
+Ns 200
+
+newmtl glass
+Ni 1.45
+d 0.2
+
+newmtl lamp
+Ke 4 4 4
+
+newmtl brick wall
+map_Kd -s 2 2 1 -clamp on textures/brick.png
+";
+
+    #[test]
+    fn test_parse() {
+        let materials = parse_mtl(MTL, "test.mtl", Path::new("assets")).unwrap();
+        assert_eq!(materials.len(), 5);
+        assert_eq!(materials[0].name, "red");
+        assert_eq!(materials[0].diffuse, Vec3::new(0.8, 0.1, 0.1));
+        assert_eq!(materials[1].shininess, 200.0);
+        assert_eq!(materials[2].refraction_index, Some(1.45));
+        assert_eq!(materials[2].dissolve, 0.2);
+        assert_eq!(materials[3].emission, Vec3::new(4.0, 4.0, 4.0));
+        assert_eq!(materials[4].name, "brick wall");
+        assert_eq!(materials[4].diffuse_map, Some(Path::new("assets").join("textures/brick.png")));
+
+        //everything but the missing texture builds
+        let mut images = crate::render::ImageCache::new();
+        for material in &materials[..4] {
+            let built = material.to_material(&mut images).unwrap();
+            assert_eq!(built.is_emissive(), material.name == "lamp");
+        }
+        let message = materials[4].to_material(&mut images).err().unwrap().to_string();
+        assert!(message.contains("brick.png"), "{}", message);
+    }
+
+    #[test]
+    fn test_errors_name_the_line() {
+        let message = parse_mtl("newmtl a\nKd 0.5 oops 0.5\n", "bad.mtl", Path::new("")).err().unwrap().to_string();
+        assert!(message.contains("bad.mtl line 2") && message.contains("oops"), "{}", message);
+
+        let message = parse_mtl("Kd 1 1 1\n", "bad.mtl", Path::new("")).err().unwrap().to_string();
+        assert!(message.contains("line 1") && message.contains("newmtl"), "{}", message);
+
+        let message = parse_mtl("newmtl a\nKd 1 1\n", "bad.mtl", Path::new("")).err().unwrap().to_string();
+        assert!(message.contains("1 or 3"), "{}", message);
+    }
+}
@@ -0,0 +1,412 @@
+use crate::import::error::ImportError;
+use crate::import::lines::logical_lines;
+use crate::import::lines::statement;
+use crate::import::lines::Statement;
+use crate::import::mtl::load_mtl;
+use crate::import::mtl::MtlMaterial;
+use crate::math::Vec3;
+use crate::render::*;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+//the faces of one group or object that share a material
+pub struct ObjMesh {
+    //the last 'o' or 'g' before the faces, empty if there was none
+    pub name: String,
+    //the 'usemtl' in effect, None for the default material
+    pub material: Option<String>,
+    pub buffers: MeshBuffers,
+}
+
+//a wavefront obj file split into meshes, with the materials of its mtl libraries
+pub struct ObjModel {
+    pub meshes: Vec<ObjMesh>,
+    //mtl files named by 'mtllib', relative to the obj file
+    pub libraries: Vec<String>,
+    pub materials: Vec<MtlMaterial>,
+}
+
+//maps the file wide indices of a mesh's vertices to its own buffers
+#[derive(Default)]
+struct Remap {
+    positions: HashMap<usize, usize>,
+    normals: HashMap<usize, usize>,
+    uvs: HashMap<usize, usize>,
+}
+
+fn remap<T: Copy>(global: usize, values: &[T], map: &mut HashMap<usize, usize>, local: &mut Vec<T>) -> usize {
+    return *map.entry(global).or_insert_with(|| {
+        local.push(values[global]);
+        local.len() - 1
+    });
+}
+
+//one corner of a face, as 0-based indices into the file wide lists
+struct Corner {
+    position: usize,
+    uv: Option<usize>,
+    normal: Option<usize>,
+}
+
+impl ObjModel {
+    //also reads every mtl library the file uses
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ImportError> {
+        let path = path.as_ref();
+        let mut model = ObjModel::load_geometry(path)?;
+
+        let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+        for library in &model.libraries {
+            model.materials.extend(load_mtl(base_dir.join(library))?);
+        }
+        return Ok(model);
+    }
+
+    //only the obj file itself, for when the materials are replaced anyway
+    pub fn load_geometry(path: impl AsRef<Path>) -> Result<Self, ImportError> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path).map_err(|e| ImportError::Io(path.display().to_string(), e))?;
+        return ObjModel::parse(&source, &path.display().to_string());
+    }
+
+    //file is only used in error messages, mtl libraries are not read
+    pub fn parse(source: &str, file: &str) -> Result<Self, ImportError> {
+        let mut positions: Vec<Vec3> = Vec::new();
+        let mut normals: Vec<Vec3> = Vec::new();
+        let mut uvs: Vec<(f64, f64)> = Vec::new();
+
+        let mut meshes: Vec<ObjMesh> = Vec::new();
+        let mut remaps: Vec<Remap> = Vec::new();
+        let mut mesh_of: HashMap<(String, Option<String>), usize> = HashMap::new();
+        let mut libraries: Vec<String> = Vec::new();
+        let mut name = String::new();
+        let mut material: Option<String> = None;
+
+        for (line, content) in logical_lines(source) {
+            let statement = statement(line, &content);
+            let error = |message: String| statement.error(file, message);
+
+            match statement.keyword {
+                "v" => {
+                    //a w coordinate or a vertex color may follow
+                    let values = statement.numbers(3, 7).map_err(error)?;
+                    positions.push(Vec3::new(values[0], values[1], values[2]));
+                }
+                "vn" => {
+                    let values = statement.numbers(3, 3).map_err(error)?;
+                    normals.push(Vec3::new(values[0], values[1], values[2]));
+                }
+                "vt" => {
+                    let values = statement.numbers(1, 3).map_err(error)?;
+                    uvs.push((values[0], values.get(1).copied().unwrap_or(0_f64)));
+                }
+                "f" => {
+                    let corners = statement
+                        .args
+                        .iter()
+                        .map(|arg| parse_corner(arg, positions.len(), uvs.len(), normals.len()))
+                        .collect::<Result<Vec<Corner>, String>>()
+                        .map_err(error)?;
+                    if corners.len() < 3 {
+                        return Err(error(format!("a face needs at least 3 vertices, found {}", corners.len())));
+                    }
+
+                    let key = (name.clone(), material.clone());
+                    let index = *mesh_of.entry(key).or_insert_with(|| {
+                        meshes.push(ObjMesh {
+                            name: name.clone(),
+                            material: material.clone(),
+                            buffers: MeshBuffers::default(),
+                        });
+                        remaps.push(Remap::default());
+                        meshes.len() - 1
+                    });
+                    let buffers = &mut meshes[index].buffers;
+                    let remap_of = &mut remaps[index];
+
+                    //a corner without a uv or normal drops them from the whole face
+                    let has_uvs = corners.iter().all(|c| c.uv.is_some());
+                    let has_normals = corners.iter().all(|c| c.normal.is_some());
+                    let points: Vec<Vec3> = corners.iter().map(|c| positions[c.position]).collect();
+
+                    for [a, b, c] in triangulate(&points) {
+                        let triangle = [&corners[a], &corners[b], &corners[c]];
+                        let mut face = MeshFace::new(triangle.map(|c| remap(c.position, &positions, &mut remap_of.positions, &mut buffers.positions)));
+                        if has_uvs {
+                            face = face.with_uvs(triangle.map(|c| remap(c.uv.unwrap(), &uvs, &mut remap_of.uvs, &mut buffers.uvs)));
+                        }
+                        if has_normals {
+                            face = face.with_normals(triangle.map(|c| remap(c.normal.unwrap(), &normals, &mut remap_of.normals, &mut buffers.normals)));
+                        }
+                        buffers.faces.push(face);
+                    }
+                }
+                "o" | "g" => name = statement.args.join(" "),
+                "usemtl" => material = Some(named(&statement).map_err(error)?),
+                "mtllib" => libraries.push(named(&statement).map_err(error)?),
+                //smoothing groups, lines, points and free form geometry
+                _ => {}
+            }
+        }
+
+        return Ok(Self {
+            meshes,
+            libraries,
+            materials: Vec::new(),
+        });
+    }
+
+    pub fn material(&self, name: &str) -> Option<&MtlMaterial> {
+        return self.materials.iter().rev().find(|material| material.name == name);
+    }
+
+    //every mesh with its own material, faces without one are light grey
+    pub fn build(mut self, images: &mut ImageCache) -> Result<Vec<TriangleMesh>, ImportError> {
+        let mut built = Vec::new();
+        for mesh in std::mem::take(&mut self.meshes) {
+            let material: Box<dyn Material> = match &mesh.material {
+                Some(name) => match self.material(name) {
+                    Some(material) => material.to_material(images)?,
+                    None => {
+                        return Err(ImportError::Invalid(format!(
+                            "material '{}' is not defined in any of the mtl libraries {:?}",
+                            name, self.libraries
+                        )))
+                    }
+                },
+                None => Box::new(Lambertian::new(MtlMaterial::new("").diffuse)),
+            };
+            built.push(TriangleMesh::new(mesh.buffers, material).map_err(ImportError::Invalid)?);
+        }
+        return Ok(built);
+    }
+}
+
+fn named(statement: &Statement) -> Result<String, String> {
+    if statement.rest.is_empty() {
+        return Err(format!("'{}' needs a name", statement.keyword));
+    }
+    return Ok(statement.rest.to_string());
+}
+
+//v, v/vt, v//vn or v/vt/vn, counting from 1 or backwards from the last vertex with negative numbers
+fn parse_corner(arg: &str, positions: usize, uvs: usize, normals: usize) -> Result<Corner, String> {
+    let parts: Vec<&str> = arg.split('/').collect();
+    if parts.len() > 3 || parts[0].is_empty() {
+        return Err(format!("'{}' is not a face vertex, expected v, v/vt, v//vn or v/vt/vn", arg));
+    }
+
+    let optional = |i: usize, count: usize, kind: &str| -> Result<Option<usize>, String> {
+        return match parts.get(i) {
+            Some(part) if !part.is_empty() => resolve_index(part, count, kind).map(Some),
+            _ => Ok(None),
+        };
+    };
+
+    return Ok(Corner {
+        position: resolve_index(parts[0], positions, "vertex")?,
+        uv: optional(1, uvs, "texture coordinate")?,
+        normal: optional(2, normals, "normal")?,
+    });
+}
+
+fn resolve_index(text: &str, count: usize, kind: &str) -> Result<usize, String> {
+    let index = text
+        .parse::<i64>()
+        .map_err(|_| format!("expected a {} index, found '{}'", kind, text))?;
+
+    let resolved = if index > 0 { index - 1 } else { count as i64 + index };
+    if index == 0 {
+        return Err(format!("{} indices start at 1, 0 is not valid", kind));
+    }
+    if resolved < 0 || resolved >= count as i64 {
+        return Err(format!("{} {} does not exist, {} have been defined so far", kind, index, count));
+    }
+    return Ok(resolved as usize);
+}
+
+//splits a polygon into triangles of indices into points, keeping its winding.
+//ear clipping in the plane of the polygon, so concave faces come out right
+fn triangulate(points: &[Vec3]) -> Vec<[usize; 3]> {
+    let n = points.len();
+    if n == 3 {
+        return vec![[0, 1, 2]];
+    }
+
+    //newell's method, robust for polygons that are not quite flat
+    let mut normal = Vec3::new(0.0, 0.0, 0.0);
+    for i in 0..n {
+        let (a, b) = (points[i], points[(i + 1) % n]);
+        normal += Vec3::new(
+            (a.y() - b.y()) * (a.z() + b.z()),
+            (a.z() - b.z()) * (a.x() + b.x()),
+            (a.x() - b.x()) * (a.y() + b.y()),
+        );
+    }
+
+    //drop the axis the polygon faces most, ordered so it stays counter clockwise in 2d
+    let kz = if normal.x().abs() > normal.y().abs() && normal.x().abs() > normal.z().abs() {
+        0
+    } else if normal.y().abs() > normal.z().abs() {
+        1
+    } else {
+        2
+    };
+    let (kx, ky) = if normal[kz] < 0_f64 { ((kz + 2) % 3, (kz + 1) % 3) } else { ((kz + 1) % 3, (kz + 2) % 3) };
+    let flat: Vec<(f64, f64)> = points.iter().map(|p| (p[kx], p[ky])).collect();
+    let turn = |a: usize, b: usize, c: usize| -> f64 {
+        let (ab, ac) = ((flat[b].0 - flat[a].0, flat[b].1 - flat[a].1), (flat[c].0 - flat[a].0, flat[c].1 - flat[a].1));
+        return ab.0 * ac.1 - ab.1 * ac.0;
+    };
+
+    let mut remaining: Vec<usize> = (0..n).collect();
+    let mut triangles = Vec::new();
+    while remaining.len() > 3 {
+        let m = remaining.len();
+        let ear = (0..m).find(|&i| {
+            let (a, b, c) = (remaining[(i + m - 1) % m], remaining[i], remaining[(i + 1) % m]);
+            if turn(a, b, c) <= 0_f64 {
+                return false;
+            }
+            return !remaining
+                .iter()
+                .filter(|&&p| p != a && p != b && p != c)
+                .any(|&p| turn(a, b, p) >= 0_f64 && turn(b, c, p) >= 0_f64 && turn(c, a, p) >= 0_f64);
+        });
+
+        match ear {
+            Some(i) => {
+                triangles.push([remaining[(i + m - 1) % m], remaining[i], remaining[(i + 1) % m]]);
+                remaining.remove(i);
+            }
+            //degenerate or self intersecting, a fan is as good as anything
+            None => break,
+        }
+    }
+
+    for i in 1..remaining.len() - 1 {
+        triangles.push([remaining[0], remaining[i], remaining[i + 1]]);
+    }
+    return triangles;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::import::*;
+    use crate::math::Ray;
+    use crate::math::Vec3;
+    use crate::render::*;
+
+    use std::fs;
+
+    const OBJ: &str = "
+# two squares, one of them an l shaped hexagon
+mtllib scene.mtl
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 0 1
+
+o first
+usemtl red
+f 1/1/1 2/2/1 3/3/1 4/4/1
+
+g second
+v 2 0 0
+v 4 0 0
+v 4 1 0
+v 3 1 0
+v 3 2 0
+v 2 2 0
+f -6 -5 -4 -3 \\
+  -2 -1
+";
+
+    #[test]
+    fn test_parse() {
+        let model = ObjModel::parse(OBJ, "test.obj").unwrap();
+        assert_eq!(model.libraries, vec!["scene.mtl".to_string()]);
+        assert_eq!(model.meshes.len(), 2);
+
+        let first = &model.meshes[0];
+        assert_eq!(first.name, "first");
+        assert_eq!(first.material, Some("red".to_string()));
+        assert_eq!(first.buffers.faces.len(), 2);
+        assert_eq!(first.buffers.positions.len(), 4);
+        assert_eq!(first.buffers.faces[0], MeshFace::new([0, 1, 2]).with_uvs([0, 1, 2]).with_normals([0, 0, 0]));
+
+        //the hexagon only uses its own vertices and keeps the material
+        let second = &model.meshes[1];
+        assert_eq!(second.name, "second");
+        assert_eq!(second.material, Some("red".to_string()));
+        assert_eq!(second.buffers.positions.len(), 6);
+        assert_eq!(second.buffers.faces.len(), 4);
+        assert!(second.buffers.faces.iter().all(|f| f.uvs.is_none() && f.normals.is_none()));
+    }
+
+    #[test]
+    fn test_concave_face_is_covered_exactly() {
+        let mut model = ObjModel::parse(OBJ, "test.obj").unwrap();
+        let hexagon = model.meshes.remove(1).buffers;
+        for face in &hexagon.faces {
+            let [a, b, c] = face.positions.map(|i| hexagon.positions[i]);
+            assert!((b - a).cross(c - a).z() > 0.0, "winding flipped");
+        }
+
+        let mesh = TriangleMesh::new(hexagon, Box::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)))).unwrap();
+        let down = |x: f64, y: f64| Ray::new(Vec3::new(x, y, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(mesh.hit(&down(2.5, 1.5), 0.0, f64::MAX).is_some());
+        assert!(mesh.hit(&down(3.5, 0.5), 0.0, f64::MAX).is_some());
+        //the notch of the l
+        assert!(mesh.hit(&down(3.5, 1.5), 0.0, f64::MAX).is_none());
+    }
+
+    #[test]
+    fn test_unknown_material() {
+        let message = ObjModel::parse(OBJ, "test.obj").unwrap().build(&mut ImageCache::new()).err().unwrap().to_string();
+        assert!(message.contains("'red'") && message.contains("scene.mtl"), "{}", message);
+    }
+
+    #[test]
+    fn test_errors_name_the_line() {
+        let cases = [
+            ("v 1 2\n", "line 1", "3 to 7"),
+            ("v 1 2 3\nvn 0 x 1\n", "line 2", "'x'"),
+            ("v 1 2 3\nv 1 2 4\nf 1 2\n", "line 3", "at least 3"),
+            ("v 1 2 3\nv 1 2 4\nv 0 0 0\nf 1 2 4\n", "line 4", "vertex 4 does not exist"),
+            ("v 1 2 3\nv 1 2 4\nv 0 0 0\nf 1 -2 -4\n", "line 4", "vertex -4"),
+            ("v 1 2 3\nv 1 2 4\nv 0 0 0\nf 0 1 2\n", "line 4", "start at 1"),
+            ("v 1 2 3\nv 1 2 4\nv 0 0 0\nf 1/1 2/1 3/1\n", "line 4", "texture coordinate 1"),
+            ("v 1 2 3\nv 1 2 4\nv 0 0 0\nf 1/2/3/4 2 3\n", "line 4", "1/2/3/4"),
+            ("usemtl\n", "line 1", "needs a name"),
+        ];
+        for (source, line, text) in &cases {
+            let message = ObjModel::parse(source, "bad.obj").err().unwrap().to_string();
+            assert!(message.contains(&format!("bad.obj {}", line)) && message.contains(text), "{}", message);
+        }
+    }
+
+    #[test]
+    fn test_load_with_materials() {
+        let dir = std::env::temp_dir().join(format!("raytracing_iow_obj_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("test.obj"), OBJ).unwrap();
+        fs::write(dir.join("scene.mtl"), "newmtl red\nKd 0.8 0.1 0.1\n").unwrap();
+
+        let model = ObjModel::load(dir.join("test.obj")).unwrap();
+        assert_eq!(model.material("red").unwrap().diffuse, Vec3::new(0.8, 0.1, 0.1));
+        let meshes = model.build(&mut ImageCache::new()).unwrap();
+        assert_eq!(meshes.iter().map(|m| m.len()).sum::<usize>(), 6);
+
+        fs::remove_file(dir.join("scene.mtl")).unwrap();
+        let message = ObjModel::load(dir.join("test.obj")).err().unwrap().to_string();
+        assert!(message.contains("scene.mtl"), "{}", message);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
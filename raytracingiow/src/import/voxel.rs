@@ -0,0 +1,114 @@
+use crate::import::error::ImportError;
+use crate::render::DensityGrid;
+
+use std::convert::TryInto;
+use std::fs;
+use std::path::Path;
+
+//a density grid file is the magic, the resolution along x, y and z as u32 and then every voxel as
+//an f32, all little endian with x varying fastest, then y, then z
+pub const DENSITY_GRID_MAGIC: &[u8; 8] = b"RTVGRID1";
+
+//reads a density grid file, or a headerless raw volume of the given resolution with a u8 (scaled
+//to 0..1) or a little endian f32 per voxel, whichever the file size fits
+pub fn load_density_grid(path: impl AsRef<Path>, resolution: Option<[usize; 3]>) -> Result<DensityGrid, ImportError> {
+    let path = path.as_ref();
+    let bytes = fs::read(path).map_err(|e| ImportError::Io(path.display().to_string(), e))?;
+    return parse_density_grid(&bytes, &path.display().to_string(), resolution);
+}
+
+pub fn parse_density_grid(bytes: &[u8], file: &str, resolution: Option<[usize; 3]>) -> Result<DensityGrid, ImportError> {
+    let invalid = |message: String| ImportError::Invalid(format!("{}: {}", file, message));
+
+    if bytes.starts_with(DENSITY_GRID_MAGIC) {
+        if resolution.is_some() {
+            return Err(invalid("a density grid file has its own resolution, leave it out".to_string()));
+        }
+        let header = DENSITY_GRID_MAGIC.len() + 12;
+        if bytes.len() < header {
+            return Err(invalid("header is cut short".to_string()));
+        }
+        let size = |i: usize| u32::from_le_bytes(bytes[8 + 4 * i..12 + 4 * i].try_into().unwrap()) as usize;
+        let resolution = [size(0), size(1), size(2)];
+        let count = resolution.iter().try_fold(1_usize, |count, &n| count.checked_mul(n));
+        if count.and_then(|count| count.checked_mul(4)) != Some(bytes.len() - header) {
+            return Err(invalid(format!("{:?} voxels don't match the {} bytes after the header", resolution, bytes.len() - header)));
+        }
+        return DensityGrid::new(resolution, read_f32s(&bytes[header..])).map_err(invalid);
+    }
+
+    let resolution = match resolution {
+        Some(resolution) => resolution,
+        None => return Err(invalid("not a density grid file, a raw volume needs a resolution".to_string())),
+    };
+    let count = match resolution.iter().try_fold(1_usize, |count, &n| count.checked_mul(n)) {
+        Some(count) => count,
+        None => return Err(invalid(format!("{:?} voxels are too many", resolution))),
+    };
+    let values = if bytes.len() == count {
+        bytes.iter().map(|&b| b as f32 / 255_f32).collect()
+    } else if Some(bytes.len()) == count.checked_mul(4) {
+        read_f32s(bytes)
+    } else {
+        return Err(invalid(format!(
+            "{} bytes are neither {} u8 nor {} f32 voxels of {:?}",
+            bytes.len(),
+            count,
+            count,
+            resolution
+        )));
+    };
+    return DensityGrid::new(resolution, values).map_err(invalid);
+}
+
+fn read_f32s(bytes: &[u8]) -> Vec<f32> {
+    return bytes.chunks_exact(4).map(|b| f32::from_le_bytes(b.try_into().unwrap())).collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::import::*;
+    use crate::math::Vec3;
+
+    fn grid_file(resolution: [u32; 3], values: &[f32]) -> Vec<u8> {
+        let mut bytes = DENSITY_GRID_MAGIC.to_vec();
+        for n in &resolution {
+            bytes.extend_from_slice(&n.to_le_bytes());
+        }
+        for v in values {
+            bytes.extend_from_slice(&v.to_le_bytes());
+        }
+        return bytes;
+    }
+
+    #[test]
+    fn test_grid_file() {
+        let values: Vec<f32> = (0..24).map(|i| i as f32 * 0.5).collect();
+        let grid = parse_density_grid(&grid_file([2, 3, 4], &values), "cloud.vgrid", None).unwrap();
+        assert_eq!(grid.resolution(), [2, 3, 4]);
+        assert_eq!(grid.value(1, 2, 3), 11.5);
+        assert_eq!(grid.value(1, 0, 0), 0.5);
+
+        let message = parse_density_grid(&grid_file([2, 3, 5], &values), "cloud.vgrid", None).err().unwrap().to_string();
+        assert!(message.contains("cloud.vgrid") && message.contains("don't match"), "{}", message);
+        let message = parse_density_grid(&grid_file([2, 3, 4], &values), "cloud.vgrid", Some([2, 3, 4])).err().unwrap().to_string();
+        assert!(message.contains("own resolution"), "{}", message);
+    }
+
+    #[test]
+    fn test_raw_volumes() {
+        let grid = parse_density_grid(&[0, 51, 255, 102], "smoke.raw", Some([2, 2, 1])).unwrap();
+        assert!((grid.value(1, 0, 0) - 0.2).abs() < 1e-6);
+        assert!((grid.sample(Vec3::new(0.75, 0.25, 0.5)) - 0.2).abs() < 1e-6);
+
+        let bytes: Vec<u8> = [1.5_f32, 2.0].iter().flat_map(|v| v.to_le_bytes()).collect();
+        let grid = parse_density_grid(&bytes, "smoke.raw", Some([1, 1, 2])).unwrap();
+        assert_eq!(grid.value(0, 0, 1), 2.0);
+
+        let message = parse_density_grid(&[0, 1, 2], "smoke.raw", Some([2, 2, 1])).err().unwrap().to_string();
+        assert!(message.contains("neither"), "{}", message);
+        assert!(parse_density_grid(&[0, 1, 2, 3], "smoke.raw", None).is_err());
+        let message = parse_density_grid(&[0, 1, 2, 3], "smoke.raw", Some([usize::MAX, 2, 1])).err().unwrap().to_string();
+        assert!(message.contains("too many"), "{}", message);
+    }
+}
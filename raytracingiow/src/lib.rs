@@ -0,0 +1,8 @@
+extern crate rand;
+
+pub mod color;
+pub mod import;
+pub mod math;
+pub mod output;
+pub mod render;
+pub mod scene;
@@ -0,0 +1,144 @@
+use crate::math::Ray;
+use crate::math::Vec3;
+
+//axis aligned bounding box
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Aabb {
+    min: Vec3,
+    max: Vec3,
+}
+
+impl Aabb {
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Self { min, max }
+    }
+
+    //smallest box containing both points, in any order
+    pub fn from_points(a: Vec3, b: Vec3) -> Self {
+        Self {
+            min: Vec3::new(a.x().min(b.x()), a.y().min(b.y()), a.z().min(b.z())),
+            max: Vec3::new(a.x().max(b.x()), a.y().max(b.y()), a.z().max(b.z())),
+        }
+    }
+
+    pub fn min(&self) -> Vec3 {
+        return self.min;
+    }
+
+    pub fn max(&self) -> Vec3 {
+        return self.max;
+    }
+
+    pub fn surrounding(&self, other: &Aabb) -> Aabb {
+        return Aabb::from_points(
+            Vec3::new(self.min.x().min(other.min.x()), self.min.y().min(other.min.y()), self.min.z().min(other.min.z())),
+            Vec3::new(self.max.x().max(other.max.x()), self.max.y().max(other.max.y()), self.max.z().max(other.max.z())),
+        );
+    }
+
+    //grows every axis thinner than min_extent to that size, flat shapes would otherwise
+    //get boxes that rays can slip past through rounding
+    pub fn padded(&self, min_extent: f64) -> Aabb {
+        let mut min = self.min;
+        let mut max = self.max;
+        for axis in 0..3 {
+            let missing = min_extent - (max[axis] - min[axis]);
+            if missing > 0.0 {
+                min[axis] -= 0.5 * missing;
+                max[axis] += 0.5 * missing;
+            }
+        }
+        return Aabb::new(min, max);
+    }
+
+    pub fn centroid(&self) -> Vec3 {
+        return 0.5 * (self.min + self.max);
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        let d = self.max - self.min;
+        return 2.0 * (d.x() * d.y() + d.y() * d.z() + d.z() * d.x());
+    }
+
+    //index of the axis along which the box is widest
+    pub fn longest_axis(&self) -> i32 {
+        let d = self.max - self.min;
+        if d.x() > d.y() && d.x() > d.z() {
+            return 0;
+        } else if d.y() > d.z() {
+            return 1;
+        } else {
+            return 2;
+        }
+    }
+
+    //slab test, true if the ray passes through the box somewhere between t_min and t_max
+    pub fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> bool {
+        return self.intersect(ray, t_min, t_max).is_some();
+    }
+
+    //the part of t_min..t_max where the ray is inside the box
+    pub fn intersect(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<(f64, f64)> {
+        let origin = ray.origin();
+        let direction = ray.direction();
+        let mut t_min = t_min;
+        let mut t_max = t_max;
+
+        for axis in 0..3 {
+            let inv_d = 1.0 / direction[axis];
+            let mut t0 = (self.min[axis] - origin[axis]) * inv_d;
+            let mut t1 = (self.max[axis] - origin[axis]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+
+            //written so that a NaN from 0 * inf leaves the interval unchanged
+            t_min = if t0 > t_min { t0 } else { t_min };
+            t_max = if t1 < t_max { t1 } else { t_max };
+            if t_max < t_min {
+                return None;
+            }
+        }
+
+        return Some((t_min, t_max));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Aabb;
+    use crate::math::Ray;
+    use crate::math::Vec3;
+
+    fn unit_box() -> Aabb {
+        return Aabb::new(Vec3::new(-1.0, -1.0, -1.0), Vec3::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn test_hit() {
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(unit_box().hit(&ray, 0.0, f64::MAX));
+        assert!(!unit_box().hit(&ray, 0.0, 3.0));
+        assert_eq!(unit_box().intersect(&ray, 0.0, f64::MAX), Some((4.0, 6.0)));
+        assert_eq!(unit_box().intersect(&ray, 5.0, 5.5), Some((5.0, 5.5)));
+    }
+
+    #[test]
+    fn test_miss() {
+        let ray = Ray::new(Vec3::new(0.0, 2.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(!unit_box().hit(&ray, 0.0, f64::MAX));
+
+        let behind = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(!unit_box().hit(&behind, 0.0, f64::MAX));
+    }
+
+    #[test]
+    fn test_surrounding() {
+        let other = Aabb::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(3.0, 2.0, 1.0));
+        let both = unit_box().surrounding(&other);
+        assert_eq!(both.min(), Vec3::new(-1.0, -1.0, -1.0));
+        assert_eq!(both.max(), Vec3::new(3.0, 2.0, 1.0));
+        assert_eq!(both.longest_axis(), 0);
+        assert_eq!(unit_box().surface_area(), 24.0);
+    }
+}
@@ -0,0 +1,217 @@
+use crate::math::Vec3;
+
+use std::ops;
+
+//4x4 matrix for affine transforms of column vectors, stored by rows
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Mat4 {
+    m: [[f64; 4]; 4],
+}
+
+impl Mat4 {
+    pub fn from_rows(m: [[f64; 4]; 4]) -> Self {
+        Self { m }
+    }
+
+    //the order glTF and OpenGL store matrices in
+    pub fn from_column_major(values: [f64; 16]) -> Self {
+        let mut m = [[0_f64; 4]; 4];
+        for (i, value) in values.iter().enumerate() {
+            m[i % 4][i / 4] = *value;
+        }
+        return Self { m };
+    }
+
+    pub fn identity() -> Self {
+        return Mat4::scaling(Vec3::new(1.0, 1.0, 1.0));
+    }
+
+    pub fn translation(offset: Vec3) -> Self {
+        let mut matrix = Mat4::identity();
+        for row in 0..3 {
+            matrix.m[row][3] = offset[row as i32];
+        }
+        return matrix;
+    }
+
+    pub fn scaling(factors: Vec3) -> Self {
+        let mut m = [[0_f64; 4]; 4];
+        for i in 0..3 {
+            m[i][i] = factors[i as i32];
+        }
+        m[3][3] = 1_f64;
+        return Self { m };
+    }
+
+    //unit quaternion as [x, y, z, w]
+    pub fn from_quaternion(q: [f64; 4]) -> Self {
+        let [x, y, z, w] = q;
+        return Self {
+            m: [
+                [1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y - z * w), 2.0 * (x * z + y * w), 0.0],
+                [2.0 * (x * y + z * w), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z - x * w), 0.0],
+                [2.0 * (x * z - y * w), 2.0 * (y * z + x * w), 1.0 - 2.0 * (x * x + y * y), 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        };
+    }
+
+    //counter clockwise looking down the axis towards the origin
+    pub fn rotation(axis: Vec3, degrees: f64) -> Self {
+        let axis = axis.unit_vector();
+        let half = degrees.to_radians() / 2_f64;
+        let s = half.sin();
+        return Mat4::from_quaternion([axis.x() * s, axis.y() * s, axis.z() * s, half.cos()]);
+    }
+
+    pub fn get(&self, row: usize, column: usize) -> f64 {
+        return self.m[row][column];
+    }
+
+    pub fn transpose(&self) -> Mat4 {
+        let mut m = [[0_f64; 4]; 4];
+        for (row, values) in m.iter_mut().enumerate() {
+            for (column, value) in values.iter_mut().enumerate() {
+                *value = self.m[column][row];
+            }
+        }
+        return Mat4 { m };
+    }
+
+    //of the upper 3x3, negative when the transform mirrors
+    pub fn determinant3(&self) -> f64 {
+        let m = &self.m;
+        return m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1]) - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0]);
+    }
+
+    pub fn is_finite(&self) -> bool {
+        return self.m.iter().flatten().all(|value| value.is_finite());
+    }
+
+    //gauss jordan elimination with partial pivoting, None for a singular or non finite matrix
+    pub fn inverse(&self) -> Option<Mat4> {
+        if !self.is_finite() {
+            return None;
+        }
+        let mut a = self.m;
+        let mut inverse = Mat4::identity().m;
+
+        for column in 0..4 {
+            let pivot = (column..4)
+                .max_by(|&i, &j| a[i][column].abs().total_cmp(&a[j][column].abs()))
+                .unwrap();
+            if a[pivot][column].abs() < 1e-300 {
+                return None;
+            }
+            a.swap(column, pivot);
+            inverse.swap(column, pivot);
+
+            let scale = 1_f64 / a[column][column];
+            for k in 0..4 {
+                a[column][k] *= scale;
+                inverse[column][k] *= scale;
+            }
+
+            for row in 0..4 {
+                if row == column {
+                    continue;
+                }
+                let factor = a[row][column];
+                for k in 0..4 {
+                    a[row][k] -= factor * a[column][k];
+                    inverse[row][k] -= factor * inverse[column][k];
+                }
+            }
+        }
+
+        return Some(Mat4 { m: inverse });
+    }
+
+    pub fn transform_point(&self, p: Vec3) -> Vec3 {
+        let m = &self.m;
+        return Vec3::new(
+            m[0][0] * p.x() + m[0][1] * p.y() + m[0][2] * p.z() + m[0][3],
+            m[1][0] * p.x() + m[1][1] * p.y() + m[1][2] * p.z() + m[1][3],
+            m[2][0] * p.x() + m[2][1] * p.y() + m[2][2] * p.z() + m[2][3],
+        );
+    }
+
+    //ignores the translation
+    pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+        let m = &self.m;
+        return Vec3::new(
+            m[0][0] * v.x() + m[0][1] * v.y() + m[0][2] * v.z(),
+            m[1][0] * v.x() + m[1][1] * v.y() + m[1][2] * v.z(),
+            m[2][0] * v.x() + m[2][1] * v.y() + m[2][2] * v.z(),
+        );
+    }
+}
+
+impl ops::Mul for Mat4 {
+    type Output = Mat4;
+
+    //self applied after rhs
+    fn mul(self, rhs: Mat4) -> Mat4 {
+        let mut m = [[0_f64; 4]; 4];
+        for (row, values) in m.iter_mut().enumerate() {
+            for (column, value) in values.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.m[row][k] * rhs.m[k][column]).sum();
+            }
+        }
+        return Mat4 { m };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Mat4;
+    use crate::math::Vec3;
+
+    const EPS: f64 = 1e-12;
+
+    fn close(a: Vec3, b: Vec3) -> bool {
+        return (a - b).length() < EPS;
+    }
+
+    #[test]
+    fn test_compose() {
+        let half = std::f64::consts::FRAC_1_SQRT_2;
+        //90 degrees about y
+        let rotate = Mat4::from_quaternion([0.0, half, 0.0, half]);
+        let scale = Mat4::scaling(Vec3::new(2.0, 2.0, 2.0));
+        let translate = Mat4::translation(Vec3::new(1.0, 0.0, 0.0));
+        let transform = translate * rotate * scale;
+
+        assert!(close(rotate.transform_vector(Vec3::new(1.0, 0.0, 0.0)), Vec3::new(0.0, 0.0, -1.0)));
+        assert!(close(transform.transform_point(Vec3::new(1.0, 0.0, 0.0)), Vec3::new(1.0, 0.0, -2.0)));
+        assert!(close(transform.transform_vector(Vec3::new(1.0, 0.0, 0.0)), Vec3::new(0.0, 0.0, -2.0)));
+        assert!((transform.determinant3() - 8.0).abs() < EPS);
+        assert!(close(Mat4::rotation(Vec3::new(0.0, 3.0, 0.0), 90.0).transform_vector(Vec3::new(1.0, 0.0, 0.0)), Vec3::new(0.0, 0.0, -1.0)));
+        assert!(close(Mat4::rotation(Vec3::new(0.0, 0.0, 1.0), 90.0).transform_vector(Vec3::new(1.0, 0.0, 0.0)), Vec3::new(0.0, 1.0, 0.0)));
+        assert_eq!(Mat4::from_column_major([1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0, 1.0]), translate);
+    }
+
+    #[test]
+    fn test_inverse() {
+        let transform = Mat4::translation(Vec3::new(3.0, -1.0, 2.0))
+            * Mat4::from_quaternion([0.1825742, 0.3651484, 0.5477226, 0.7302967])
+            * Mat4::scaling(Vec3::new(1.0, 0.5, -3.0));
+        let inverse = transform.inverse().unwrap();
+        let p = Vec3::new(0.3, -7.0, 1.5);
+        assert!((inverse.transform_point(transform.transform_point(p)) - p).length() < 1e-9);
+
+        let product = transform * inverse;
+        for row in 0..4 {
+            for column in 0..4 {
+                let expected = if row == column { 1.0 } else { 0.0 };
+                assert!((product.get(row, column) - expected).abs() < 1e-9);
+            }
+        }
+
+        assert!(Mat4::scaling(Vec3::new(1.0, 0.0, 1.0)).inverse().is_none());
+        assert!(Mat4::scaling(Vec3::new(1.0, f64::NAN, 1.0)).inverse().is_none());
+        assert!(Mat4::translation(Vec3::new(f64::INFINITY, 0.0, 0.0)).inverse().is_none());
+        assert_eq!(Mat4::identity().transpose(), Mat4::identity());
+    }
+}
@@ -1,5 +1,9 @@
+mod aabb;
+mod matrix;
 mod ray;
 mod vector;
 
+pub use self::aabb::Aabb;
+pub use self::matrix::Mat4;
 pub use self::ray::Ray;
 pub use self::vector::Vec3;
@@ -3,11 +3,18 @@ use crate::math::Vec3;
 pub struct Ray {
     origin: Vec3,
     direction: Vec3,
+    //moment inside the shutter interval the ray was sent at, moving objects are hit where they are then
+    time: f64,
 }
 
 impl Ray {
     pub fn new(origin: Vec3, direction: Vec3) -> Self {
-        Self { origin, direction }
+        Self { origin, direction, time: 0_f64 }
+    }
+
+    pub fn with_time(mut self, time: f64) -> Self {
+        self.time = time;
+        return self;
     }
 
     pub fn origin(&self) -> Vec3 {
@@ -18,6 +25,10 @@ impl Ray {
         return self.direction;
     }
 
+    pub fn time(&self) -> f64 {
+        return self.time;
+    }
+
     pub fn point_at_distance(&self, t: f64) -> Vec3 {
         return self.origin + t * self.direction;
     }
@@ -90,6 +90,18 @@ impl ops::AddAssign for Vec3 {
     }
 }
 
+impl ops::Neg for Vec3 {
+    type Output = Vec3;
+
+    fn neg(self) -> Vec3 {
+        Self {
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+        }
+    }
+}
+
 impl ops::Sub for Vec3 {
     type Output = Vec3;
 
@@ -262,6 +274,11 @@ mod tests {
         assert_eq!(first, Vec3::new(-3.0, -3.0, 1.0));
     }
 
+    #[test]
+    fn test_neg() {
+        assert_eq!(-Vec3::new(1.0, -2.0, 0.5), Vec3::new(-1.0, 2.0, -0.5));
+    }
+
     #[test]
     fn test_mul() {
         assert_eq!(Vec3::new(1.0, 2.0, 3.0) * 2.0, Vec3::new(2.0, 4.0, 6.0));
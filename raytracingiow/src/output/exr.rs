@@ -0,0 +1,231 @@
+use crate::output::Image;
+use crate::output::ImageWriter;
+
+use half::f16;
+use std::io;
+use std::io::BufWriter;
+use std::io::Write;
+
+const MAGIC: [u8; 4] = [0x76, 0x2f, 0x31, 0x01];
+//version 2, single part scanline file
+const VERSION: [u8; 4] = [2, 0, 0, 0];
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ExrPixelType {
+    Half,
+    Float,
+}
+
+impl ExrPixelType {
+    fn id(&self) -> i32 {
+        return match self {
+            ExrPixelType::Half => 1,
+            ExrPixelType::Float => 2,
+        };
+    }
+
+    fn size(&self) -> usize {
+        return match self {
+            ExrPixelType::Half => 2,
+            ExrPixelType::Float => 4,
+        };
+    }
+}
+
+//one named plane of linear data, row-major with the top row first
+pub struct ExrChannel<'a> {
+    pub name: &'a str,
+    pub data: &'a [f32],
+}
+
+//uncompressed single part scanline OpenEXR
+pub struct ExrWriter {
+    pixel_type: ExrPixelType,
+}
+
+impl ExrWriter {
+    pub fn new(pixel_type: ExrPixelType) -> Self {
+        Self { pixel_type }
+    }
+
+    //writes any number of channels, e.g. R, G, B plus extra passes like Z or albedo.R
+    pub fn write_channels(&self, width: usize, height: usize, channels: &[ExrChannel], writer: &mut dyn Write) -> io::Result<()> {
+        for channel in channels {
+            if channel.data.len() != width * height {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("channel {} has {} values for a {}x{} image", channel.name, channel.data.len(), width, height),
+                ));
+            }
+        }
+
+        //the format requires channels sorted by name
+        let mut channels: Vec<&ExrChannel> = channels.iter().collect();
+        channels.sort_by(|a, b| a.name.cmp(b.name));
+
+        let mut header: Vec<u8> = Vec::new();
+        header.extend_from_slice(&MAGIC);
+        header.extend_from_slice(&VERSION);
+
+        let mut chlist: Vec<u8> = Vec::new();
+        for channel in &channels {
+            chlist.extend_from_slice(channel.name.as_bytes());
+            chlist.push(0);
+            chlist.extend_from_slice(&self.pixel_type.id().to_le_bytes());
+            //p_linear and 3 reserved bytes
+            chlist.extend_from_slice(&[0, 0, 0, 0]);
+            //x and y sampling
+            chlist.extend_from_slice(&1_i32.to_le_bytes());
+            chlist.extend_from_slice(&1_i32.to_le_bytes());
+        }
+        chlist.push(0);
+
+        let mut window: Vec<u8> = Vec::new();
+        for v in &[0, 0, width as i32 - 1, height as i32 - 1] {
+            window.extend_from_slice(&v.to_le_bytes());
+        }
+
+        write_attribute(&mut header, "channels", "chlist", &chlist);
+        //no compression
+        write_attribute(&mut header, "compression", "compression", &[0]);
+        write_attribute(&mut header, "dataWindow", "box2i", &window);
+        write_attribute(&mut header, "displayWindow", "box2i", &window);
+        //increasing y
+        write_attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+        write_attribute(&mut header, "pixelAspectRatio", "float", &1_f32.to_le_bytes());
+        write_attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+        write_attribute(&mut header, "screenWindowWidth", "float", &1_f32.to_le_bytes());
+        header.push(0);
+
+        //one block per scanline: y, byte count, then each channel's values for the line
+        let line_size = width * channels.len() * self.pixel_type.size();
+        let block_size = 8 + line_size;
+        let table_end = header.len() + 8 * height;
+
+        let mut writer = BufWriter::new(writer);
+        writer.write_all(&header)?;
+        for y in 0..height {
+            writer.write_all(&((table_end + y * block_size) as u64).to_le_bytes())?;
+        }
+
+        for y in 0..height {
+            writer.write_all(&(y as i32).to_le_bytes())?;
+            writer.write_all(&(line_size as i32).to_le_bytes())?;
+
+            for channel in &channels {
+                for &value in &channel.data[y * width..(y + 1) * width] {
+                    match self.pixel_type {
+                        ExrPixelType::Half => writer.write_all(&f16::from_f32(value).to_le_bytes())?,
+                        ExrPixelType::Float => writer.write_all(&value.to_le_bytes())?,
+                    }
+                }
+            }
+        }
+        writer.flush()?;
+
+        return Ok(());
+    }
+}
+
+fn write_attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(kind.as_bytes());
+    header.push(0);
+    header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    header.extend_from_slice(value);
+}
+
+impl ImageWriter for ExrWriter {
+    fn write(&self, image: &Image, writer: &mut dyn Write) -> io::Result<()> {
+        let plane = |channel: i32| -> Vec<f32> { image.pixels().iter().map(|p| p[channel] as f32).collect() };
+        let (r, g, b) = (plane(0), plane(1), plane(2));
+
+        let channels = [
+            ExrChannel { name: "R", data: &r },
+            ExrChannel { name: "G", data: &g },
+            ExrChannel { name: "B", data: &b },
+        ];
+        return self.write_channels(image.width(), image.height(), &channels, writer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Vec3;
+    use crate::output::*;
+
+    use half::f16;
+
+    fn read_f32(bytes: &[u8], at: usize) -> f32 {
+        return f32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]]);
+    }
+
+    fn read_i32(bytes: &[u8], at: usize) -> i32 {
+        return i32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]]);
+    }
+
+    fn read_u64(bytes: &[u8], at: usize) -> usize {
+        let mut b = [0; 8];
+        b.copy_from_slice(&bytes[at..at + 8]);
+        return u64::from_le_bytes(b) as usize;
+    }
+
+    fn header_len(bytes: &[u8]) -> usize {
+        //attributes are name\0type\0size value, the header ends with an empty name
+        let mut at = 8;
+        while bytes[at] != 0 {
+            let name_end = at + bytes[at..].iter().position(|&b| b == 0).unwrap();
+            let type_end = name_end + 1 + bytes[name_end + 1..].iter().position(|&b| b == 0).unwrap();
+            let size = read_i32(bytes, type_end + 1) as usize;
+            at = type_end + 5 + size;
+        }
+        return at + 1;
+    }
+
+    #[test]
+    fn test_float_layout() {
+        //values above 1 must survive untouched
+        let image = Image::from_pixels(2, 2, vec![
+            Vec3::new(1.0, 2.0, 3.0),
+            Vec3::new(4.0, 5.0, 6.0),
+            Vec3::new(7.0, 8.0, 9.0),
+            Vec3::new(10.0, 11.0, 12.5),
+        ]);
+
+        let mut bytes: Vec<u8> = Vec::new();
+        ExrWriter::new(ExrPixelType::Float).write(&image, &mut bytes).unwrap();
+
+        assert_eq!(&bytes[0..4], &[0x76, 0x2f, 0x31, 0x01]);
+        let table = header_len(&bytes);
+        let line_size = 2 * 3 * 4;
+        assert_eq!(bytes.len(), table + 2 * 8 + 2 * (8 + line_size));
+
+        //second scanline, channels in B G R order
+        let block = read_u64(&bytes, table + 8);
+        assert_eq!(read_i32(&bytes, block), 1);
+        assert_eq!(read_i32(&bytes, block + 4), line_size as i32);
+        let data = block + 8;
+        assert_eq!(read_f32(&bytes, data), 9.0);
+        assert_eq!(read_f32(&bytes, data + 4), 12.5);
+        assert_eq!(read_f32(&bytes, data + 8), 8.0);
+        assert_eq!(read_f32(&bytes, data + 20), 10.0);
+    }
+
+    #[test]
+    fn test_half_channels() {
+        let depth = [0.5_f32, 100.0];
+        let red = [1.5_f32, 0.0];
+        let channels = [ExrChannel { name: "Z", data: &depth }, ExrChannel { name: "R", data: &red }];
+
+        let mut bytes: Vec<u8> = Vec::new();
+        ExrWriter::new(ExrPixelType::Half).write_channels(2, 1, &channels, &mut bytes).unwrap();
+
+        let block = read_u64(&bytes, header_len(&bytes));
+        let value = |i: usize| f16::from_le_bytes([bytes[block + 8 + 2 * i], bytes[block + 9 + 2 * i]]).to_f32();
+        assert_eq!([value(0), value(1), value(2), value(3)], [1.5, 0.0, 0.5, 100.0]);
+
+        let short = [ExrChannel { name: "R", data: &red[..1] }];
+        assert!(ExrWriter::new(ExrPixelType::Half).write_channels(2, 1, &short, &mut Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,245 @@
+use crate::math::Vec3;
+use crate::output::Image;
+use crate::output::ImageWriter;
+
+use std::io;
+use std::io::BufRead;
+use std::io::BufWriter;
+use std::io::Read;
+use std::io::Write;
+
+//radiance .hdr with uncompressed RGBE scanlines
+pub struct HdrWriter;
+
+impl HdrWriter {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for HdrWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+//shared exponent encoding, each channel keeps 8 bits of mantissa relative to the brightest one
+pub fn to_rgbe(color: Vec3) -> [u8; 4] {
+    //nan is black, infinity the brightest value rgbe can hold
+    let clean = |c: f64| if c.is_nan() { 0_f64 } else { c.clamp(0_f64, f64::MAX) };
+    let (r, g, b) = (clean(color.r()), clean(color.g()), clean(color.b()));
+    let v = r.max(g).max(b);
+    if v < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+
+    //v = mantissa * 2^exponent with mantissa in [0.5, 1). the stored exponent byte runs from 1 to 255,
+    //brighter values saturate the mantissas
+    let exponent = (v.log2().floor() as i32 + 1).min(127);
+    let scale = 256.0 / 2_f64.powi(exponent);
+
+    return [
+        (r * scale).min(255.0) as u8,
+        (g * scale).min(255.0) as u8,
+        (b * scale).min(255.0) as u8,
+        (exponent + 128) as u8,
+    ];
+}
+
+pub fn from_rgbe(rgbe: [u8; 4]) -> Vec3 {
+    if rgbe[3] == 0 {
+        return Vec3::new(0.0, 0.0, 0.0);
+    }
+
+    let scale = 2_f64.powi(rgbe[3] as i32 - 128 - 8);
+    return Vec3::new(
+        (rgbe[0] as f64 + 0.5) * scale,
+        (rgbe[1] as f64 + 0.5) * scale,
+        (rgbe[2] as f64 + 0.5) * scale,
+    );
+}
+
+impl ImageWriter for HdrWriter {
+    fn write(&self, image: &Image, writer: &mut dyn Write) -> io::Result<()> {
+        let mut writer = BufWriter::new(writer);
+
+        writer.write_all(b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n")?;
+        writer.write_all(format!("-Y {} +X {}\n", image.height(), image.width()).as_bytes())?;
+        for p in image.pixels() {
+            writer.write_all(&to_rgbe(*p))?;
+        }
+        writer.flush()?;
+
+        return Ok(());
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    return io::Error::new(io::ErrorKind::InvalidData, message);
+}
+
+fn read_line(reader: &mut impl BufRead) -> io::Result<String> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Err(invalid_data("hdr header ends early".to_string()));
+    }
+    return Ok(line.trim_end().to_string());
+}
+
+fn read_byte(reader: &mut impl Read) -> io::Result<u8> {
+    let mut byte = [0; 1];
+    reader.read_exact(&mut byte)?;
+    return Ok(byte[0]);
+}
+
+//one scanline of the adaptive run length encoding, each channel stored separately
+fn read_rle_scanline(reader: &mut impl Read, width: usize, line: &mut [[u8; 4]]) -> io::Result<()> {
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let count = read_byte(reader)? as usize;
+            if count > 128 {
+                let run = count - 128;
+                if x + run > width {
+                    return Err(invalid_data("hdr run goes past the end of the scanline".to_string()));
+                }
+                let value = read_byte(reader)?;
+                for pixel in &mut line[x..x + run] {
+                    pixel[channel] = value;
+                }
+                x += run;
+            } else {
+                if count == 0 || x + count > width {
+                    return Err(invalid_data("bad hdr literal run".to_string()));
+                }
+                for pixel in &mut line[x..x + count] {
+                    pixel[channel] = read_byte(reader)?;
+                }
+                x += count;
+            }
+        }
+    }
+    return Ok(());
+}
+
+//radiance rgbe files in the standard -Y h +X w orientation, flat or run length encoded
+pub fn read_hdr(mut reader: impl BufRead) -> io::Result<Image> {
+    let magic = read_line(&mut reader)?;
+    if !magic.starts_with("#?") {
+        return Err(invalid_data("not a radiance hdr file".to_string()));
+    }
+
+    loop {
+        let line = read_line(&mut reader)?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some(format) = line.strip_prefix("FORMAT=") {
+            if format != "32-bit_rle_rgbe" {
+                return Err(invalid_data(format!("unsupported hdr pixel format {}", format)));
+            }
+        }
+    }
+
+    let resolution = read_line(&mut reader)?;
+    let parts: Vec<&str> = resolution.split_whitespace().collect();
+    let (height, width) = match parts.as_slice() {
+        ["-Y", h, "+X", w] => match (h.parse::<usize>(), w.parse::<usize>()) {
+            (Ok(h), Ok(w)) => (h, w),
+            _ => return Err(invalid_data(format!("bad hdr resolution '{}'", resolution))),
+        },
+        _ => return Err(invalid_data(format!("unsupported hdr orientation '{}', expected -Y h +X w", resolution))),
+    };
+
+    let mut pixels: Vec<Vec3> = Vec::with_capacity(width * height);
+    let mut line = vec![[0_u8; 4]; width];
+    for _ in 0..height {
+        let mut first = [0_u8; 4];
+        reader.read_exact(&mut first)?;
+
+        //encoded scanlines start with 2 2 and the width, anything else is flat
+        let encoded = (8..0x8000).contains(&width) && first[0] == 2 && first[1] == 2 && ((first[2] as usize) << 8 | first[3] as usize) == width;
+        if encoded {
+            read_rle_scanline(&mut reader, width, &mut line)?;
+        } else if width > 0 {
+            line[0] = first;
+            for pixel in &mut line[1..] {
+                reader.read_exact(pixel)?;
+            }
+        }
+
+        pixels.extend(line.iter().map(|rgbe| from_rgbe(*rgbe)));
+    }
+
+    return Ok(Image::from_pixels(width, height, pixels));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Vec3;
+    use crate::output::*;
+
+    #[test]
+    fn test_rgbe_round_trip() {
+        for color in &[Vec3::new(1.0, 0.5, 0.25), Vec3::new(1000.0, 3.0, 0.0), Vec3::new(0.01, 0.02, 0.03)] {
+            let decoded = from_rgbe(to_rgbe(*color));
+            let brightest = color.r().max(color.g()).max(color.b());
+            //8 bits of mantissa relative to the brightest channel
+            assert!((decoded - *color).length() < brightest / 64.0, "{:?} -> {:?}", color, decoded);
+        }
+        assert_eq!(to_rgbe(Vec3::new(0.0, 0.0, 0.0)), [0, 0, 0, 0]);
+        assert_eq!(to_rgbe(Vec3::new(1.0, 0.0, 0.0)), [128, 0, 0, 129]);
+
+        //out of range values saturate instead of wrapping the exponent
+        assert_eq!(to_rgbe(Vec3::new(f64::INFINITY, f64::NAN, 1.0)), [255, 0, 0, 255]);
+        assert_eq!(to_rgbe(Vec3::new(1e300, 0.0, 0.0)), [255, 0, 0, 255]);
+        assert_eq!(to_rgbe(Vec3::new(f64::NAN, f64::NEG_INFINITY, 0.0)), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_write_hdr() {
+        let image = Image::from_pixels(2, 1, vec![Vec3::new(1.0, 0.0, 0.0), Vec3::new(8.0, 8.0, 8.0)]);
+        let mut out: Vec<u8> = Vec::new();
+        HdrWriter::new().write(&image, &mut out).unwrap();
+
+        let header = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 2\n";
+        assert_eq!(&out[..header.len()], &header[..]);
+        assert_eq!(&out[header.len()..], &[128, 0, 0, 129, 128, 128, 128, 132]);
+    }
+
+    #[test]
+    fn test_read_hdr() {
+        let image = Image::from_pixels(2, 2, vec![
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(8.0, 8.0, 8.0),
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.5, 2.0, 100.0),
+        ]);
+        let mut bytes: Vec<u8> = Vec::new();
+        HdrWriter::new().write(&image, &mut bytes).unwrap();
+
+        let read = read_hdr(&bytes[..]).unwrap();
+        assert_eq!((read.width(), read.height()), (2, 2));
+        for (a, b) in read.pixels().iter().zip(image.pixels()) {
+            assert!((*a - *b).length() < 0.01 * b.length().max(1.0), "{:?} {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_read_rle_hdr() {
+        //8 pixels wide, each channel as a run of 8 or as literals
+        let mut bytes: Vec<u8> = b"#?RGBE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 8\n".to_vec();
+        bytes.extend_from_slice(&[2, 2, 0, 8]);
+        bytes.extend_from_slice(&[128 + 8, 128]);
+        bytes.extend_from_slice(&[8, 0, 0, 0, 0, 128, 128, 128, 128]);
+        bytes.extend_from_slice(&[128 + 8, 0]);
+        bytes.extend_from_slice(&[128 + 8, 129]);
+
+        let read = read_hdr(&bytes[..]).unwrap();
+        assert_eq!(read.pixel(0, 0), from_rgbe([128, 0, 0, 129]));
+        assert_eq!(read.pixel(7, 0), from_rgbe([128, 128, 0, 129]));
+
+        assert!(read_hdr(&b"P6\n"[..]).is_err());
+        assert!(read_hdr(&b"#?RADIANCE\n\n+Y 1 +X 8\n"[..]).is_err());
+    }
+}
@@ -0,0 +1,71 @@
+use crate::color::RGB;
+use crate::math::Vec3;
+use crate::output::ToneMap;
+
+//floating point framebuffer of linear radiance, row-major with the top row first
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<Vec3>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![Vec3::new(0.0, 0.0, 0.0); width * height],
+        }
+    }
+
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<Vec3>) -> Self {
+        assert_eq!(pixels.len(), width * height, "pixel count does not match {}x{}", width, height);
+        Self { width, height, pixels }
+    }
+
+    pub fn width(&self) -> usize {
+        return self.width;
+    }
+
+    pub fn height(&self) -> usize {
+        return self.height;
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Vec3 {
+        return self.pixels[y * self.width + x];
+    }
+
+    pub fn pixels(&self) -> &[Vec3] {
+        return &self.pixels;
+    }
+
+    pub fn pixels_mut(&mut self) -> &mut [Vec3] {
+        return &mut self.pixels;
+    }
+
+    //tone mapped and display encoded pixels, each channel in [0, 1]
+    pub fn display_pixels<'a>(&'a self, tone_map: &'a ToneMap) -> impl Iterator<Item = Vec3> + 'a {
+        return self.pixels.iter().map(move |p| tone_map.apply(*p));
+    }
+
+    //0xRRGGBB per pixel, for the window and the u32 based helpers
+    pub fn to_u32_buffer(&self, tone_map: &ToneMap) -> Vec<u32> {
+        return self.display_pixels(tone_map).map(|p| p.to_u32_rgb()).collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Vec3;
+    use crate::output::Image;
+    use crate::output::ToneMap;
+
+    #[test]
+    fn test_display_pixels_clamp() {
+        let image = Image::from_pixels(2, 1, vec![Vec3::new(4.0, 0.25, -1.0), Vec3::new(0.0, 1.0, 0.0)]);
+        let tone_map = ToneMap::gamma_2();
+        let display: Vec<Vec3> = image.display_pixels(&tone_map).collect();
+        assert_eq!(display[0], Vec3::new(1.0, 0.5, 0.0));
+        assert_eq!(image.to_u32_buffer(&tone_map), vec![0xFF7F00, 0x00FF00]);
+    }
+}
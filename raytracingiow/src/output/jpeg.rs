@@ -0,0 +1,44 @@
+use crate::math::Vec3;
+use crate::output::Image;
+
+use jpeg_decoder::PixelFormat;
+use std::io;
+use std::io::Read;
+
+//baseline and progressive rgb or grey jpegs with values in [0, 1] as stored, normally srgb encoded
+pub fn read_jpeg(reader: impl Read) -> io::Result<Image> {
+    let mut decoder = jpeg_decoder::Decoder::new(reader);
+    let data = decoder.decode().map_err(io::Error::other)?;
+    let info = decoder.info().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "jpeg has no image"))?;
+    let (width, height) = (info.width as usize, info.height as usize);
+
+    let pixels: Vec<Vec3> = match info.pixel_format {
+        PixelFormat::RGB24 => data
+            .chunks_exact(3)
+            .map(|p| Vec3::new(p[0] as f64 / 255.0, p[1] as f64 / 255.0, p[2] as f64 / 255.0))
+            .collect(),
+        PixelFormat::L8 => data
+            .iter()
+            .map(|&grey| {
+                let grey = grey as f64 / 255.0;
+                Vec3::new(grey, grey, grey)
+            })
+            .collect(),
+        //the decoder hands out 16 bit samples in native byte order
+        PixelFormat::L16 => data
+            .chunks_exact(2)
+            .map(|p| {
+                let grey = u16::from_ne_bytes([p[0], p[1]]) as f64 / 65535.0;
+                Vec3::new(grey, grey, grey)
+            })
+            .collect(),
+        PixelFormat::CMYK32 => {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "cmyk jpegs are not supported, convert it to rgb"));
+        }
+    };
+
+    if pixels.len() != width * height {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "jpeg is shorter than its size"));
+    }
+    return Ok(Image::from_pixels(width, height, pixels));
+}
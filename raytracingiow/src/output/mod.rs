@@ -0,0 +1,173 @@
+mod exr;
+mod hdr;
+mod image;
+mod jpeg;
+mod png;
+mod ppm;
+mod tonemap;
+
+pub use self::exr::*;
+pub use self::hdr::*;
+pub use self::image::*;
+pub use self::jpeg::*;
+pub use self::png::*;
+pub use self::ppm::*;
+pub use self::tonemap::*;
+
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::BufReader;
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
+
+pub trait ImageWriter {
+    fn write(&self, image: &Image, writer: &mut dyn Write) -> io::Result<()>;
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ImageFormat {
+    PpmAscii,
+    PpmBinary,
+    Png8,
+    Png16,
+    //linear high dynamic range formats
+    ExrHalf,
+    ExrFloat,
+    Hdr,
+}
+
+impl ImageFormat {
+    //guess the format from the file extension, .ppm is written as binary P6, .png as 8 bit and .exr as half float
+    pub fn from_path(path: impl AsRef<Path>) -> Option<ImageFormat> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        return match extension.as_str() {
+            "ppm" => Some(ImageFormat::PpmBinary),
+            "png" => Some(ImageFormat::Png8),
+            "exr" => Some(ImageFormat::ExrHalf),
+            "hdr" => Some(ImageFormat::Hdr),
+            _ => None,
+        };
+    }
+
+    //the tone map only applies to the low dynamic range formats, exr and hdr stay linear
+    pub fn writer(&self, tone_map: ToneMap) -> Box<dyn ImageWriter> {
+        return match self {
+            ImageFormat::PpmAscii => Box::new(PpmWriter::new_ascii(tone_map)),
+            ImageFormat::PpmBinary => Box::new(PpmWriter::new_binary(tone_map)),
+            ImageFormat::Png8 => Box::new(PngWriter::new_8_bit(tone_map)),
+            ImageFormat::Png16 => Box::new(PngWriter::new_16_bit(tone_map)),
+            ImageFormat::ExrHalf => Box::new(ExrWriter::new(ExrPixelType::Half)),
+            ImageFormat::ExrFloat => Box::new(ExrWriter::new(ExrPixelType::Float)),
+            ImageFormat::Hdr => Box::new(HdrWriter::new()),
+        };
+    }
+}
+
+impl FromStr for ImageFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        return match s {
+            "ppm-ascii" => Ok(ImageFormat::PpmAscii),
+            "ppm" => Ok(ImageFormat::PpmBinary),
+            "png" | "png8" => Ok(ImageFormat::Png8),
+            "png16" => Ok(ImageFormat::Png16),
+            "exr" | "exr-half" => Ok(ImageFormat::ExrHalf),
+            "exr-float" => Ok(ImageFormat::ExrFloat),
+            "hdr" => Ok(ImageFormat::Hdr),
+            _ => Err(format!(
+                "unknown image format '{}', expected one of: ppm, ppm-ascii, png8, png16, exr-half, exr-float, hdr",
+                s
+            )),
+        };
+    }
+}
+
+impl fmt::Display for ImageFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ImageFormat::PpmAscii => "ppm-ascii",
+            ImageFormat::PpmBinary => "ppm",
+            ImageFormat::Png8 => "png8",
+            ImageFormat::Png16 => "png16",
+            ImageFormat::ExrHalf => "exr-half",
+            ImageFormat::ExrFloat => "exr-float",
+            ImageFormat::Hdr => "hdr",
+        };
+        return write!(f, "{}", name);
+    }
+}
+
+//writes the image to path, in the given format or else the one matching the extension
+pub fn save_image(path: impl AsRef<Path>, image: &Image, format: Option<ImageFormat>, tone_map: ToneMap) -> io::Result<()> {
+    let path = path.as_ref();
+    let format = match format.or_else(|| ImageFormat::from_path(path)) {
+        Some(format) => format,
+        None => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("can't tell the image format of {}, use a .png, .ppm, .exr or .hdr extension", path.display()),
+            ))
+        }
+    };
+
+    let mut file = File::create(path)?;
+    format.writer(tone_map).write(image, &mut file)?;
+    file.flush()?;
+
+    return Ok(());
+}
+
+//reads a png, jpeg or radiance hdr image held in memory, telling them apart by their first bytes
+pub fn read_image(bytes: &[u8]) -> io::Result<Image> {
+    if bytes.starts_with(b"\x89PNG") {
+        return read_png(bytes);
+    }
+    if bytes.starts_with(&[0xff, 0xd8]) {
+        return read_jpeg(bytes);
+    }
+    if bytes.starts_with(b"#?") {
+        return read_hdr(bytes);
+    }
+    return Err(io::Error::new(io::ErrorKind::InvalidData, "not a png, jpeg or hdr image"));
+}
+
+//reads a png, jpeg or radiance hdr file. hdr pixels are linear radiance,
+//the others are the values as stored, usually srgb encoded
+pub fn load_image(path: impl AsRef<Path>) -> io::Result<Image> {
+    let path = path.as_ref();
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_ascii_lowercase();
+    let reader = match extension.as_str() {
+        "png" | "jpg" | "jpeg" | "hdr" => BufReader::new(File::open(path)?),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("can't read {}, images must be .png, .jpg or .hdr", path.display()),
+            ))
+        }
+    };
+
+    return match extension.as_str() {
+        "png" => read_png(reader),
+        "hdr" => read_hdr(reader),
+        _ => read_jpeg(reader),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::output::ImageFormat;
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(ImageFormat::from_path("output/render.PNG"), Some(ImageFormat::Png8));
+        assert_eq!(ImageFormat::from_path("render.ppm"), Some(ImageFormat::PpmBinary));
+        assert_eq!(ImageFormat::from_path("render.exr"), Some(ImageFormat::ExrHalf));
+        assert_eq!(ImageFormat::from_path("render.hdr"), Some(ImageFormat::Hdr));
+        assert_eq!(ImageFormat::from_path("render.txt"), None);
+        assert_eq!(ImageFormat::from_path("render"), None);
+        assert_eq!("png16".parse::<ImageFormat>(), Ok(ImageFormat::Png16));
+    }
+}
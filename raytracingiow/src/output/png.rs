@@ -0,0 +1,154 @@
+use crate::color::RGBu32;
+use crate::math::Vec3;
+use crate::output::Image;
+use crate::output::ImageWriter;
+use crate::output::ToneMap;
+
+use std::io;
+use std::io::Read;
+use std::io::Write;
+
+pub struct PngWriter {
+    //8 or 16 bits per channel
+    bit_depth: png::BitDepth,
+    tone_map: ToneMap,
+}
+
+impl PngWriter {
+    pub fn new_8_bit(tone_map: ToneMap) -> Self {
+        Self { bit_depth: png::BitDepth::Eight, tone_map }
+    }
+
+    pub fn new_16_bit(tone_map: ToneMap) -> Self {
+        Self { bit_depth: png::BitDepth::Sixteen, tone_map }
+    }
+}
+
+impl ImageWriter for PngWriter {
+    fn write(&self, image: &Image, writer: &mut dyn Write) -> io::Result<()> {
+        let mut encoder = png::Encoder::new(writer, image.width() as u32, image.height() as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(self.bit_depth);
+
+        let mut data: Vec<u8> = Vec::new();
+        match self.bit_depth {
+            png::BitDepth::Sixteen => {
+                for p in image.display_pixels(&self.tone_map) {
+                    for c in &[p.r(), p.g(), p.b()] {
+                        data.extend_from_slice(&((c * 65535.0).round() as u16).to_be_bytes());
+                    }
+                }
+            }
+            _ => {
+                for rgb in image.to_u32_buffer(&self.tone_map) {
+                    data.extend_from_slice(&[rgb.get_r(), rgb.get_g(), rgb.get_b()]);
+                }
+            }
+        }
+
+        let mut png_writer = encoder.write_header().map_err(io::Error::other)?;
+        png_writer.write_image_data(&data).map_err(io::Error::other)?;
+        png_writer.finish().map_err(io::Error::other)?;
+
+        return Ok(());
+    }
+}
+
+//8 and 16 bit rgb, grey and palette images with values in [0, 1] exactly as stored,
+//the transfer function is left for the caller to undo. alpha is dropped
+pub fn read_png(reader: impl Read) -> io::Result<Image> {
+    let mut decoder = png::Decoder::new(reader);
+    //palettes and sub byte grey become 8 bit rgb and grey
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let mut reader = decoder.read_info().map_err(io::Error::other)?;
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data).map_err(io::Error::other)?;
+
+    let channels = info.color_type.samples();
+    let sample = |i: usize| -> f64 {
+        return match info.bit_depth {
+            png::BitDepth::Sixteen => u16::from_be_bytes([data[2 * i], data[2 * i + 1]]) as f64 / 65535.0,
+            _ => data[i] as f64 / 255.0,
+        };
+    };
+
+    let (width, height) = (info.width as usize, info.height as usize);
+    let pixels = (0..width * height)
+        .map(|p| {
+            let first = p * channels;
+            if channels < 3 {
+                let grey = sample(first);
+                Vec3::new(grey, grey, grey)
+            } else {
+                Vec3::new(sample(first), sample(first + 1), sample(first + 2))
+            }
+        })
+        .collect();
+
+    return Ok(Image::from_pixels(width, height, pixels));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Vec3;
+    use crate::output::*;
+
+    fn decode(bytes: &[u8]) -> (::png::OutputInfo, Vec<u8>) {
+        let decoder = ::png::Decoder::new(bytes);
+        let mut reader = decoder.read_info().unwrap();
+        let mut data = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut data).unwrap();
+        return (info, data);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let image = Image::from_pixels(2, 1, vec![Vec3::new(1.0, 0.25, 0.0), Vec3::new(0.0, 0.0, 4.0)]);
+
+        let mut bytes: Vec<u8> = Vec::new();
+        PngWriter::new_8_bit(ToneMap::gamma_2()).write(&image, &mut bytes).unwrap();
+        let (info, data) = decode(&bytes);
+        assert_eq!((info.width, info.height), (2, 1));
+        assert_eq!(info.bit_depth, ::png::BitDepth::Eight);
+        assert_eq!(data, vec![255, 127, 0, 0, 0, 255]);
+
+        let mut bytes: Vec<u8> = Vec::new();
+        PngWriter::new_16_bit(ToneMap::gamma_2()).write(&image, &mut bytes).unwrap();
+        let (info, data) = decode(&bytes);
+        assert_eq!(info.bit_depth, ::png::BitDepth::Sixteen);
+        assert_eq!(&data[0..4], &[255, 255, 128, 0]);
+    }
+
+    #[test]
+    fn test_read_png() {
+        let image = Image::from_pixels(2, 2, vec![
+            Vec3::new(1.0, 0.25, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(0.5, 0.5, 0.5),
+            Vec3::new(0.0, 1.0, 0.0),
+        ]);
+        let linear = ToneMap {
+            transfer: TransferFunction::Linear,
+            ..ToneMap::default()
+        };
+
+        let mut bytes: Vec<u8> = Vec::new();
+        PngWriter::new_16_bit(linear).write(&image, &mut bytes).unwrap();
+        let read = read_png(&bytes[..]).unwrap();
+        assert_eq!((read.width(), read.height()), (2, 2));
+        for (a, b) in read.pixels().iter().zip(image.pixels()) {
+            assert!((*a - *b).length() < 1e-4, "{:?} {:?}", a, b);
+        }
+
+        //a 2x1 8 bit grey image with alpha
+        let mut bytes: Vec<u8> = Vec::new();
+        {
+            let mut encoder = ::png::Encoder::new(&mut bytes, 2, 1);
+            encoder.set_color(::png::ColorType::GrayscaleAlpha);
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(&[255, 0, 51, 255]).unwrap();
+        }
+        let read = read_png(&bytes[..]).unwrap();
+        assert_eq!(read.pixels(), &[Vec3::new(1.0, 1.0, 1.0), Vec3::new(0.2, 0.2, 0.2)]);
+    }
+}
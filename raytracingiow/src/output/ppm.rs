@@ -0,0 +1,105 @@
+use crate::color::RGBu32;
+use crate::output::Image;
+use crate::output::ImageWriter;
+use crate::output::ToneMap;
+
+use std::fs::File;
+use std::io;
+use std::io::BufWriter;
+use std::io::Write;
+
+//writes an ascii P3 ppm of the buffer produced by f
+pub fn draw_picture(
+    x_size: usize,
+    y_size: usize,
+    filename: &str,
+    f: impl Fn(usize, usize) -> Vec<u32>,
+) -> io::Result<()> {
+    let buffer = f(x_size, y_size);
+    return save_ppm(filename, x_size, y_size, &buffer);
+}
+
+pub fn save_ppm(filename: &str, x_size: usize, y_size: usize, buffer: &[u32]) -> io::Result<()> {
+    let output_file = File::create(filename)?;
+    let mut writer = BufWriter::new(output_file);
+
+    write_ppm(&mut writer, x_size, y_size, buffer)?;
+    writer.flush()?;
+
+    return Ok(());
+}
+
+pub fn write_ppm(writer: &mut impl Write, x_size: usize, y_size: usize, buffer: &[u32]) -> io::Result<()> {
+    let header = format!(
+        "P3\n{x_size} {y_size}\n255\n",
+        x_size = x_size,
+        y_size = y_size
+    );
+    writer.write_all(header.as_bytes())?;
+
+    for val in buffer {
+        let ir = val.get_r();
+        let ig = val.get_g();
+        let ib = val.get_b();
+
+        writer.write_all(format!("{} {} {}\n", ir, ig, ib).as_bytes())?;
+    }
+
+    return Ok(());
+}
+
+pub struct PpmWriter {
+    //human readable P3 instead of binary P6
+    ascii: bool,
+    tone_map: ToneMap,
+}
+
+impl PpmWriter {
+    pub fn new_ascii(tone_map: ToneMap) -> Self {
+        Self { ascii: true, tone_map }
+    }
+
+    pub fn new_binary(tone_map: ToneMap) -> Self {
+        Self { ascii: false, tone_map }
+    }
+}
+
+impl ImageWriter for PpmWriter {
+    fn write(&self, image: &Image, writer: &mut dyn Write) -> io::Result<()> {
+        let buffer = image.to_u32_buffer(&self.tone_map);
+        let mut writer = BufWriter::new(writer);
+
+        if self.ascii {
+            write_ppm(&mut writer, image.width(), image.height(), &buffer)?;
+        } else {
+            writer.write_all(format!("P6\n{} {}\n255\n", image.width(), image.height()).as_bytes())?;
+            for val in buffer {
+                writer.write_all(&[val.get_r(), val.get_g(), val.get_b()])?;
+            }
+        }
+        writer.flush()?;
+
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Vec3;
+    use crate::output::*;
+
+    #[test]
+    fn test_write_binary_ppm() {
+        let image = Image::from_pixels(2, 1, vec![Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.25)]);
+        let mut out: Vec<u8> = Vec::new();
+        PpmWriter::new_binary(ToneMap::gamma_2()).write(&image, &mut out).unwrap();
+        assert_eq!(out, b"P6\n2 1\n255\n\xFF\x00\x00\x00\xFF\x7F".to_vec());
+    }
+
+    #[test]
+    fn test_write_ppm() {
+        let mut out: Vec<u8> = Vec::new();
+        write_ppm(&mut out, 2, 1, &[0xFF0000, 0x00FF7F]).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "P3\n2 1\n255\n255 0 0\n0 255 127\n");
+    }
+}
@@ -0,0 +1,267 @@
+use crate::math::Vec3;
+
+use std::fmt;
+use std::str::FromStr;
+
+//compresses linear radiance into the displayable [0, 1] range
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ToneMapOperator {
+    //hard clip at 1
+    Clamp,
+    //L / (1 + L) on luminance
+    Reinhard,
+    //reinhard that maps the white point to 1 instead of infinity
+    ExtendedReinhard,
+    //john hable's uncharted 2 filmic curve
+    Hable,
+    //krzysztof narkowicz's fit of the ACES reference rendering transform
+    Aces,
+}
+
+//encoding of the tone mapped values for an 8 or 16 bit file
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TransferFunction {
+    Srgb,
+    //square root, what the book uses
+    Gamma2,
+    Linear,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ToneMap {
+    pub operator: ToneMapOperator,
+    //in stops, each stop doubles the radiance
+    pub exposure: f64,
+    //smallest luminance mapped to pure white by the extended reinhard operator
+    pub white_point: f64,
+    pub transfer: TransferFunction,
+}
+
+impl Default for ToneMap {
+    fn default() -> Self {
+        Self {
+            operator: ToneMapOperator::Clamp,
+            exposure: 0.0,
+            white_point: 4.0,
+            transfer: TransferFunction::Srgb,
+        }
+    }
+}
+
+fn luminance(c: Vec3) -> f64 {
+    return 0.2126 * c.r() + 0.7152 * c.g() + 0.0722 * c.b();
+}
+
+//scales the color so its luminance becomes l, keeping the hue
+fn with_luminance(c: Vec3, l: f64) -> Vec3 {
+    let current = luminance(c);
+    if current <= 0.0 {
+        return Vec3::new(0.0, 0.0, 0.0);
+    }
+    return c * (l / current);
+}
+
+fn hable_curve(x: f64) -> f64 {
+    let (a, b, c, d, e, f) = (0.15, 0.50, 0.10, 0.20, 0.02, 0.30);
+    return ((x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f)) - e / f;
+}
+
+fn aces_curve(x: f64) -> f64 {
+    //the fit expects the input scaled to match the reference transform's exposure
+    let x = 0.6 * x;
+    return (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14);
+}
+
+fn per_channel(c: Vec3, f: impl Fn(f64) -> f64) -> Vec3 {
+    return Vec3::new(f(c.r()), f(c.g()), f(c.b()));
+}
+
+impl ToneMapOperator {
+    pub fn apply(&self, c: Vec3, white_point: f64) -> Vec3 {
+        return match self {
+            ToneMapOperator::Clamp => c,
+            ToneMapOperator::Reinhard => {
+                let l = luminance(c);
+                with_luminance(c, l / (1.0 + l))
+            }
+            ToneMapOperator::ExtendedReinhard => {
+                let l = luminance(c);
+                with_luminance(c, l * (1.0 + l / (white_point * white_point)) / (1.0 + l))
+            }
+            ToneMapOperator::Hable => {
+                let exposure_bias = 2.0;
+                let white_scale = 1.0 / hable_curve(11.2);
+                per_channel(c, |x| hable_curve(exposure_bias * x) * white_scale)
+            }
+            ToneMapOperator::Aces => per_channel(c, aces_curve),
+        };
+    }
+}
+
+impl TransferFunction {
+    //c in [0, 1]
+    pub fn encode(&self, c: f64) -> f64 {
+        return match self {
+            TransferFunction::Srgb => {
+                if c <= 0.0031308 {
+                    12.92 * c
+                } else {
+                    1.055 * c.powf(1.0 / 2.4) - 0.055
+                }
+            }
+            TransferFunction::Gamma2 => c.sqrt(),
+            TransferFunction::Linear => c,
+        };
+    }
+
+    //inverse of encode, turns stored values such as a texture's back into linear ones
+    pub fn decode(&self, c: f64) -> f64 {
+        return match self {
+            TransferFunction::Srgb => {
+                if c <= 0.04045 {
+                    c / 12.92
+                } else {
+                    ((c + 0.055) / 1.055).powf(2.4)
+                }
+            }
+            TransferFunction::Gamma2 => c * c,
+            TransferFunction::Linear => c,
+        };
+    }
+}
+
+impl ToneMap {
+    //the book's display transform, clip then square root
+    pub fn gamma_2() -> Self {
+        Self {
+            transfer: TransferFunction::Gamma2,
+            ..ToneMap::default()
+        }
+    }
+
+    //linear radiance to display encoded values in [0, 1]
+    pub fn apply(&self, linear: Vec3) -> Vec3 {
+        let exposed = linear * 2_f64.powf(self.exposure);
+        let mapped = self.operator.apply(exposed, self.white_point);
+        return per_channel(mapped, |c| {
+            //NaN from a bad sample shows up as black instead of poisoning the encoder
+            let clamped = if c.is_nan() { 0.0 } else { c.clamp(0.0, 1.0) };
+            self.transfer.encode(clamped)
+        });
+    }
+}
+
+impl FromStr for ToneMapOperator {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        return match s {
+            "clamp" => Ok(ToneMapOperator::Clamp),
+            "reinhard" => Ok(ToneMapOperator::Reinhard),
+            "reinhard-extended" => Ok(ToneMapOperator::ExtendedReinhard),
+            "hable" | "filmic" => Ok(ToneMapOperator::Hable),
+            "aces" => Ok(ToneMapOperator::Aces),
+            _ => Err(format!(
+                "unknown tone map operator '{}', expected one of: clamp, reinhard, reinhard-extended, hable, aces",
+                s
+            )),
+        };
+    }
+}
+
+impl fmt::Display for ToneMapOperator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ToneMapOperator::Clamp => "clamp",
+            ToneMapOperator::Reinhard => "reinhard",
+            ToneMapOperator::ExtendedReinhard => "reinhard-extended",
+            ToneMapOperator::Hable => "hable",
+            ToneMapOperator::Aces => "aces",
+        };
+        return write!(f, "{}", name);
+    }
+}
+
+impl FromStr for TransferFunction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        return match s {
+            "srgb" => Ok(TransferFunction::Srgb),
+            "gamma2" => Ok(TransferFunction::Gamma2),
+            "linear" => Ok(TransferFunction::Linear),
+            _ => Err(format!("unknown transfer function '{}', expected one of: srgb, gamma2, linear", s)),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Vec3;
+    use crate::output::*;
+
+    const EPS: f64 = 1e-4;
+
+    fn grey(v: f64) -> Vec3 {
+        return Vec3::new(v, v, v);
+    }
+
+    fn linear(operator: ToneMapOperator) -> ToneMap {
+        return ToneMap {
+            operator,
+            transfer: TransferFunction::Linear,
+            ..ToneMap::default()
+        };
+    }
+
+    #[test]
+    fn test_srgb() {
+        let srgb = TransferFunction::Srgb;
+        assert_eq!(srgb.encode(0.0), 0.0);
+        assert!((srgb.encode(1.0) - 1.0).abs() < EPS);
+        assert!((srgb.encode(0.5) - 0.7354).abs() < EPS);
+        assert!((srgb.encode(0.002) - 0.02584).abs() < EPS);
+        for &c in &[0.0, 0.001, 0.2, 0.5, 1.0] {
+            assert!((srgb.decode(srgb.encode(c)) - c).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_reinhard() {
+        assert!((linear(ToneMapOperator::Reinhard).apply(grey(1.0)).r() - 0.5).abs() < EPS);
+
+        let extended = linear(ToneMapOperator::ExtendedReinhard);
+        assert!((extended.apply(grey(4.0)).r() - 1.0).abs() < EPS);
+        assert!(extended.apply(grey(2.0)).r() < 1.0);
+
+        //luminance based, hue is kept
+        let orange = linear(ToneMapOperator::Reinhard).apply(Vec3::new(0.8, 0.4, 0.0));
+        assert!((orange.r() / orange.g() - 2.0).abs() < EPS);
+    }
+
+    #[test]
+    fn test_filmic_curves_are_monotonic_and_bounded() {
+        for operator in &[ToneMapOperator::Hable, ToneMapOperator::Aces] {
+            let tone_map = linear(*operator);
+            let mut previous = -1.0;
+            for i in 0..200 {
+                let v = tone_map.apply(grey(i as f64 * 0.1)).r();
+                assert!(v >= previous && v <= 1.0, "{} at {}", operator, i);
+                previous = v;
+            }
+            assert_eq!(tone_map.apply(grey(0.0)).r(), 0.0);
+        }
+        assert!((linear(ToneMapOperator::Hable).apply(grey(5.6)).r() - 1.0).abs() < EPS);
+    }
+
+    #[test]
+    fn test_exposure() {
+        let tone_map = ToneMap {
+            exposure: 1.0,
+            ..linear(ToneMapOperator::Clamp)
+        };
+        assert_eq!(tone_map.apply(grey(0.25)), grey(0.5));
+        assert_eq!(tone_map.apply(grey(f64::NAN)), grey(0.0));
+        assert_eq!(tone_map.apply(grey(1.0)), grey(1.0));
+    }
+}
@@ -0,0 +1,155 @@
+use rand::Rng;
+use rand::RngCore;
+
+use std::f64::consts::PI;
+
+//shape of the lens opening, decides the shape of out of focus highlights (bokeh)
+#[derive(Clone, Debug)]
+pub struct ApertureShape {
+    //outline of the opening scaled to fit the unit disk, empty for a circle
+    outline: Vec<(f64, f64)>,
+    //running total of the areas of the fan triangles (origin, outline[i], outline[i + 1])
+    cumulative_area: Vec<f64>,
+}
+
+impl ApertureShape {
+    pub fn circle() -> Self {
+        Self {
+            outline: Vec::new(),
+            cumulative_area: Vec::new(),
+        }
+    }
+
+    //regular polygon formed by n aperture blades, rotation in degrees
+    pub fn polygon(blades: u32, rotation: f64) -> Result<Self, String> {
+        if blades < 3 {
+            return Err(format!("an aperture needs at least 3 blades, got {}", blades));
+        }
+
+        let outline = (0..blades)
+            .map(|i| {
+                let angle = rotation.to_radians() + 2_f64 * PI * i as f64 / blades as f64;
+                (angle.cos(), angle.sin())
+            })
+            .collect();
+        return ApertureShape::custom(outline);
+    }
+
+    //any polygon that is star shaped around the origin, such as a star or a heart,
+    //points in counter clockwise order. it is scaled to fit the unit disk
+    pub fn custom(outline: Vec<(f64, f64)>) -> Result<Self, String> {
+        if outline.len() < 3 {
+            return Err(format!("an aperture outline needs at least 3 points, got {}", outline.len()));
+        }
+
+        let max_radius = outline
+            .iter()
+            .map(|(x, y)| (x * x + y * y).sqrt())
+            .fold(0_f64, f64::max);
+        if outline.iter().any(|(x, y)| !x.is_finite() || !y.is_finite()) {
+            return Err("aperture outline points must be finite".to_string());
+        }
+        if max_radius == 0_f64 {
+            return Err("aperture outline has no extent, every point is at the origin".to_string());
+        }
+        let outline: Vec<(f64, f64)> = outline.iter().map(|(x, y)| (x / max_radius, y / max_radius)).collect();
+
+        let mut total = 0_f64;
+        let mut cumulative_area: Vec<f64> = Vec::with_capacity(outline.len());
+        for i in 0..outline.len() {
+            let (a, b) = (outline[i], outline[(i + 1) % outline.len()]);
+            let area = 0.5 * (a.0 * b.1 - a.1 * b.0);
+            if area < 0_f64 {
+                return Err(format!(
+                    "aperture outline must wind counter clockwise around the origin, edge {} does not",
+                    i
+                ));
+            }
+            total += area;
+            cumulative_area.push(total);
+        }
+
+        return Ok(Self {
+            outline,
+            cumulative_area,
+        });
+    }
+
+    //uniformly distributed point inside the opening, within the unit disk
+    pub fn sample(&self, rng: &mut dyn RngCore) -> (f64, f64) {
+        if self.outline.is_empty() {
+            loop {
+                let x = 2_f64 * rng.gen::<f64>() - 1_f64;
+                let y = 2_f64 * rng.gen::<f64>() - 1_f64;
+                if x * x + y * y < 1_f64 {
+                    return (x, y);
+                }
+            }
+        }
+
+        //pick a fan triangle proportionally to its area, then a uniform point inside it
+        let total = *self.cumulative_area.last().unwrap();
+        let target = rng.gen::<f64>() * total;
+        let i = self
+            .cumulative_area
+            .iter()
+            .position(|&area| target < area)
+            .unwrap_or(self.outline.len() - 1);
+        let (a, b) = (self.outline[i], self.outline[(i + 1) % self.outline.len()]);
+
+        let r1 = rng.gen::<f64>().sqrt();
+        let r2: f64 = rng.gen();
+        return (r1 * ((1_f64 - r2) * a.0 + r2 * b.0), r1 * ((1_f64 - r2) * a.1 + r2 * b.1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::render::ApertureShape;
+
+    use rand::SeedableRng;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn test_polygon_samples_stay_inside() {
+        let blades = 6;
+        let hexagon = ApertureShape::polygon(blades, 0.0).unwrap();
+        let apothem = (PI / blades as f64).cos();
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+
+        for _ in 0..10000 {
+            let (x, y) = hexagon.sample(&mut rng);
+            for i in 0..blades {
+                //outward normal of the edge between vertex i and i + 1
+                let angle = 2.0 * PI * (i as f64 + 0.5) / blades as f64;
+                assert!(x * angle.cos() + y * angle.sin() <= apothem + 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_custom_is_uniform() {
+        //a square scaled into the unit disk, each quadrant should get a quarter of the samples
+        let square = ApertureShape::custom(vec![(2.0, 2.0), (-2.0, 2.0), (-2.0, -2.0), (2.0, -2.0)]).unwrap();
+        let mut rng = rand::rngs::StdRng::seed_from_u64(2);
+
+        let mut quadrants = [0; 4];
+        for _ in 0..40000 {
+            let (x, y) = square.sample(&mut rng);
+            assert!(x * x + y * y <= 1.0 + 1e-9);
+            quadrants[(x > 0.0) as usize * 2 + (y > 0.0) as usize] += 1;
+        }
+        for count in &quadrants {
+            assert!((*count - 10000_i32).abs() < 500, "{:?}", quadrants);
+        }
+    }
+
+    #[test]
+    fn test_invalid_outlines() {
+        assert!(ApertureShape::polygon(2, 0.0).is_err());
+        assert!(ApertureShape::custom(vec![(1.0, 0.0), (0.0, 1.0)]).is_err());
+        assert!(ApertureShape::custom(vec![(1.0, 0.0), (0.0, -1.0), (-1.0, 0.0)]).is_err());
+        assert!(ApertureShape::custom(vec![(0.0, 0.0), (0.0, 0.0), (0.0, 0.0)]).is_err());
+        assert!(ApertureShape::custom(vec![(1.0, 0.0), (0.0, f64::INFINITY), (-1.0, 0.0)]).is_err());
+    }
+}
@@ -0,0 +1,344 @@
+use crate::math::Aabb;
+use crate::math::Ray;
+use crate::math::Vec3;
+use crate::render::light::Light;
+use crate::render::renderable::HitRecord;
+use crate::render::renderable::Renderable;
+
+//number of centroid buckets evaluated per axis when looking for the cheapest split
+const SAH_BUCKETS: usize = 12;
+//relative cost of testing a ray against a child box vs against a primitive
+const TRAVERSAL_COST: f64 = 0.125;
+const MAX_LEAF_SIZE: usize = 4;
+
+//bounding volume hierarchy, a drop in replacement for a Vec world
+pub struct Bvh<T: Renderable> {
+    nodes: Vec<BvhNode>,
+    //bounded objects, ordered so every leaf covers a contiguous range
+    objects: Vec<T>,
+    //objects without a bounding box are tested against every ray
+    unbounded: Vec<T>,
+}
+
+enum BvhNode {
+    Leaf { bbox: Aabb, start: usize, count: usize },
+    Interior { bbox: Aabb, left: usize, right: usize, axis: i32 },
+}
+
+impl BvhNode {
+    fn bbox(&self) -> &Aabb {
+        return match self {
+            BvhNode::Leaf { bbox, .. } => bbox,
+            BvhNode::Interior { bbox, .. } => bbox,
+        };
+    }
+}
+
+struct BuildItem {
+    index: usize,
+    bbox: Aabb,
+    centroid: Vec3,
+}
+
+#[derive(Copy, Clone)]
+struct Bucket {
+    count: usize,
+    bbox: Option<Aabb>,
+}
+
+fn union(a: Option<Aabb>, b: &Aabb) -> Option<Aabb> {
+    return match a {
+        Some(a) => Some(a.surrounding(b)),
+        None => Some(*b),
+    };
+}
+
+fn bucket_of(centroid: f64, min: f64, extent: f64) -> usize {
+    let b = ((centroid - min) / extent * SAH_BUCKETS as f64) as usize;
+    return b.min(SAH_BUCKETS - 1);
+}
+
+impl<T: Renderable> Bvh<T> {
+    pub fn new(objects: Vec<T>) -> Self {
+        let mut items: Vec<BuildItem> = Vec::new();
+        let mut bounded: Vec<Option<T>> = Vec::new();
+        let mut unbounded: Vec<T> = Vec::new();
+
+        for object in objects {
+            match object.bounding_box() {
+                Some(bbox) => {
+                    items.push(BuildItem {
+                        index: bounded.len(),
+                        bbox,
+                        centroid: bbox.centroid(),
+                    });
+                    bounded.push(Some(object));
+                }
+                None => unbounded.push(object),
+            }
+        }
+
+        let mut nodes: Vec<BvhNode> = Vec::new();
+        if !items.is_empty() {
+            build_node(&mut items, 0, &mut nodes);
+        }
+
+        //reorder the objects to match the leaf ranges
+        let objects = items
+            .iter()
+            .map(|item| bounded[item.index].take().unwrap())
+            .collect();
+
+        return Self {
+            nodes,
+            objects,
+            unbounded,
+        };
+    }
+
+    pub fn len(&self) -> usize {
+        return self.objects.len() + self.unbounded.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.len() == 0;
+    }
+
+    fn hit_node<'a>(&'a self, index: usize, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'a>> {
+        let node = &self.nodes[index];
+        if !node.bbox().hit(ray, t_min, t_max) {
+            return None;
+        }
+
+        match node {
+            BvhNode::Leaf { start, count, .. } => {
+                return self.objects[*start..*start + *count].hit(ray, t_min, t_max);
+            }
+            BvhNode::Interior { left, right, axis, .. } => {
+                //visit the child nearer to the ray origin first so the far one can be culled
+                let (near, far) = if ray.direction()[*axis] < 0.0 { (*right, *left) } else { (*left, *right) };
+
+                let near_hit = self.hit_node(near, ray, t_min, t_max);
+                let max = near_hit.as_ref().map_or(t_max, |hit| hit.t);
+                let far_hit = self.hit_node(far, ray, t_min, max);
+
+                return far_hit.or(near_hit);
+            }
+        }
+    }
+
+    //every object along the ray takes its share, there is no nearest one to stop at
+    fn transmittance_node(&self, index: usize, ray: &Ray, t_min: f64, t_max: f64) -> f64 {
+        let node = &self.nodes[index];
+        if !node.bbox().hit(ray, t_min, t_max) {
+            return 1_f64;
+        }
+
+        return match node {
+            BvhNode::Leaf { start, count, .. } => self.objects[*start..*start + *count].transmittance(ray, t_min, t_max),
+            BvhNode::Interior { left, right, .. } => {
+                let left = self.transmittance_node(*left, ray, t_min, t_max);
+                if left == 0_f64 {
+                    return 0_f64;
+                }
+                left * self.transmittance_node(*right, ray, t_min, t_max)
+            }
+        };
+    }
+}
+
+//builds the subtree over items, which start at offset in the final object order, returns its node index
+fn build_node(items: &mut [BuildItem], offset: usize, nodes: &mut Vec<BvhNode>) -> usize {
+    let bbox = items
+        .iter()
+        .fold(None, |acc, item| union(acc, &item.bbox))
+        .unwrap();
+
+    let index = nodes.len();
+    nodes.push(BvhNode::Leaf {
+        bbox,
+        start: offset,
+        count: items.len(),
+    });
+
+    if items.len() == 1 {
+        return index;
+    }
+
+    let centroid_bounds = items
+        .iter()
+        .fold(None, |acc, item| union(acc, &Aabb::new(item.centroid, item.centroid)))
+        .unwrap();
+
+    let (axis, split_bucket, split_cost) = find_split(items, &bbox, &centroid_bounds);
+    let leaf_cost = items.len() as f64;
+
+    if items.len() <= MAX_LEAF_SIZE && split_cost >= leaf_cost {
+        return index;
+    }
+
+    let min = centroid_bounds.min()[axis];
+    let extent = centroid_bounds.max()[axis] - min;
+
+    let mid = if extent > 0.0 {
+        partition(items, |item| bucket_of(item.centroid[axis], min, extent) <= split_bucket)
+    } else {
+        0
+    };
+
+    //every centroid landed on one side, fall back to splitting at the median
+    let mid = if mid == 0 || mid == items.len() {
+        items.sort_unstable_by(|a, b| a.centroid[axis].total_cmp(&b.centroid[axis]));
+        items.len() / 2
+    } else {
+        mid
+    };
+
+    let (left_items, right_items) = items.split_at_mut(mid);
+    let left = build_node(left_items, offset, nodes);
+    let right = build_node(right_items, offset + mid, nodes);
+
+    nodes[index] = BvhNode::Interior { bbox, left, right, axis };
+    return index;
+}
+
+//surface area heuristic, returns (axis, last bucket of the left child, cost) of the cheapest split
+fn find_split(items: &[BuildItem], bbox: &Aabb, centroid_bounds: &Aabb) -> (i32, usize, f64) {
+    let mut best = (centroid_bounds.longest_axis(), SAH_BUCKETS / 2, f64::MAX);
+    let parent_area = bbox.surface_area();
+
+    for axis in 0..3 {
+        let min = centroid_bounds.min()[axis];
+        let extent = centroid_bounds.max()[axis] - min;
+        if extent <= 0.0 {
+            continue;
+        }
+
+        let mut buckets = [Bucket { count: 0, bbox: None }; SAH_BUCKETS];
+        for item in items {
+            let bucket = &mut buckets[bucket_of(item.centroid[axis], min, extent)];
+            bucket.count += 1;
+            bucket.bbox = union(bucket.bbox, &item.bbox);
+        }
+
+        for split in 0..SAH_BUCKETS - 1 {
+            let (left, right) = buckets.split_at(split + 1);
+            let cost_of = |side: &[Bucket]| -> f64 {
+                let count: usize = side.iter().map(|b| b.count).sum();
+                let bbox = side.iter().filter_map(|b| b.bbox).fold(None, |acc, b| union(acc, &b));
+                return bbox.map_or(0.0, |b| count as f64 * b.surface_area());
+            };
+
+            let cost = TRAVERSAL_COST + (cost_of(left) + cost_of(right)) / parent_area;
+            if cost < best.2 {
+                best = (axis, split, cost);
+            }
+        }
+    }
+
+    return best;
+}
+
+//moves the items matching pred to the front, returns how many matched
+fn partition(items: &mut [BuildItem], pred: impl Fn(&BuildItem) -> bool) -> usize {
+    let mut mid = 0;
+    for i in 0..items.len() {
+        if pred(&items[i]) {
+            items.swap(i, mid);
+            mid += 1;
+        }
+    }
+    return mid;
+}
+
+impl<T: Renderable> Renderable for Bvh<T> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let unbounded_hit = self.unbounded.hit(ray, t_min, t_max);
+        if self.nodes.is_empty() {
+            return unbounded_hit;
+        }
+
+        let max = unbounded_hit.as_ref().map_or(t_max, |hit| hit.t);
+        return self.hit_node(0, ray, t_min, max).or(unbounded_hit);
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        if !self.unbounded.is_empty() {
+            return None;
+        }
+        return self.nodes.first().map(|node| *node.bbox());
+    }
+
+    fn collect_lights<'a>(&'a self, lights: &mut Vec<&'a dyn Light>) {
+        self.objects.collect_lights(lights);
+        self.unbounded.collect_lights(lights);
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f64, t_max: f64) -> f64 {
+        let unbounded = self.unbounded.transmittance(ray, t_min, t_max);
+        if self.nodes.is_empty() || unbounded == 0_f64 {
+            return unbounded;
+        }
+        return unbounded * self.transmittance_node(0, ray, t_min, t_max);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Ray;
+    use crate::math::Vec3;
+    use crate::render::*;
+
+    use rand::Rng;
+    use rand::SeedableRng;
+
+    fn random_spheres(n: usize) -> Vec<Sphere> {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        return (0..n)
+            .map(|_| {
+                let center = Vec3::new(rng.gen_range(-10.0, 10.0), rng.gen_range(-10.0, 10.0), rng.gen_range(-10.0, 10.0));
+                Sphere::new(center, rng.gen_range(0.05, 0.5), Box::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5))))
+            })
+            .collect();
+    }
+
+    #[test]
+    fn test_matches_linear_search() {
+        let linear = random_spheres(300);
+        let bvh = Bvh::new(random_spheres(300));
+        assert_eq!(bvh.len(), 300);
+        assert_eq!(bvh.bounding_box(), linear.bounding_box());
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(11);
+        for _ in 0..2000 {
+            let origin = Vec3::new(rng.gen_range(-15.0, 15.0), rng.gen_range(-15.0, 15.0), rng.gen_range(-15.0, 15.0));
+            let direction = Vec3::new(rng.gen_range(-1.0, 1.0), rng.gen_range(-1.0, 1.0), rng.gen_range(-1.0, 1.0));
+            let ray = Ray::new(origin, direction);
+
+            let expected = linear.hit(&ray, 0.001, f64::MAX).map(|hit| hit.t);
+            let actual = bvh.hit(&ray, 0.001, f64::MAX).map(|hit| hit.t);
+            assert_eq!(expected, actual);
+        }
+    }
+
+    #[test]
+    fn test_nan_centroid() {
+        //stacked spheres fall back to the median split, which must not choke on a broken one
+        let material = || Box::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)));
+        let mut spheres: Vec<Sphere> = (0..20).map(|_| Sphere::new(Vec3::new(0.0, 0.0, -5.0), 1.0, material())).collect();
+        spheres.push(Sphere::new(Vec3::new(f64::NAN, f64::NAN, f64::NAN), 1.0, material()));
+        let bvh = Bvh::new(spheres);
+
+        let hit = bvh.hit(&Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0)), 0.001, f64::MAX).unwrap();
+        assert!((hit.t - 4.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_empty() {
+        let bvh: Bvh<Sphere> = Bvh::new(Vec::new());
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(bvh.is_empty());
+        assert!(bvh.hit(&ray, 0.0, f64::MAX).is_none());
+        assert!(bvh.bounding_box().is_none());
+    }
+}
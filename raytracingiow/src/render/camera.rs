@@ -1,24 +1,213 @@
 use crate::math::Vec3;
 use crate::math::Ray;
+use crate::render::aperture::ApertureShape;
+
+use rand::Rng;
+use rand::RngCore;
 
 pub struct Camera {
+    look_from: Vec3,
+    look_at: Vec3,
+    vup: Vec3,
+    //vertical field of view in degrees
+    vfov: f64,
+    //image width / height
+    aspect: f64,
+    //diameter of the lens, 0 for a pinhole camera with everything in focus
+    aperture: f64,
+    //distance from look_from to the plane in perfect focus
+    focus_dist: f64,
+    aperture_shape: ApertureShape,
+    //rays are spread evenly over the time the shutter is open, the same open and close times freeze motion
+    shutter_open: f64,
+    shutter_close: f64,
+
+    //image plane on the focus plane, derived from the parameters above
     bottom_left: Vec3,
     horizontal: Vec3,
     vertical: Vec3,
-    origin: Vec3
+    //camera right and up directions, used to offset ray origins across the lens
+    u: Vec3,
+    v: Vec3,
 }
 
 impl Camera {
-    pub fn new(bottom_left: Vec3, horizontal: Vec3, vertical: Vec3, origin: Vec3) -> Camera {
-        Camera {
-            bottom_left,
-            horizontal,
-            vertical,
-            origin
+    pub fn new(look_from: Vec3, look_at: Vec3, vup: Vec3, vfov: f64, aspect: f64) -> Camera {
+        let mut camera = Camera {
+            look_from,
+            look_at,
+            vup,
+            vfov,
+            aspect,
+            aperture: 0_f64,
+            focus_dist: 1_f64,
+            aperture_shape: ApertureShape::circle(),
+            shutter_open: 0_f64,
+            shutter_close: 0_f64,
+            bottom_left: Vec3::new(0.0, 0.0, 0.0),
+            horizontal: Vec3::new(0.0, 0.0, 0.0),
+            vertical: Vec3::new(0.0, 0.0, 0.0),
+            u: Vec3::new(0.0, 0.0, 0.0),
+            v: Vec3::new(0.0, 0.0, 0.0),
+        };
+        camera.update_image_plane();
+        return camera;
+    }
+
+    //thin lens depth of field, objects at focus_dist from the camera are sharp
+    pub fn with_lens(mut self, aperture: f64, focus_dist: f64) -> Camera {
+        self.aperture = aperture;
+        self.focus_dist = focus_dist;
+        self.update_image_plane();
+        return self;
+    }
+
+    pub fn with_aperture_shape(mut self, aperture_shape: ApertureShape) -> Camera {
+        self.aperture_shape = aperture_shape;
+        return self;
+    }
+
+    pub fn with_shutter(mut self, open: f64, close: f64) -> Camera {
+        self.shutter_open = open;
+        self.shutter_close = close;
+        return self;
+    }
+
+    pub fn look_from(&self) -> Vec3 {
+        return self.look_from;
+    }
+
+    pub fn aspect_ratio(&self) -> f64 {
+        return self.aspect;
+    }
+
+    //call when the output resolution changes so the image is not stretched
+    pub fn set_aspect_ratio(&mut self, aspect: f64) {
+        self.aspect = aspect;
+        self.update_image_plane();
+    }
+
+    fn update_image_plane(&mut self) {
+        let half_height = (self.vfov.to_radians() / 2_f64).tan();
+        let half_width = self.aspect * half_height;
+
+        //orthonormal basis, the camera looks down -w
+        let w = (self.look_from - self.look_at).unit_vector();
+        let u = self.vup.cross(w).unit_vector();
+        let v = w.cross(u);
+
+        let focus_dist = self.focus_dist;
+        self.bottom_left = self.look_from - half_width * focus_dist * u - half_height * focus_dist * v - focus_dist * w;
+        self.horizontal = 2_f64 * half_width * focus_dist * u;
+        self.vertical = 2_f64 * half_height * focus_dist * v;
+        self.u = u;
+        self.v = v;
+    }
+
+    //u, v in [0, 1] from the bottom left corner of the image
+    pub fn get_ray(&self, u: f64, v: f64, rng: &mut dyn RngCore) -> Ray {
+        let offset = if self.aperture > 0_f64 {
+            let (x, y) = self.aperture_shape.sample(rng);
+            let lens_radius = self.aperture / 2_f64;
+            lens_radius * (x * self.u + y * self.v)
+        } else {
+            Vec3::new(0.0, 0.0, 0.0)
+        };
+
+        let time = if self.shutter_close > self.shutter_open {
+            self.shutter_open + rng.gen::<f64>() * (self.shutter_close - self.shutter_open)
+        } else {
+            self.shutter_open
+        };
+
+        let origin = self.look_from + offset;
+        return Ray::new(origin, self.bottom_left + u * self.horizontal + v * self.vertical - origin).with_time(time);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Vec3;
+    use crate::render::ApertureShape;
+    use crate::render::Camera;
+
+    use rand::SeedableRng;
+
+    const EPS: f64 = 1e-9;
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < EPS, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn test_matches_book_image_plane() {
+        let origin = Vec3::new(0.0, 0.0, 0.0);
+        let rng = &mut rand::rngs::StdRng::seed_from_u64(0);
+        let camera = Camera::new(origin, Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 1.0, 0.0), 90.0, 2.0);
+
+        assert_close(camera.get_ray(0.0, 0.0, rng).direction(), Vec3::new(-2.0, -1.0, -1.0));
+        assert_close(camera.get_ray(1.0, 1.0, rng).direction(), Vec3::new(2.0, 1.0, -1.0));
+        assert_close(camera.get_ray(0.5, 0.5, rng).direction(), Vec3::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn test_look_at() {
+        let look_from = Vec3::new(3.0, 3.0, 2.0);
+        let look_at = Vec3::new(0.0, 0.0, -1.0);
+        let mut camera = Camera::new(look_from, look_at, Vec3::new(0.0, 1.0, 0.0), 20.0, 1.5);
+        let rng = &mut rand::rngs::StdRng::seed_from_u64(0);
+
+        let center = camera.get_ray(0.5, 0.5, rng);
+        assert_close(center.origin(), look_from);
+        assert_close(center.direction().unit_vector(), (look_at - look_from).unit_vector());
+
+        //changing the aspect widens the view without moving its center or height
+        let top = camera.get_ray(0.5, 1.0, rng).direction();
+        camera.set_aspect_ratio(3.0);
+        assert_close(camera.get_ray(0.5, 0.5, rng).direction().unit_vector(), (look_at - look_from).unit_vector());
+        assert_close(camera.get_ray(0.5, 1.0, rng).direction(), top);
+    }
+
+    #[test]
+    fn test_depth_of_field() {
+        let look_from = Vec3::new(0.0, 0.0, 0.0);
+        let look_at = Vec3::new(0.0, 0.0, -4.0);
+        let hexagon = ApertureShape::polygon(6, 0.0).unwrap();
+        let camera = Camera::new(look_from, look_at, Vec3::new(0.0, 1.0, 0.0), 90.0, 1.0)
+            .with_lens(0.5, 4.0)
+            .with_aperture_shape(hexagon);
+        let rng = &mut rand::rngs::StdRng::seed_from_u64(0);
+
+        let mut spread = 0_f64;
+        for _ in 0..100 {
+            let ray = camera.get_ray(0.25, 0.75, rng);
+
+            //origins lie on the lens, in the plane facing the scene and within the aperture
+            assert!(ray.origin().z().abs() < EPS);
+            assert!(ray.origin().length() <= 0.25 + EPS);
+            spread = spread.max(ray.origin().length());
+
+            //every ray through the same pixel meets at the focus plane
+            let t = -4.0 / ray.direction().z();
+            assert_close(ray.point_at_distance(t), Vec3::new(-2.0, 2.0, -4.0));
         }
+        assert!(spread > 0.1);
     }
 
-    pub fn get_ray(&self, u: f64, v: f64) -> Ray {
-        return Ray::new(self.origin, self.bottom_left + u * self.horizontal + v * self.vertical - self.origin);
+    #[test]
+    fn test_shutter() {
+        let camera = Camera::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 1.0, 0.0), 90.0, 1.0);
+        let rng = &mut rand::rngs::StdRng::seed_from_u64(0);
+        assert_eq!(camera.get_ray(0.5, 0.5, rng).time(), 0.0);
+
+        let camera = camera.with_shutter(1.0, 1.5);
+        let (mut earliest, mut latest) = (f64::MAX, f64::MIN);
+        for _ in 0..1000 {
+            let time = camera.get_ray(0.5, 0.5, rng).time();
+            earliest = earliest.min(time);
+            latest = latest.max(time);
+        }
+        assert!((1.0..1.01).contains(&earliest));
+        assert!((1.49..1.5).contains(&latest));
     }
 }
@@ -0,0 +1,143 @@
+use crate::math::Aabb;
+use crate::math::Ray;
+use crate::math::Vec3;
+use crate::render::material::Material;
+use crate::render::medium::medium_salt;
+use crate::render::medium::RayRandom;
+use crate::render::renderable::HitRecord;
+use crate::render::renderable::Renderable;
+
+//smoke, fog or haze of the same density everywhere inside a boundary shape. rays passing through
+//scatter after an exponentially distributed distance, with the material as the phase function
+//(isotropic or henyey greenstein). the boundary must be closed and convex, only the first stretch
+//of a ray inside it is filled
+pub struct ConstantMedium {
+    boundary: Box<dyn Renderable>,
+    //extinction coefficient, collisions per unit of distance
+    density: f64,
+    phase_function: Box<dyn Material>,
+    salt: u64,
+}
+
+impl ConstantMedium {
+    pub fn new(boundary: Box<dyn Renderable>, density: f64, phase_function: Box<dyn Material>) -> Self {
+        let salt = match boundary.bounding_box() {
+            Some(bbox) => medium_salt(&[density, bbox.min().x(), bbox.min().y(), bbox.min().z(), bbox.max().x(), bbox.max().y(), bbox.max().z()]),
+            None => medium_salt(&[density]),
+        };
+
+        Self {
+            boundary,
+            density,
+            phase_function,
+            salt,
+        }
+    }
+
+    //the part of t_min..t_max inside the boundary, wherever the ray starts
+    fn inside(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<(f64, f64)> {
+        let enter = self.boundary.hit(ray, -f64::MAX, f64::MAX)?.t;
+        let exit = self.boundary.hit(ray, enter + 1e-4, f64::MAX)?.t;
+
+        let start = enter.max(t_min);
+        let end = exit.min(t_max);
+        if start >= end {
+            return None;
+        }
+        return Some((start, end));
+    }
+}
+
+impl Renderable for ConstantMedium {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let (start, end) = self.inside(ray, t_min, t_max)?;
+        let speed = ray.direction().length();
+        let distance = RayRandom::new(ray, self.salt).free_flight(self.density);
+        if distance > (end - start) * speed {
+            return None;
+        }
+
+        let t = start + distance / speed;
+        return Some(HitRecord {
+            t,
+            position: ray.point_at_distance(t),
+            //a volume has no surface, phase functions ignore the normal
+            normal: Vec3::new(1.0, 0.0, 0.0),
+            u: 0_f64,
+            v: 0_f64,
+            material: &*self.phase_function,
+        });
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        return self.boundary.bounding_box();
+    }
+
+    //beer lambert, exactly
+    fn transmittance(&self, ray: &Ray, t_min: f64, t_max: f64) -> f64 {
+        return match self.inside(ray, t_min, t_max) {
+            Some((start, end)) => (-self.density * (end - start) * ray.direction().length()).exp(),
+            None => 1_f64,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Ray;
+    use crate::math::Vec3;
+    use crate::render::*;
+
+    use rand::Rng;
+    use rand::SeedableRng;
+
+    fn smoke(density: f64) -> ConstantMedium {
+        let boundary = Sphere::new(Vec3::new(0.0, 0.0, -5.0), 1.0, Box::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5))));
+        return ConstantMedium::new(Box::new(boundary), density, Box::new(Isotropic::new(Vec3::new(0.9, 0.9, 0.9))));
+    }
+
+    #[test]
+    fn test_transmittance() {
+        //a ray through the middle crosses 2 units of medium and gets through with probability exp(-2 density)
+        let medium = smoke(0.5);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(16);
+        let n = 20000;
+        let mut passed = 0;
+        for _ in 0..n {
+            //jitter the origin a little so every ray hashes differently
+            let origin = Vec3::new(0.0, 0.0, 1e-9 * rng.gen::<f64>());
+            match medium.hit(&Ray::new(origin, Vec3::new(0.0, 0.0, -1.0)), 0.001, f64::MAX) {
+                Some(hit) => {
+                    assert!(hit.t >= 4.0 && hit.t <= 6.0);
+                    assert!(!hit.material.is_specular());
+                }
+                None => passed += 1,
+            }
+        }
+        let expected = (-1.0_f64).exp();
+        assert!((passed as f64 / n as f64 - expected).abs() < 0.015, "{}", passed as f64 / n as f64);
+    }
+
+    #[test]
+    fn test_consistent_and_bounded() {
+        let medium = smoke(100.0);
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -2.0));
+        let hit = medium.hit(&ray, 0.001, f64::MAX).unwrap();
+        //the same collision however often the ray is tested, and the direction's length doesn't matter
+        assert_eq!(medium.hit(&ray, 0.001, f64::MAX).unwrap().t, hit.t);
+        assert!(hit.t >= 2.0 && hit.t < 2.1);
+
+        //a ray starting inside scatters on its way out, one that ends before the medium never does
+        let inside = Ray::new(Vec3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 1.0, 0.0));
+        assert!(medium.hit(&inside, 0.001, f64::MAX).unwrap().t < 1.0);
+        assert!(medium.hit(&ray, 0.001, 1.9).is_none());
+        assert!(medium.hit(&Ray::new(Vec3::new(0.0, 2.0, 0.0), Vec3::new(0.0, 0.0, -1.0)), 0.001, f64::MAX).is_none());
+
+        let bbox = medium.bounding_box().unwrap();
+        assert_eq!(bbox.min(), Vec3::new(-1.0, -1.0, -6.0));
+
+        let transmittance = smoke(0.5).transmittance(&ray, 0.001, f64::MAX);
+        assert!((transmittance - (-1.0_f64).exp()).abs() < 1e-6);
+        assert!((smoke(0.5).transmittance(&ray, 0.001, 2.25) - (-0.25_f64).exp()).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,117 @@
+use crate::math::Vec3;
+
+//dense 3d grid of densities, x varies fastest, then y, then z
+pub struct DensityGrid {
+    resolution: [usize; 3],
+    values: Vec<f32>,
+}
+
+impl DensityGrid {
+    pub fn new(resolution: [usize; 3], values: Vec<f32>) -> Result<Self, String> {
+        if resolution.contains(&0) {
+            return Err(format!("density grid resolution {:?} is empty", resolution));
+        }
+        let count = match resolution.iter().try_fold(1_usize, |count, &n| count.checked_mul(n)) {
+            Some(count) => count,
+            None => return Err(format!("density grid resolution {:?} has too many voxels", resolution)),
+        };
+        if values.len() != count {
+            return Err(format!("density grid {:?} needs {} values, got {}", resolution, count, values.len()));
+        }
+        if let Some(value) = values.iter().find(|v| !v.is_finite() || **v < 0_f32) {
+            return Err(format!("density grid values must be finite and not negative, got {}", value));
+        }
+
+        return Ok(Self { resolution, values });
+    }
+
+    pub fn resolution(&self) -> [usize; 3] {
+        return self.resolution;
+    }
+
+    //indices outside the grid are clamped to its edge
+    pub fn value(&self, x: i64, y: i64, z: i64) -> f64 {
+        let [nx, ny, nz] = self.resolution;
+        let x = x.clamp(0, nx as i64 - 1) as usize;
+        let y = y.clamp(0, ny as i64 - 1) as usize;
+        let z = z.clamp(0, nz as i64 - 1) as usize;
+        return self.values[(z * ny + y) * nx + x] as f64;
+    }
+
+    //trilinear between voxel centres, p runs over [0, 1] on each axis across the whole grid
+    pub fn sample(&self, p: Vec3) -> f64 {
+        let mut base = [0_i64; 3];
+        let mut f = [0_f64; 3];
+        for axis in 0..3 {
+            let x = p[axis as i32] * self.resolution[axis] as f64 - 0.5;
+            let floor = x.floor();
+            base[axis] = floor as i64;
+            f[axis] = x - floor;
+        }
+
+        let mut result = 0_f64;
+        for corner in 0..8 {
+            let (dx, dy, dz) = (corner & 1, (corner >> 1) & 1, (corner >> 2) & 1);
+            let weight = (if dx == 1 { f[0] } else { 1_f64 - f[0] })
+                * (if dy == 1 { f[1] } else { 1_f64 - f[1] })
+                * (if dz == 1 { f[2] } else { 1_f64 - f[2] });
+            if weight > 0_f64 {
+                result += weight * self.value(base[0] + dx, base[1] + dy, base[2] + dz);
+            }
+        }
+        return result;
+    }
+
+    //largest value of the voxels from min to max inclusive, clamped to the grid
+    pub fn max_in(&self, min: [i64; 3], max: [i64; 3]) -> f64 {
+        let mut result = 0_f64;
+        for z in min[2].max(0)..=max[2].min(self.resolution[2] as i64 - 1) {
+            for y in min[1].max(0)..=max[1].min(self.resolution[1] as i64 - 1) {
+                for x in min[0].max(0)..=max[0].min(self.resolution[0] as i64 - 1) {
+                    result = result.max(self.value(x, y, z));
+                }
+            }
+        }
+        return result;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Vec3;
+    use crate::render::*;
+
+    const EPS: f64 = 1e-9;
+
+    #[test]
+    fn test_trilinear() {
+        //0 on the left half, 1 on the right, x fastest
+        let grid = DensityGrid::new([2, 1, 1], vec![0.0, 1.0]).unwrap();
+        assert!((grid.sample(Vec3::new(0.25, 0.5, 0.5)) - 0.0).abs() < EPS);
+        assert!((grid.sample(Vec3::new(0.5, 0.5, 0.5)) - 0.5).abs() < EPS);
+        assert!((grid.sample(Vec3::new(0.625, 0.1, 0.9)) - 0.75).abs() < EPS);
+        //clamped past the outer voxel centres
+        assert!((grid.sample(Vec3::new(1.0, 0.5, 0.5)) - 1.0).abs() < EPS);
+
+        let values: Vec<f32> = (0..27).map(|i| i as f32).collect();
+        let grid = DensityGrid::new([3, 3, 3], values).unwrap();
+        assert_eq!(grid.value(2, 1, 0), 5.0);
+        assert_eq!(grid.value(0, 0, 2), 18.0);
+        //linear data is reproduced exactly
+        let p = Vec3::new(0.4, 0.5, 0.7);
+        let expected = (3.0 * p.x() - 0.5) + 3.0 * (3.0 * p.y() - 0.5) + 9.0 * (3.0 * p.z() - 0.5);
+        assert!((grid.sample(p) - expected).abs() < 1e-6);
+        assert_eq!(grid.max_in([0, 0, 0], [1, 1, 1]), 13.0);
+        assert_eq!(grid.max_in([-4, -4, -4], [9, 9, 9]), 26.0);
+    }
+
+    #[test]
+    fn test_rejects_bad_grids() {
+        assert!(DensityGrid::new([2, 2, 0], Vec::new()).is_err());
+        assert!(DensityGrid::new([2, 2, 2], vec![1.0; 7]).is_err());
+        let message = DensityGrid::new([usize::MAX, 2, 2], Vec::new()).err().unwrap();
+        assert!(message.contains("too many"), "{}", message);
+        let message = DensityGrid::new([1, 1, 2], vec![1.0, -1.0]).err().unwrap();
+        assert!(message.contains("negative"), "{}", message);
+    }
+}
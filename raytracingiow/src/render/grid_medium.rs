@@ -0,0 +1,289 @@
+use crate::math::Aabb;
+use crate::math::Ray;
+use crate::math::Vec3;
+use crate::render::density_grid::DensityGrid;
+use crate::render::material::Material;
+use crate::render::medium::medium_salt;
+use crate::render::medium::RayRandom;
+use crate::render::renderable::HitRecord;
+use crate::render::renderable::Renderable;
+
+//voxels per side of a majorant cell
+const MAJORANT_CELL: usize = 8;
+//below this transmittance ratio tracking plays russian roulette instead of going on
+const ROULETTE_THRESHOLD: f64 = 0.1;
+
+//clouds, smoke and explosions whose density varies over a voxel grid stretched across a box. collisions
+//are found by delta tracking and shadow rays dimmed by ratio tracking, both against the largest density
+//of each coarse cell of the grid so thin parts are crossed in long steps
+pub struct GridMedium {
+    grid: DensityGrid,
+    bounds: Aabb,
+    //scales the grid values into collisions per unit of distance
+    density: f64,
+    phase_function: Box<dyn Material>,
+    majorants: Vec<f64>,
+    majorant_resolution: [usize; 3],
+    salt: u64,
+}
+
+impl GridMedium {
+    pub fn new(grid: DensityGrid, bounds: Aabb, density: f64, phase_function: Box<dyn Material>) -> Self {
+        let resolution = grid.resolution();
+        let majorant_resolution = [
+            resolution[0].div_ceil(MAJORANT_CELL),
+            resolution[1].div_ceil(MAJORANT_CELL),
+            resolution[2].div_ceil(MAJORANT_CELL),
+        ];
+
+        //trilinear lookups inside a cell also reach the voxels just past its sides
+        let mut majorants = Vec::new();
+        for z in 0..majorant_resolution[2] {
+            for y in 0..majorant_resolution[1] {
+                for x in 0..majorant_resolution[0] {
+                    let first = |c: usize| (c * MAJORANT_CELL) as i64 - 1;
+                    let last = |c: usize| ((c + 1) * MAJORANT_CELL) as i64;
+                    majorants.push(density * grid.max_in([first(x), first(y), first(z)], [last(x), last(y), last(z)]));
+                }
+            }
+        }
+
+        let (min, max) = (bounds.min(), bounds.max());
+        let salt = medium_salt(&[density, min.x(), min.y(), min.z(), max.x(), max.y(), max.z()]);
+        Self {
+            grid,
+            bounds,
+            density,
+            phase_function,
+            majorants,
+            majorant_resolution,
+            salt,
+        }
+    }
+
+    //collisions per unit of distance at a point inside the bounds
+    pub fn density_at(&self, p: Vec3) -> f64 {
+        return self.density * self.grid.sample((p - self.bounds.min()) / (self.bounds.max() - self.bounds.min()));
+    }
+
+    //walks the majorant cells the ray crosses between t_min and t_max in order, calling visit with the
+    //start, end and majorant of each stretch until it returns false
+    fn traverse(&self, ray: &Ray, t_min: f64, t_max: f64, mut visit: impl FnMut(f64, f64, f64) -> bool) {
+        let (start, end) = match self.bounds.intersect(ray, t_min, t_max) {
+            Some(interval) => interval,
+            None => return,
+        };
+
+        //majorant cell coordinates, the ray keeps its t
+        let cell_size = (self.bounds.max() - self.bounds.min()) * (MAJORANT_CELL as f64)
+            / Vec3::new(self.grid.resolution()[0] as f64, self.grid.resolution()[1] as f64, self.grid.resolution()[2] as f64);
+        let origin = (ray.origin() - self.bounds.min()) / cell_size;
+        let direction = ray.direction() / cell_size;
+
+        let entry = origin + start * direction;
+        let mut cell = [0_i64; 3];
+        let mut next = [f64::MAX; 3];
+        let mut delta = [f64::MAX; 3];
+        let mut step = [0_i64; 3];
+        for axis in 0..3 {
+            let i = axis as i32;
+            cell[axis] = (entry[i].floor() as i64).clamp(0, self.majorant_resolution[axis] as i64 - 1);
+            if direction[i] > 0_f64 {
+                next[axis] = (cell[axis] as f64 + 1_f64 - origin[i]) / direction[i];
+                delta[axis] = 1_f64 / direction[i];
+                step[axis] = 1;
+            } else if direction[i] < 0_f64 {
+                next[axis] = (cell[axis] as f64 - origin[i]) / direction[i];
+                delta[axis] = -1_f64 / direction[i];
+                step[axis] = -1;
+            }
+        }
+
+        let mut t = start;
+        while t < end {
+            let axis = (0..3).min_by(|&a, &b| next[a].total_cmp(&next[b])).unwrap();
+            let exit = next[axis].min(end);
+            let [x, y, z] = cell;
+            let [nx, ny, _] = self.majorant_resolution;
+            let majorant = self.majorants[(z as usize * ny + y as usize) * nx + x as usize];
+            if exit > t && !visit(t, exit, majorant) {
+                return;
+            }
+
+            t = exit;
+            cell[axis] += step[axis];
+            next[axis] += delta[axis];
+            if cell[axis] < 0 || cell[axis] >= self.majorant_resolution[axis] as i64 {
+                return;
+            }
+        }
+    }
+}
+
+impl Renderable for GridMedium {
+    //delta tracking, tentative collisions at the majorant rate are real with probability density / majorant
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let mut rng = RayRandom::new(ray, self.salt);
+        let speed = ray.direction().length();
+        let mut collision: Option<f64> = None;
+
+        self.traverse(ray, t_min, t_max, |start, end, majorant| {
+            if majorant <= 0_f64 {
+                return true;
+            }
+            let mut t = start;
+            loop {
+                t += rng.free_flight(majorant) / speed;
+                if t >= end {
+                    return true;
+                }
+                if rng.next_f64() * majorant < self.density_at(ray.point_at_distance(t)) {
+                    collision = Some(t);
+                    return false;
+                }
+            }
+        });
+
+        let t = collision?;
+        return Some(HitRecord {
+            t,
+            position: ray.point_at_distance(t),
+            //a volume has no surface, phase functions ignore the normal
+            normal: Vec3::new(1.0, 0.0, 0.0),
+            u: 0_f64,
+            v: 0_f64,
+            material: &*self.phase_function,
+        });
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        return Some(self.bounds);
+    }
+
+    //ratio tracking, every tentative collision keeps the null fraction 1 - density / majorant
+    fn transmittance(&self, ray: &Ray, t_min: f64, t_max: f64) -> f64 {
+        //a different stream from hit, which may be tested with the same ray
+        let mut rng = RayRandom::new(ray, !self.salt);
+        let speed = ray.direction().length();
+        let mut transmittance = 1_f64;
+
+        self.traverse(ray, t_min, t_max, |start, end, majorant| {
+            if majorant <= 0_f64 {
+                return true;
+            }
+            let mut t = start;
+            loop {
+                t += rng.free_flight(majorant) / speed;
+                if t >= end {
+                    return true;
+                }
+                let density = self.density_at(ray.point_at_distance(t));
+                transmittance *= 1_f64 - (density / majorant).min(1_f64);
+
+                if transmittance < ROULETTE_THRESHOLD {
+                    if rng.next_f64() < 0.5 {
+                        transmittance = 0_f64;
+                        return false;
+                    }
+                    transmittance *= 2_f64;
+                }
+            }
+        });
+
+        return transmittance;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Aabb;
+    use crate::math::Ray;
+    use crate::math::Vec3;
+    use crate::render::*;
+
+    use rand::Rng;
+    use rand::SeedableRng;
+
+    fn isotropic() -> Box<dyn Material> {
+        return Box::new(Isotropic::new(Vec3::new(0.9, 0.9, 0.9)));
+    }
+
+    //a 2 unit cube around (0 0 -5), empty on the left half and uniformly dense on the right
+    fn half_filled(density: f64) -> GridMedium {
+        let values: Vec<f32> = (0..32 * 16 * 16).map(|i| if i % 32 < 16 { 0.0 } else { 1.0 }).collect();
+        let grid = DensityGrid::new([32, 16, 16], values).unwrap();
+        let bounds = Aabb::new(Vec3::new(-1.0, -1.0, -6.0), Vec3::new(1.0, 1.0, -4.0));
+        return GridMedium::new(grid, bounds, density, isotropic());
+    }
+
+    fn jittered(rng: &mut rand::rngs::StdRng, x: f64) -> Ray {
+        return Ray::new(Vec3::new(x, 0.3, 1e-9 * rng.gen::<f64>()), Vec3::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn test_delta_tracking_matches_beer_lambert() {
+        //2 units of density 0.5 on the right, the ray gets through with probability exp(-1)
+        let medium = half_filled(0.5);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(17);
+        let n = 20000;
+        let mut passed = 0;
+        let mut ratio = 0.0;
+        for _ in 0..n {
+            let ray = jittered(&mut rng, 0.6);
+            match medium.hit(&ray, 0.001, f64::MAX) {
+                Some(hit) => assert!(hit.t >= 4.0 && hit.t <= 6.0 && medium.density_at(hit.position) > 0.0),
+                None => passed += 1,
+            }
+            ratio += medium.transmittance(&ray, 0.001, f64::MAX);
+        }
+        let expected = (-1.0_f64).exp();
+        assert!((passed as f64 / n as f64 - expected).abs() < 0.015, "{}", passed as f64 / n as f64);
+        assert!((ratio / n as f64 - expected).abs() < 0.015, "{}", ratio / n as f64);
+
+        //nothing ever collides in the empty half
+        for _ in 0..1000 {
+            let ray = jittered(&mut rng, -0.6);
+            assert!(medium.hit(&ray, 0.001, f64::MAX).is_none());
+            assert_eq!(medium.transmittance(&ray, 0.001, f64::MAX), 1.0);
+        }
+    }
+
+    #[test]
+    fn test_slanted_rays_cross_every_cell() {
+        //a smooth blob, a diagonal ray's transmittance must match integrating the density along it
+        let n = 24;
+        let mut values = Vec::new();
+        for z in 0..n {
+            for y in 0..n {
+                for x in 0..n {
+                    let p = Vec3::new(x as f64, y as f64, z as f64) / (n as f64 - 1.0) - Vec3::new(0.5, 0.5, 0.5);
+                    values.push((1.0 - 3.0 * p.length_squared()).max(0.0) as f32);
+                }
+            }
+        }
+        let bounds = Aabb::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(3.0, 2.0, 4.0));
+        let medium = GridMedium::new(DensityGrid::new([n, n, n], values).unwrap(), bounds, 0.5, isotropic());
+
+        let origin = Vec3::new(-1.0, -0.5, -1.0);
+        let direction = Vec3::new(4.2, 3.1, 6.0);
+        let steps = 20000;
+        let optical_depth: f64 = (0..steps)
+            .map(|i| medium.density_at(origin + ((i as f64 + 0.5) / steps as f64) * direction) * direction.length() / steps as f64)
+            .sum();
+        let expected = (-optical_depth).exp();
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(18);
+        let rays = 20000;
+        let (mut passed, mut ratio) = (0, 0.0);
+        for _ in 0..rays {
+            let ray = Ray::new(origin + Vec3::new(1e-9 * rng.gen::<f64>(), 0.0, 0.0), direction);
+            if medium.hit(&ray, 0.0, 1.0).is_none() {
+                passed += 1;
+            }
+            ratio += medium.transmittance(&ray, 0.0, 1.0);
+        }
+        assert!(expected > 0.2 && expected < 0.8, "{}", expected);
+        assert!((passed as f64 / rays as f64 - expected).abs() < 0.015, "{} {}", passed as f64 / rays as f64, expected);
+        assert!((ratio / rays as f64 - expected).abs() < 0.015, "{} {}", ratio / rays as f64, expected);
+    }
+}
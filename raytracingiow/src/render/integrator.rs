@@ -0,0 +1,341 @@
+use crate::math::Ray;
+use crate::math::Vec3;
+use crate::render::light::LightList;
+use crate::render::renderable::Renderable;
+use crate::render::renderer::RenderSettings;
+
+use rand::RngCore;
+use std::fmt;
+use std::str::FromStr;
+
+//computes the color seen along a ray
+pub type ColorFn = fn(&Ray, &dyn Renderable) -> Vec3;
+
+#[derive(Copy, Clone)]
+pub enum Integrator {
+    //sky gradient only, chapter 3
+    Background,
+    //flat red sphere, chapter 4
+    Sphere,
+    //surface normals as colors, chapter 5
+    Normals,
+    //recursive material scattering, chapter 7
+    Path,
+    //path tracing that also samples a light at every diffuse hit, weighting
+    //light and material samples with multiple importance sampling
+    PathNee,
+    Custom(ColorFn),
+}
+
+//what a ray sees when it leaves the scene
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Background {
+    //the book's white to blue gradient
+    Sky,
+    //a constant color, black for scenes lit only by emissive materials
+    Color(Vec3),
+}
+
+impl Background {
+    pub fn color(&self, ray: &Ray) -> Vec3 {
+        return match self {
+            Background::Sky => sky_color(ray),
+            Background::Color(color) => *color,
+        };
+    }
+}
+
+impl Integrator {
+    pub fn color(&self, ray: &Ray, world: &dyn Renderable, lights: &LightList, settings: &RenderSettings, rng: &mut dyn RngCore) -> Vec3 {
+        return match self {
+            Integrator::Background => settings.background.color(ray),
+            Integrator::Sphere => get_color_chapter_4(ray, world),
+            Integrator::Normals => get_color_chapter_5(ray, world),
+            Integrator::Path => get_color_chapter_7_tail(ray, world, &settings.background, 0, settings.max_bounces, rng),
+            Integrator::PathNee => get_color_nee(ray, world, lights, &settings.background, settings.max_bounces, rng),
+            Integrator::Custom(color_fn) => color_fn(ray, world),
+        };
+    }
+}
+
+impl FromStr for Integrator {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        return match s {
+            "background" => Ok(Integrator::Background),
+            "sphere" => Ok(Integrator::Sphere),
+            "normals" => Ok(Integrator::Normals),
+            "path" => Ok(Integrator::Path),
+            "path-nee" => Ok(Integrator::PathNee),
+            _ => Err(format!(
+                "unknown integrator '{}', expected one of: background, sphere, normals, path, path-nee",
+                s
+            )),
+        };
+    }
+}
+
+impl fmt::Debug for Integrator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Integrator::Background => "background",
+            Integrator::Sphere => "sphere",
+            Integrator::Normals => "normals",
+            Integrator::Path => "path",
+            Integrator::PathNee => "path-nee",
+            Integrator::Custom(_) => "custom",
+        };
+        return write!(f, "{}", name);
+    }
+}
+
+//chapter 3
+//ignores world parameter, creates own world objects
+pub fn get_bg_color(ray: &Ray, _world: &dyn Renderable) -> Vec3 {
+    return sky_color(ray);
+}
+
+fn sky_color(ray: &Ray) -> Vec3 {
+    let white: Vec3 = Vec3::new(1.0, 1.0, 1.0);
+    let blue: Vec3 = Vec3::new(0.5, 0.7, 1.0);
+
+    let unit_direction = ray.direction().unit_vector();
+    let t = 0.5 * (unit_direction.y() + 1.0); // 0 to 1.0
+                                              //lerp
+    return (1.0 - t) * white + t * blue;
+}
+
+//chapter 4
+fn hit_sphere(center: &Vec3, radius: f64, ray: &Ray) -> bool {
+    //t*t*dot(B, B) + 2*t*dot(B,A-C) + dot(A-C,A-C) - R*R = 0
+
+    let ac = ray.origin() - *center;
+    let a = ray.direction().dot(ray.direction());
+    let b = 2.0 * ray.direction().dot(ac);
+    let c = ac.dot(ac) - radius * radius;
+
+    let discriminant = b.powi(2) - 4_f64 * a * c;
+
+    return discriminant > 0_f64;
+}
+
+pub fn get_color_chapter_4(ray: &Ray, world: &dyn Renderable) -> Vec3 {
+    let center = Vec3::new(0_f64, 0_f64, -1_f64);
+    let red = Vec3::new(1_f64, 0_f64, 0_f64);
+
+    if hit_sphere(&center, 0.5, ray) {
+        return red;
+    } else {
+        return get_bg_color(ray, world);
+    }
+}
+
+//chapter 5
+pub fn get_color_chapter_5(ray: &Ray, world: &dyn Renderable) -> Vec3 {
+    match world.hit(ray, 0_f64, f64::MAX) {
+        Some(hit_record) => {
+            //hack, map surface_normal from [-1,1] xyz into range [0,1] rgb for visualization
+            let surface_normal = hit_record.normal;
+            return 0.5 * Vec3::new(
+                    surface_normal.x() + 1_f64,
+                    surface_normal.y() + 1_f64,
+                    surface_normal.z() + 1_f64,
+                );
+        }
+        None => return get_bg_color(ray, world),
+    }
+}
+
+//chapter 7
+pub fn get_color_chapter_7(ray: &Ray, world: &dyn Renderable) -> Vec3 {
+    return get_color_chapter_7_tail(ray, world, &Background::Sky, 0, 50, &mut rand::thread_rng());
+}
+
+fn get_color_chapter_7_tail(ray: &Ray, world: &dyn Renderable, background: &Background, num_bounces: u32, max_bounces: u32, rng: &mut dyn RngCore) -> Vec3 {
+
+    //add a little to the minimum to fix floating point inaccuracies
+    match world.hit(ray, 0.001_f64, f64::MAX) {
+        Some(hit_record) => {
+            let emitted = hit_record.material.emitted(ray, &hit_record);
+            match hit_record.material.scatter(ray, &hit_record, rng) {
+                Some(scatter_record) => {
+                    let attenuation = scatter_record.attenuation;
+                    let scattered = scatter_record.scattered;
+                    //recurse
+                    if num_bounces < max_bounces {
+                        return emitted + attenuation * get_color_chapter_7_tail(&scattered, world, background, num_bounces + 1, max_bounces, rng);
+                    } else {
+                        return emitted;
+                    }
+                }
+                None => return emitted
+            }
+        }
+        None => return background.color(ray),
+    }
+}
+
+//weight of a sample from the strategy with density a when another with density b could also produce it
+fn power_heuristic(a: f64, b: f64) -> f64 {
+    let (a2, b2) = (a * a, b * b);
+    if a2 + b2 <= 0_f64 {
+        return 0_f64;
+    }
+    return a2 / (a2 + b2);
+}
+
+fn is_black(c: Vec3) -> bool {
+    return c.r() == 0_f64 && c.g() == 0_f64 && c.b() == 0_f64;
+}
+
+//next event estimation, iterative so long paths don't grow the stack
+fn get_color_nee(ray: &Ray, world: &dyn Renderable, lights: &LightList, background: &Background, max_bounces: u32, rng: &mut dyn RngCore) -> Vec3 {
+    let mut radiance = Vec3::new(0.0, 0.0, 0.0);
+    let mut throughput = Vec3::new(1.0, 1.0, 1.0);
+    let mut ray = Ray::new(ray.origin(), ray.direction()).with_time(ray.time());
+    //density the material sampled the current ray with, None for camera rays and specular bounces
+    //whose emission hits can't be produced by light sampling
+    let mut scatter_pdf: Option<f64> = None;
+
+    for num_bounces in 0..=max_bounces {
+        let hit_record = match world.hit(&ray, 0.001_f64, f64::MAX) {
+            Some(hit_record) => hit_record,
+            None => {
+                radiance += throughput * background.color(&ray);
+                break;
+            }
+        };
+        let material = hit_record.material;
+
+        let emitted = material.emitted(&ray, &hit_record);
+        if !is_black(emitted) {
+            let weight = match scatter_pdf {
+                Some(pdf) => power_heuristic(pdf, lights.pdf(ray.origin(), ray.direction().unit_vector())),
+                None => 1_f64,
+            };
+            radiance += weight * throughput * emitted;
+        }
+
+        if num_bounces == max_bounces {
+            break;
+        }
+
+        if !material.is_specular() {
+            if let Some(sample) = lights.sample(hit_record.position, rng) {
+                let f = material.eval(&ray, &hit_record, sample.direction);
+                if !is_black(f) {
+                    //surfaces in front of the sampled point block it, media dim it by ratio tracking
+                    let shadow = Ray::new(hit_record.position, sample.direction).with_time(ray.time());
+                    let near = sample.distance * (1_f64 - 1e-6) - 1e-6;
+                    let visibility = world.transmittance(&shadow, 0.001_f64, near);
+                    if visibility > 0_f64 {
+                        if let Some(light_hit) = world.hit(&shadow, near, sample.distance * (1_f64 + 1e-6) + 1e-6) {
+                            let light = light_hit.material.emitted(&shadow, &light_hit);
+                            let weight = power_heuristic(sample.pdf, material.pdf(&ray, &hit_record, sample.direction));
+                            radiance += (visibility * weight / sample.pdf) * throughput * f * light;
+                        }
+                    }
+                }
+            }
+        }
+
+        match material.scatter(&ray, &hit_record, rng) {
+            Some(scatter_record) => {
+                let scattered = scatter_record.scattered;
+                scatter_pdf = if material.is_specular() {
+                    None
+                } else {
+                    Some(material.pdf(&ray, &hit_record, scattered.direction()))
+                };
+                throughput *= scatter_record.attenuation;
+                ray = scattered;
+            }
+            None => break,
+        }
+    }
+
+    return radiance;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Ray;
+    use crate::math::Vec3;
+    use crate::render::*;
+
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_integrator_from_str() {
+        assert!(matches!("path".parse::<Integrator>(), Ok(Integrator::Path)));
+        assert!(matches!("normals".parse::<Integrator>(), Ok(Integrator::Normals)));
+        assert!(matches!("path-nee".parse::<Integrator>(), Ok(Integrator::PathNee)));
+        assert!("whitted".parse::<Integrator>().is_err());
+    }
+
+    #[test]
+    fn test_path_adds_emission() {
+        let settings = RenderSettings {
+            background: Background::Color(Vec3::new(0.0, 0.0, 0.0)),
+            ..RenderSettings::default()
+        };
+        let world: Vec<Sphere> = vec![
+            Sphere::new(Vec3::new(0.0, 0.0, -2.0), 0.5, Box::new(DiffuseLight::new(Vec3::new(3.0, 2.0, 1.0)))),
+            Sphere::new(Vec3::new(0.0, 0.0, 2.0), 0.5, Box::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)))),
+        ];
+        let lights = LightList::new(&world);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(5);
+
+        let at_light = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        assert_eq!(Integrator::Path.color(&at_light, &world, &lights, &settings, &mut rng), Vec3::new(3.0, 2.0, 1.0));
+
+        //the diffuse sphere is only lit by the light, never brighter than half of it
+        let at_diffuse = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        let mut total = Vec3::new(0.0, 0.0, 0.0);
+        for _ in 0..1000 {
+            let color = Integrator::Path.color(&at_diffuse, &world, &lights, &settings, &mut rng);
+            assert!(color.r() <= 1.5 + 1e-9);
+            total += color;
+        }
+        assert!(total.r() > 0.0);
+
+        let at_nothing = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(Integrator::Path.color(&at_nothing, &world, &lights, &settings, &mut rng), Vec3::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_nee_matches_path() {
+        //a small light straight above a diffuse ground, the analytic answer is albedo * emit * (r / d)^2
+        let settings = RenderSettings {
+            background: Background::Color(Vec3::new(0.0, 0.0, 0.0)),
+            ..RenderSettings::default()
+        };
+        let world: Vec<Sphere> = vec![
+            Sphere::new(Vec3::new(0.0, 3.0, 0.0), 1.0, Box::new(DiffuseLight::new(Vec3::new(4.0, 4.0, 4.0)))),
+            Sphere::new(Vec3::new(0.0, -100.0, 0.0), 100.0, Box::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)))),
+        ];
+        let lights = LightList::new(&world);
+        let ray = Ray::new(Vec3::new(0.0, 1.0, 2.0), Vec3::new(0.0, -1.0, -2.0));
+        let expected = 0.5 * 4.0 / 9.0;
+
+        let estimate = |integrator: Integrator, seed: u64| -> (f64, f64) {
+            let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+            let n = 20000;
+            let (mut sum, mut sum_squared) = (0.0, 0.0);
+            for _ in 0..n {
+                let v = integrator.color(&ray, &world, &lights, &settings, &mut rng).r();
+                sum += v;
+                sum_squared += v * v;
+            }
+            let mean = sum / n as f64;
+            return (mean, sum_squared / n as f64 - mean * mean);
+        };
+
+        let (path_mean, path_variance) = estimate(Integrator::Path, 1);
+        let (nee_mean, nee_variance) = estimate(Integrator::PathNee, 2);
+        assert!((path_mean - expected).abs() < 0.02, "path {}", path_mean);
+        assert!((nee_mean - expected).abs() < 0.005, "nee {}", nee_mean);
+        assert!(nee_variance < path_variance / 10.0, "{} vs {}", nee_variance, path_variance);
+    }
+}
@@ -0,0 +1,286 @@
+use crate::math::Aabb;
+use crate::math::Mat4;
+use crate::math::Ray;
+use crate::math::Vec3;
+use crate::render::renderable::HitRecord;
+use crate::render::renderable::Renderable;
+use crate::render::transformed::transform_box;
+
+use std::sync::Arc;
+
+//boxes sampled along every segment between two keyframes when bounding the motion
+const BOX_STEPS: usize = 32;
+
+//placement of an object at one moment, applied as scale, then rotation, then translation
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Keyframe {
+    pub time: f64,
+    pub translation: Vec3,
+    //unit quaternion as [x, y, z, w]
+    pub rotation: [f64; 4],
+    pub scale: Vec3,
+}
+
+impl Keyframe {
+    pub fn new(time: f64) -> Self {
+        Self {
+            time,
+            translation: Vec3::new(0.0, 0.0, 0.0),
+            rotation: [0.0, 0.0, 0.0, 1.0],
+            scale: Vec3::new(1.0, 1.0, 1.0),
+        }
+    }
+
+    pub fn with_translation(mut self, translation: Vec3) -> Self {
+        self.translation = translation;
+        return self;
+    }
+
+    //turns after any rotation already given, so rotations about x, y and z can be chained
+    pub fn with_rotation(mut self, axis: Vec3, degrees: f64) -> Self {
+        let axis = axis.unit_vector();
+        let half = degrees.to_radians() / 2_f64;
+        let s = half.sin();
+        self.rotation = quaternion_product([axis.x() * s, axis.y() * s, axis.z() * s, half.cos()], self.rotation);
+        return self;
+    }
+
+    pub fn with_scale(mut self, scale: Vec3) -> Self {
+        self.scale = scale;
+        return self;
+    }
+
+    fn matrix(&self) -> Mat4 {
+        return Mat4::translation(self.translation) * Mat4::from_quaternion(self.rotation) * Mat4::scaling(self.scale);
+    }
+
+    //translation and scale move linearly, the rotation turns at a constant rate
+    fn interpolate(&self, next: &Keyframe, f: f64) -> Keyframe {
+        return Keyframe {
+            time: self.time + f * (next.time - self.time),
+            translation: self.translation + f * (next.translation - self.translation),
+            rotation: slerp(self.rotation, next.rotation, f),
+            scale: self.scale + f * (next.scale - self.scale),
+        };
+    }
+}
+
+//rotation a applied after rotation b
+fn quaternion_product(a: [f64; 4], b: [f64; 4]) -> [f64; 4] {
+    let [ax, ay, az, aw] = a;
+    let [bx, by, bz, bw] = b;
+    return [
+        aw * bx + ax * bw + ay * bz - az * by,
+        aw * by - ax * bz + ay * bw + az * bx,
+        aw * bz + ax * by - ay * bx + az * bw,
+        aw * bw - ax * bx - ay * by - az * bz,
+    ];
+}
+
+//the shorter way round between two unit quaternions
+fn slerp(a: [f64; 4], b: [f64; 4], f: f64) -> [f64; 4] {
+    let mut dot: f64 = (0..4).map(|i| a[i] * b[i]).sum();
+    let mut b = b;
+    if dot < 0_f64 {
+        b = [-b[0], -b[1], -b[2], -b[3]];
+        dot = -dot;
+    }
+
+    //nearly the same rotation, a plain blend avoids dividing by sin(0)
+    let (wa, wb) = if dot > 0.9995 {
+        (1_f64 - f, f)
+    } else {
+        let theta = dot.acos();
+        (((1_f64 - f) * theta).sin() / theta.sin(), (f * theta).sin() / theta.sin())
+    };
+
+    let q = [wa * a[0] + wb * b[0], wa * a[1] + wb * b[1], wa * a[2] + wb * b[2], wa * a[3] + wb * b[3]];
+    let length = q.iter().map(|c| c * c).sum::<f64>().sqrt();
+    return [q[0] / length, q[1] / length, q[2] / length, q[3] / length];
+}
+
+//angle a rotation turns through on the way to another
+fn rotation_between(a: [f64; 4], b: [f64; 4]) -> f64 {
+    let dot: f64 = (0..4).map(|i| a[i] * b[i]).sum();
+    return 2_f64 * dot.abs().min(1_f64).acos();
+}
+
+//an object whose placement is interpolated between keyframes at the time of each ray, held at the first and
+//last keyframe outside them. like moving spheres, its lights are only reached by following paths
+pub struct Keyframed {
+    object: Arc<dyn Renderable>,
+    keyframes: Vec<Keyframe>,
+    bbox: Option<Aabb>,
+}
+
+impl Keyframed {
+    pub fn new(object: Arc<dyn Renderable>, keyframes: Vec<Keyframe>) -> Result<Self, String> {
+        if keyframes.is_empty() {
+            return Err("needs at least one keyframe".to_string());
+        }
+        for pair in keyframes.windows(2) {
+            if pair[1].time <= pair[0].time {
+                return Err(format!("keyframe times must increase, {} comes after {}", pair[1].time, pair[0].time));
+            }
+        }
+        for keyframe in &keyframes {
+            let s = keyframe.scale;
+            if s.x() * s.y() * s.z() == 0_f64 {
+                return Err(format!("keyframe at time {} has a zero scale", keyframe.time));
+            }
+        }
+
+        let bbox = object.bounding_box().map(|b| motion_box(&b, &keyframes));
+        return Ok(Self { object, keyframes, bbox });
+    }
+
+    pub fn keyframe_at(&self, time: f64) -> Keyframe {
+        let first = &self.keyframes[0];
+        let last = &self.keyframes[self.keyframes.len() - 1];
+        if time <= first.time {
+            return *first;
+        }
+        if time >= last.time {
+            return *last;
+        }
+
+        let next = self.keyframes.iter().position(|k| k.time > time).unwrap();
+        let (a, b) = (&self.keyframes[next - 1], &self.keyframes[next]);
+        return a.interpolate(b, (time - a.time) / (b.time - a.time));
+    }
+}
+
+//boxes at closely spaced times along every segment, grown by how far a corner can bulge out
+//of a straight line while turning between two of them
+fn motion_box(bbox: &Aabb, keyframes: &[Keyframe]) -> Aabb {
+    let mut result = transform_box(bbox, &keyframes[0].matrix());
+    let reach = bbox.min().length().max(bbox.max().length()) * Vec3::new(1.0, 1.0, 1.0).length();
+
+    for pair in keyframes.windows(2) {
+        let (a, b) = (&pair[0], &pair[1]);
+        for step in 1..=BOX_STEPS {
+            let keyframe = a.interpolate(b, step as f64 / BOX_STEPS as f64);
+            result = result.surrounding(&transform_box(bbox, &keyframe.matrix()));
+        }
+
+        let max_scale = [a.scale, b.scale].iter().map(|s| s.x().abs().max(s.y().abs()).max(s.z().abs())).fold(0_f64, f64::max);
+        let step_angle = rotation_between(a.rotation, b.rotation) / BOX_STEPS as f64;
+        let bulge = reach * max_scale * (1_f64 - (step_angle / 2_f64).cos());
+        let margin = Vec3::new(bulge, bulge, bulge);
+        result = Aabb::new(result.min() - margin, result.max() + margin);
+    }
+    return result;
+}
+
+impl Renderable for Keyframed {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let to_object = self.keyframe_at(ray.time()).matrix().inverse()?;
+        let local = Ray::new(to_object.transform_point(ray.origin()), to_object.transform_vector(ray.direction())).with_time(ray.time());
+        let hit = self.object.hit(&local, t_min, t_max)?;
+
+        return Some(HitRecord {
+            position: ray.point_at_distance(hit.t),
+            normal: to_object.transpose().transform_vector(hit.normal).unit_vector(),
+            ..hit
+        });
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        return self.bbox;
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f64, t_max: f64) -> f64 {
+        return match self.keyframe_at(ray.time()).matrix().inverse() {
+            Some(to_object) => {
+                let local = Ray::new(to_object.transform_point(ray.origin()), to_object.transform_vector(ray.direction())).with_time(ray.time());
+                self.object.transmittance(&local, t_min, t_max)
+            }
+            None => 1_f64,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Ray;
+    use crate::math::Vec3;
+    use crate::render::*;
+
+    use rand::Rng;
+    use rand::SeedableRng;
+    use std::sync::Arc;
+
+    const EPS: f64 = 1e-9;
+
+    fn quad() -> Arc<dyn Renderable> {
+        //1 by 1 facing +z, its corner at the origin
+        return Arc::new(XYRect::new(0.0, 1.0, 0.0, 1.0, 0.0, Box::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)))));
+    }
+
+    #[test]
+    fn test_interpolates_between_keyframes() {
+        let keyframes = vec![
+            Keyframe::new(0.0).with_translation(Vec3::new(0.0, 0.0, -5.0)),
+            Keyframe::new(1.0).with_translation(Vec3::new(4.0, 0.0, -5.0)).with_rotation(Vec3::new(0.0, 1.0, 0.0), 90.0),
+            Keyframe::new(2.0)
+                .with_translation(Vec3::new(4.0, 0.0, -5.0))
+                .with_rotation(Vec3::new(0.0, 1.0, 0.0), 90.0)
+                .with_scale(Vec3::new(2.0, 2.0, 2.0)),
+        ];
+        let animated = Keyframed::new(quad(), keyframes).unwrap();
+        let ray = |x: f64, y: f64, time: f64| Ray::new(Vec3::new(x, y, 0.0), Vec3::new(0.0, 0.0, -1.0)).with_time(time);
+
+        let hit = animated.hit(&ray(0.5, 0.5, 0.0), 0.0, f64::MAX).unwrap();
+        assert!((hit.t - 5.0).abs() < EPS && (hit.normal - Vec3::new(0.0, 0.0, 1.0)).length() < EPS);
+        assert!(animated.hit(&ray(0.5, 0.5, -3.0), 0.0, f64::MAX).is_some());
+
+        //half way, moved by 2 and turned by 45 degrees
+        let keyframe = animated.keyframe_at(0.5);
+        assert!((keyframe.translation - Vec3::new(2.0, 0.0, -5.0)).length() < EPS);
+        let hit = animated.hit(&ray(2.3, 0.5, 0.5), 0.0, f64::MAX).unwrap();
+        let half = std::f64::consts::FRAC_1_SQRT_2;
+        assert!((hit.normal - Vec3::new(half, 0.0, half)).length() < EPS);
+        assert!((hit.t - (5.0 + 0.3)).abs() < EPS);
+
+        //the rotation is kept while the scale grows, the quad stands edge on along the ray
+        assert!(animated.hit(&ray(4.0, 1.5, 2.0), 0.0, f64::MAX).is_none());
+        let hit = animated.hit(&Ray::new(Vec3::new(10.0, 1.5, -6.5), Vec3::new(-1.0, 0.0, 0.0)).with_time(5.0), 0.0, f64::MAX).unwrap();
+        assert!((hit.t - 6.0).abs() < EPS && (hit.normal - Vec3::new(1.0, 0.0, 0.0)).length() < EPS);
+    }
+
+    #[test]
+    fn test_box_covers_motion() {
+        let keyframes = vec![
+            Keyframe::new(0.0).with_translation(Vec3::new(1.0, 2.0, 3.0)),
+            Keyframe::new(1.0)
+                .with_rotation(Vec3::new(0.0, 0.0, 1.0), 170.0)
+                .with_rotation(Vec3::new(1.0, 0.0, 0.0), 60.0)
+                .with_scale(Vec3::new(3.0, 1.0, 1.0)),
+        ];
+        let sphere: Arc<dyn Renderable> = Arc::new(Sphere::new(Vec3::new(2.0, 0.0, 0.0), 0.5, Box::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)))));
+        let animated = Keyframed::new(sphere, keyframes).unwrap();
+        let bbox = animated.bounding_box().unwrap();
+
+        //every point the sphere passes through lies in the box
+        let mut rng = rand::rngs::StdRng::seed_from_u64(14);
+        for _ in 0..2000 {
+            let keyframe = animated.keyframe_at(rng.gen::<f64>());
+            let matrix = crate::math::Mat4::translation(keyframe.translation)
+                * crate::math::Mat4::from_quaternion(keyframe.rotation)
+                * crate::math::Mat4::scaling(keyframe.scale);
+            let p = matrix.transform_point(Vec3::new(2.0, 0.0, 0.0) + 0.5 * random_unit_vector(&mut rng));
+            for axis in 0..3 {
+                assert!(p[axis] >= bbox.min()[axis] - EPS && p[axis] <= bbox.max()[axis] + EPS, "{:?} outside {:?}", p, bbox);
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_keyframes() {
+        let message = Keyframed::new(quad(), vec![Keyframe::new(1.0), Keyframe::new(1.0)]).err().unwrap();
+        assert!(message.contains("increase"), "{}", message);
+        let message = Keyframed::new(quad(), vec![Keyframe::new(0.0).with_scale(Vec3::new(1.0, 0.0, 1.0))]).err().unwrap();
+        assert!(message.contains("zero scale"), "{}", message);
+        assert!(Keyframed::new(quad(), Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,194 @@
+use crate::math::Vec3;
+use crate::render::material::random_unit_vector;
+use crate::render::renderable::Renderable;
+
+use rand::Rng;
+use rand::RngCore;
+use std::f64::consts::PI;
+
+//direction from a shading point towards a light
+pub struct LightSample {
+    //unit length
+    pub direction: Vec3,
+    //along direction to the sampled point on the light
+    pub distance: f64,
+    //solid angle density of picking direction
+    pub pdf: f64,
+}
+
+//a shape that can pick points on itself as seen from a shading point
+pub trait Light: Send + Sync {
+    fn sample(&self, origin: Vec3, rng: &mut dyn RngCore) -> Option<LightSample>;
+
+    //solid angle density of sample choosing the unit direction, 0 if it misses the light
+    fn pdf(&self, origin: Vec3, direction: Vec3) -> f64;
+}
+
+//every emissive object of a world, borrowed for the length of a render
+pub struct LightList<'a> {
+    lights: Vec<&'a dyn Light>,
+}
+
+impl<'a> LightList<'a> {
+    pub fn new(world: &'a dyn Renderable) -> Self {
+        let mut lights: Vec<&'a dyn Light> = Vec::new();
+        world.collect_lights(&mut lights);
+        return Self { lights };
+    }
+
+    pub fn empty() -> Self {
+        return Self { lights: Vec::new() };
+    }
+
+    pub fn len(&self) -> usize {
+        return self.lights.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.lights.is_empty();
+    }
+
+    //picks a light uniformly, then a direction towards it. the pdf is that of the whole list
+    //so a direction covered by several lights is weighted correctly
+    pub fn sample(&self, origin: Vec3, rng: &mut dyn RngCore) -> Option<LightSample> {
+        if self.lights.is_empty() {
+            return None;
+        }
+
+        let index = ((rng.gen::<f64>() * self.lights.len() as f64) as usize).min(self.lights.len() - 1);
+        let sample = self.lights[index].sample(origin, rng)?;
+        let pdf = self.pdf(origin, sample.direction);
+        if pdf <= 0_f64 {
+            return None;
+        }
+
+        return Some(LightSample { pdf, ..sample });
+    }
+
+    pub fn pdf(&self, origin: Vec3, direction: Vec3) -> f64 {
+        if self.lights.is_empty() {
+            return 0_f64;
+        }
+
+        let total: f64 = self.lights.iter().map(|light| light.pdf(origin, direction)).sum();
+        return total / self.lights.len() as f64;
+    }
+}
+
+//solid angle density of reaching point on a surface from origin, given the area density 1 / area
+pub fn area_to_solid_angle(origin: Vec3, point: Vec3, normal: Vec3, area: f64) -> f64 {
+    let to_point = point - origin;
+    let distance_squared = to_point.length_squared();
+    let cosine = normal.dot(to_point.unit_vector()).abs();
+    if cosine < 1e-12 || distance_squared < 1e-12 {
+        return 0_f64;
+    }
+    return distance_squared / (cosine * area);
+}
+
+//light sample towards a point picked with the given solid angle density
+pub fn sample_towards(origin: Vec3, point: Vec3, pdf: f64) -> Option<LightSample> {
+    if pdf <= 0_f64 {
+        return None;
+    }
+
+    let to_point = point - origin;
+    return Some(LightSample {
+        direction: to_point.unit_vector(),
+        distance: to_point.length(),
+        pdf,
+    });
+}
+
+//two unit vectors completing w into a right handed orthonormal basis
+pub fn orthonormal_basis(w: Vec3) -> (Vec3, Vec3) {
+    let a = if w.x().abs() > 0.9 { Vec3::new(0.0, 1.0, 0.0) } else { Vec3::new(1.0, 0.0, 0.0) };
+    let v = w.cross(a).unit_vector();
+    let u = v.cross(w);
+    return (u, v);
+}
+
+//solid angle subtended by a sphere of the given radius at squared distance d2, as 1 - cos(theta_max)
+pub fn cone_extent(radius: f64, d2: f64) -> f64 {
+    let sin2 = radius * radius / d2;
+    let cos_max = (1_f64 - sin2).max(0_f64).sqrt();
+    //the same as 1 - cos_max without the cancellation for small, distant lights
+    return sin2 / (1_f64 + cos_max);
+}
+
+//uniform direction inside the cone around the unit vector w with the given 1 - cos(theta_max)
+pub fn sample_cone(w: Vec3, extent: f64, rng: &mut dyn RngCore) -> Vec3 {
+    let (u, v) = orthonormal_basis(w);
+    let one_minus_cos = rng.gen::<f64>() * extent;
+    let cos_theta = 1_f64 - one_minus_cos;
+    let sin_theta = (one_minus_cos * (2_f64 - one_minus_cos)).max(0_f64).sqrt();
+    let phi = 2_f64 * PI * rng.gen::<f64>();
+    return (sin_theta * phi.cos()) * u + (sin_theta * phi.sin()) * v + cos_theta * w;
+}
+
+//uniform point on a sphere's surface
+pub fn sample_sphere_area(center: Vec3, radius: f64, rng: &mut dyn RngCore) -> Vec3 {
+    return center + radius * random_unit_vector(rng);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Vec3;
+    use crate::render::*;
+
+    use rand::SeedableRng;
+    use std::f64::consts::PI;
+
+    fn light_sphere(center: Vec3, radius: f64) -> Sphere {
+        return Sphere::new(center, radius, Box::new(DiffuseLight::new(Vec3::new(1.0, 1.0, 1.0))));
+    }
+
+    #[test]
+    fn test_collects_emissive_objects() {
+        let world: Vec<Sphere> = vec![
+            light_sphere(Vec3::new(0.0, 2.0, 0.0), 0.5),
+            Sphere::new(Vec3::new(0.0, 0.0, 0.0), 1.0, Box::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)))),
+            light_sphere(Vec3::new(3.0, 0.0, 0.0), 0.2),
+        ];
+        assert_eq!(LightList::new(&world).len(), 2);
+        assert_eq!(LightList::new(&Bvh::new(world)).len(), 2);
+        assert!(LightList::empty().sample(Vec3::new(0.0, 0.0, 0.0), &mut rand::thread_rng()).is_none());
+    }
+
+    #[test]
+    fn test_sphere_samples_from_outside() {
+        let sphere = light_sphere(Vec3::new(0.0, 0.0, -4.0), 1.0);
+        let origin = Vec3::new(0.0, 0.0, 0.0);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(9);
+
+        let expected = 1.0 / (2.0 * PI * (1.0 - (15_f64 / 16.0).sqrt()));
+        for _ in 0..1000 {
+            let sample = sphere.sample(origin, &mut rng).unwrap();
+            assert!((sample.pdf - expected).abs() < 1e-6 * expected);
+            assert!((sphere.pdf(origin, sample.direction) - expected).abs() < 1e-6 * expected);
+
+            //the sampled point is the visible side of the sphere
+            let point = origin + sample.distance * sample.direction;
+            assert!(((point - Vec3::new(0.0, 0.0, -4.0)).length() - 1.0).abs() < 1e-9);
+            assert!(point.z() >= -4.0);
+        }
+        assert_eq!(sphere.pdf(origin, Vec3::new(0.0, 1.0, 0.0)), 0.0);
+    }
+
+    #[test]
+    fn test_sphere_samples_from_inside() {
+        //the sphere covers every direction, so 1 / pdf averages to the full 4 pi
+        let sphere = light_sphere(Vec3::new(0.0, 0.0, 0.0), 2.0);
+        let origin = Vec3::new(0.5, -0.3, 0.2);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(10);
+
+        let n = 100000;
+        let mut total = 0.0;
+        for _ in 0..n {
+            let sample = sphere.sample(origin, &mut rng).unwrap();
+            assert!((sphere.pdf(origin, sample.direction) - sample.pdf).abs() < 1e-6 * sample.pdf);
+            total += 1.0 / sample.pdf;
+        }
+        assert!((total / n as f64 - 4.0 * PI).abs() < 0.1, "{}", total / n as f64);
+    }
+}
@@ -0,0 +1,416 @@
+use crate::math::Ray;
+use crate::math::Vec3;
+use crate::render::light::orthonormal_basis;
+use crate::render::material::material::Material;
+use crate::render::material::material::ScatterRecord;
+use crate::render::material::material::reflect;
+use crate::render::renderable::HitRecord;
+use crate::render::texture::ConstantTexture;
+use crate::render::texture::Texture;
+
+use rand::Rng;
+use rand::RngCore;
+use std::f64::consts::PI;
+use std::fmt;
+use std::str::FromStr;
+
+//smaller roughness is a mirror the microfacet distribution can't represent in floating point
+const MIN_ALPHA: f64 = 1e-3;
+
+//complex index of refraction eta + i k of a metal, per rgb channel
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ComplexIor {
+    pub eta: Vec3,
+    pub k: Vec3,
+}
+
+impl ComplexIor {
+    pub fn new(eta: Vec3, k: Vec3) -> Self {
+        Self { eta, k }
+    }
+}
+
+//measured metals, sampled at roughly 650, 550 and 450 nm
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ConductorPreset {
+    Gold,
+    Copper,
+    Aluminium,
+    Silver,
+}
+
+impl ConductorPreset {
+    pub fn ior(&self) -> ComplexIor {
+        return match self {
+            ConductorPreset::Gold => ComplexIor::new(Vec3::new(0.143, 0.374, 1.442), Vec3::new(3.983, 2.385, 1.603)),
+            ConductorPreset::Copper => ComplexIor::new(Vec3::new(0.200, 0.924, 1.102), Vec3::new(3.912, 2.452, 2.142)),
+            ConductorPreset::Aluminium => ComplexIor::new(Vec3::new(1.657, 0.880, 0.521), Vec3::new(9.224, 6.270, 4.837)),
+            ConductorPreset::Silver => ComplexIor::new(Vec3::new(0.155, 0.117, 0.138), Vec3::new(4.828, 3.122, 2.147)),
+        };
+    }
+}
+
+impl FromStr for ConductorPreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        return match s {
+            "gold" => Ok(ConductorPreset::Gold),
+            "copper" => Ok(ConductorPreset::Copper),
+            "aluminium" | "aluminum" => Ok(ConductorPreset::Aluminium),
+            "silver" => Ok(ConductorPreset::Silver),
+            _ => Err(format!("unknown metal '{}', expected one of: gold, copper, aluminium, silver", s)),
+        };
+    }
+}
+
+impl fmt::Display for ConductorPreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ConductorPreset::Gold => "gold",
+            ConductorPreset::Copper => "copper",
+            ConductorPreset::Aluminium => "aluminium",
+            ConductorPreset::Silver => "silver",
+        };
+        return write!(f, "{}", name);
+    }
+}
+
+enum Fresnel {
+    Conductor(ComplexIor),
+    //schlick's approximation from the color at normal incidence, how glTF describes metals
+    Schlick(Box<dyn Texture>),
+}
+
+//metal as a surface of tiny mirrors with a ggx (trowbridge reitz) distribution of orientations. unlike
+//fuzz it keeps energy and gives the long glowing tails of real highlights. roughness is perceptual,
+//the distribution uses its square, and can differ along the tangent (u) and across it (v) for brushed metal
+pub struct Conductor {
+    fresnel: Fresnel,
+    roughness_u: Box<dyn Texture>,
+    roughness_v: Box<dyn Texture>,
+    tangent: Vec3,
+}
+
+impl Conductor {
+    pub fn new(ior: ComplexIor, roughness: f64) -> Self {
+        return Conductor::anisotropic(ior, roughness, roughness);
+    }
+
+    pub fn anisotropic(ior: ComplexIor, roughness_u: f64, roughness_v: f64) -> Self {
+        return Conductor::textured(ior, Box::new(ConstantTexture::scalar(roughness_u)), Box::new(ConstantTexture::scalar(roughness_v)));
+    }
+
+    pub fn textured(ior: ComplexIor, roughness_u: Box<dyn Texture>, roughness_v: Box<dyn Texture>) -> Self {
+        Self {
+            fresnel: Fresnel::Conductor(ior),
+            roughness_u,
+            roughness_v,
+            tangent: Vec3::new(0.0, 1.0, 0.0),
+        }
+    }
+
+    //reflectance is the color straight on, the fresnel term fades it to white at grazing angles
+    pub fn reflective(reflectance: Box<dyn Texture>, roughness_u: Box<dyn Texture>, roughness_v: Box<dyn Texture>) -> Self {
+        Self {
+            fresnel: Fresnel::Schlick(reflectance),
+            roughness_u,
+            roughness_v,
+            tangent: Vec3::new(0.0, 1.0, 0.0),
+        }
+    }
+
+    //roughness_u runs along this direction projected onto the surface, world y by default
+    pub fn with_tangent(mut self, tangent: Vec3) -> Self {
+        self.tangent = tangent.unit_vector();
+        return self;
+    }
+
+    //tangent, bitangent and normal on the side the ray arrives from
+    fn frame(&self, ray: &Ray, hit_record: &HitRecord) -> (Vec3, Vec3, Vec3) {
+        let n = if ray.direction().dot(hit_record.normal) > 0_f64 { -hit_record.normal } else { hit_record.normal };
+        let projected = self.tangent - self.tangent.dot(n) * n;
+        if projected.length_squared() < 1e-12 {
+            let (t, b) = orthonormal_basis(n);
+            return (t, b, n);
+        }
+        let t = projected.unit_vector();
+        return (t, n.cross(t), n);
+    }
+
+    fn distribution(&self, hit_record: &HitRecord) -> Ggx {
+        let alpha = |roughness: &dyn Texture| {
+            let r = roughness.scalar(hit_record.u, hit_record.v, hit_record.position).clamp(0_f64, 1_f64);
+            return (r * r).max(MIN_ALPHA);
+        };
+        return Ggx::new(alpha(&*self.roughness_u), alpha(&*self.roughness_v));
+    }
+
+    fn fresnel(&self, hit_record: &HitRecord, cos_theta: f64) -> Vec3 {
+        return match &self.fresnel {
+            Fresnel::Conductor(ior) => Vec3::new(
+                fresnel_conductor(cos_theta, ior.eta.x(), ior.k.x()),
+                fresnel_conductor(cos_theta, ior.eta.y(), ior.k.y()),
+                fresnel_conductor(cos_theta, ior.eta.z(), ior.k.z()),
+            ),
+            Fresnel::Schlick(reflectance) => {
+                let f0 = reflectance.value(hit_record.u, hit_record.v, hit_record.position);
+                let weight = (1_f64 - cos_theta).clamp(0_f64, 1_f64).powi(5);
+                f0 + weight * (Vec3::new(1.0, 1.0, 1.0) - f0)
+            }
+        };
+    }
+
+    //outgoing and incoming directions in the local frame, z along the normal
+    fn local(&self, ray: &Ray, hit_record: &HitRecord, direction: Vec3) -> (Vec3, Vec3) {
+        let (t, b, n) = self.frame(ray, hit_record);
+        let to_local = |v: Vec3| Vec3::new(v.dot(t), v.dot(b), v.dot(n));
+        return (to_local(-ray.direction().unit_vector()), to_local(direction.unit_vector()));
+    }
+}
+
+impl Material for Conductor {
+    //samples the normals visible from the ray, so every sample faces it and the weight stays near 1
+    fn scatter(&self, ray: &Ray, hit_record: &HitRecord, rng: &mut dyn RngCore) -> Option<ScatterRecord> {
+        let (t, b, n) = self.frame(ray, hit_record);
+        let outgoing = -ray.direction().unit_vector();
+        let wo = Vec3::new(outgoing.dot(t), outgoing.dot(b), outgoing.dot(n));
+        if wo.z() <= 0_f64 {
+            return None;
+        }
+
+        let ggx = self.distribution(hit_record);
+        let h = ggx.sample_visible_normal(wo, rng.gen(), rng.gen());
+        let wi = reflect(-wo, h);
+        //a path that would go on into the surface, only multiple scattering between facets could save it
+        if wi.z() <= 0_f64 {
+            return None;
+        }
+
+        //eval / pdf, where d and the cosines cancel
+        let attenuation = self.fresnel(hit_record, wo.dot(h)) * (ggx.g2(wo, wi) / ggx.g1(wo));
+        let direction = wi.x() * t + wi.y() * b + wi.z() * n;
+
+        return Some(ScatterRecord {
+            attenuation,
+            scattered: Ray::new(hit_record.position, direction).with_time(ray.time()),
+        });
+    }
+
+    fn is_specular(&self) -> bool {
+        return false;
+    }
+
+    //d g f / (4 cos_o), the cosine of the incoming light cancels against the bsdf's
+    fn eval(&self, ray: &Ray, hit_record: &HitRecord, direction: Vec3) -> Vec3 {
+        let (wo, wi) = self.local(ray, hit_record, direction);
+        if wo.z() <= 0_f64 || wi.z() <= 0_f64 {
+            return Vec3::new(0.0, 0.0, 0.0);
+        }
+
+        let h = (wo + wi).unit_vector();
+        let ggx = self.distribution(hit_record);
+        let g = ggx.g2(wo, wi);
+        return self.fresnel(hit_record, wo.dot(h)) * (ggx.d(h) * g / (4_f64 * wo.z()));
+    }
+
+    //visible normal density d_wo(h) = g1(wo) max(0, wo.h) d(h) / cos_o, over 4 wo.h for the reflection
+    fn pdf(&self, ray: &Ray, hit_record: &HitRecord, direction: Vec3) -> f64 {
+        let (wo, wi) = self.local(ray, hit_record, direction);
+        if wo.z() <= 0_f64 || wi.z() <= 0_f64 {
+            return 0_f64;
+        }
+
+        let h = (wo + wi).unit_vector();
+        let ggx = self.distribution(hit_record);
+        return ggx.g1(wo) * ggx.d(h) / (4_f64 * wo.z());
+    }
+}
+
+//anisotropic trowbridge reitz distribution in a frame with the normal along z
+pub struct Ggx {
+    alpha_x: f64,
+    alpha_y: f64,
+}
+
+impl Ggx {
+    pub fn new(alpha_x: f64, alpha_y: f64) -> Self {
+        Self { alpha_x, alpha_y }
+    }
+
+    //density of microfacet normals per unit of projected area
+    pub fn d(&self, h: Vec3) -> f64 {
+        if h.z() <= 0_f64 {
+            return 0_f64;
+        }
+        let (x, y) = (h.x() / self.alpha_x, h.y() / self.alpha_y);
+        let e = x * x + y * y + h.z() * h.z();
+        return 1_f64 / (PI * self.alpha_x * self.alpha_y * e * e);
+    }
+
+    //smith's auxiliary function, the facets hidden from w per visible one
+    pub fn lambda(&self, w: Vec3) -> f64 {
+        let cos2 = w.z() * w.z();
+        if cos2 == 0_f64 {
+            return f64::MAX;
+        }
+        let tan2 = (self.alpha_x * self.alpha_x * w.x() * w.x() + self.alpha_y * self.alpha_y * w.y() * w.y()) / cos2;
+        return 0.5 * (-1_f64 + (1_f64 + tan2).sqrt());
+    }
+
+    //fraction of the facets seen from w that are not masked
+    pub fn g1(&self, w: Vec3) -> f64 {
+        return 1_f64 / (1_f64 + self.lambda(w));
+    }
+
+    //height correlated masking and shadowing
+    pub fn g2(&self, wo: Vec3, wi: Vec3) -> f64 {
+        return 1_f64 / (1_f64 + self.lambda(wo) + self.lambda(wi));
+    }
+
+    //heitz 2018, a normal seen from wo (z > 0) with density d_wo, from two uniform numbers
+    pub fn sample_visible_normal(&self, wo: Vec3, u1: f64, u2: f64) -> Vec3 {
+        //stretch to the hemisphere configuration, where the visible normals of a half sphere are sampled
+        let v = Vec3::new(self.alpha_x * wo.x(), self.alpha_y * wo.y(), wo.z()).unit_vector();
+        let length2 = v.x() * v.x() + v.y() * v.y();
+        let t1 = if length2 > 0_f64 { Vec3::new(-v.y(), v.x(), 0.0) / length2.sqrt() } else { Vec3::new(1.0, 0.0, 0.0) };
+        let t2 = v.cross(t1);
+
+        //a disk, squashed where the half sphere hides part of it
+        let r = u1.sqrt();
+        let phi = 2_f64 * PI * u2;
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1_f64 + v.z());
+        let p2 = (1_f64 - s) * (1_f64 - p1 * p1).max(0_f64).sqrt() + s * r * phi.sin();
+        let nh = p1 * t1 + p2 * t2 + (1_f64 - p1 * p1 - p2 * p2).max(0_f64).sqrt() * v;
+
+        return Vec3::new(self.alpha_x * nh.x(), self.alpha_y * nh.y(), nh.z().max(1e-9)).unit_vector();
+    }
+}
+
+//exact fresnel reflectance of unpolarized light off a conductor, at an angle with cosine cos_theta
+pub fn fresnel_conductor(cos_theta: f64, eta: f64, k: f64) -> f64 {
+    let cos2 = cos_theta.clamp(0_f64, 1_f64).powi(2);
+    let sin2 = 1_f64 - cos2;
+    let t0 = eta * eta - k * k - sin2;
+    let a2_plus_b2 = (t0 * t0 + 4_f64 * eta * eta * k * k).sqrt();
+    let a = (0.5 * (a2_plus_b2 + t0)).max(0_f64).sqrt();
+
+    let t1 = a2_plus_b2 + cos2;
+    let t2 = 2_f64 * cos_theta.clamp(0_f64, 1_f64) * a;
+    let rs = (t1 - t2) / (t1 + t2);
+
+    let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+    let t4 = t2 * sin2;
+    let rp = rs * (t3 - t4) / (t3 + t4);
+    return 0.5 * (rs + rp);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Ray;
+    use crate::math::Vec3;
+    use crate::render::*;
+
+    use rand::Rng;
+    use rand::SeedableRng;
+    use std::f64::consts::PI;
+
+    fn hit_record(material: &dyn Material) -> HitRecord<'_> {
+        return HitRecord {
+            t: 1.0,
+            position: Vec3::new(0.0, 0.0, 0.0),
+            normal: Vec3::new(0.0, 0.0, 1.0),
+            u: 0.5,
+            v: 0.5,
+            material,
+        };
+    }
+
+    #[test]
+    fn test_fresnel() {
+        //at normal incidence ((eta - 1)^2 + k^2) / ((eta + 1)^2 + k^2), and everything reflects at grazing angles
+        let (eta, k) = (0.2, 3.9);
+        let expected = ((eta - 1.0) * (eta - 1.0) + k * k) / ((eta + 1.0) * (eta + 1.0) + k * k);
+        assert!((fresnel_conductor(1.0, eta, k) - expected).abs() < 1e-9);
+        assert!((fresnel_conductor(0.0, eta, k) - 1.0).abs() < 1e-9);
+        assert!(fresnel_conductor(0.5, 1.657, 9.224) > 0.9);
+
+        let gold = ConductorPreset::Gold.ior();
+        let straight_on = |channel: f64, k: f64| fresnel_conductor(1.0, channel, k);
+        assert!(straight_on(gold.eta.x(), gold.k.x()) > straight_on(gold.eta.z(), gold.k.z()) + 0.3);
+
+        for preset in &[ConductorPreset::Gold, ConductorPreset::Copper, ConductorPreset::Aluminium, ConductorPreset::Silver] {
+            assert_eq!(preset.to_string().parse::<ConductorPreset>(), Ok(*preset));
+        }
+        assert_eq!("aluminum".parse::<ConductorPreset>(), Ok(ConductorPreset::Aluminium));
+        assert!("brass".parse::<ConductorPreset>().unwrap_err().contains("gold, copper"));
+    }
+
+    #[test]
+    fn test_samples_match_eval_and_pdf() {
+        //white at every angle, so all that is lost is light shadowed by the facets
+        let white = || Box::new(ConstantTexture::scalar(1.0)) as Box<dyn Texture>;
+        let material = Conductor::reflective(white(), Box::new(ConstantTexture::scalar(0.7)), Box::new(ConstantTexture::scalar(0.4)))
+            .with_tangent(Vec3::new(1.0, 0.0, 0.0));
+        let record = hit_record(&material);
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 0.0) - Vec3::new(0.3, -0.2, 0.9), Vec3::new(0.3, -0.2, -0.9));
+        let mut rng = rand::rngs::StdRng::seed_from_u64(19);
+
+        let n = 200000;
+        let mut sampled = 0.0;
+        for _ in 0..n {
+            if let Some(scatter) = material.scatter(&ray, &record, &mut rng) {
+                let direction = scatter.scattered.direction();
+                let pdf = material.pdf(&ray, &record, direction);
+                let expected = material.eval(&ray, &record, direction) / pdf;
+                assert!((scatter.attenuation - expected).length() < 1e-6 * expected.length().max(1.0));
+                sampled += scatter.attenuation.x();
+            }
+        }
+
+        //the same albedo integrating eval over the hemisphere with uniform directions
+        let mut uniform = 0.0;
+        let mut covered = 0.0;
+        for _ in 0..n {
+            let z: f64 = rng.gen();
+            let phi = 2.0 * PI * rng.gen::<f64>();
+            let r = (1.0 - z * z).sqrt();
+            let direction = Vec3::new(r * phi.cos(), r * phi.sin(), z);
+            uniform += material.eval(&ray, &record, direction).x() * 2.0 * PI;
+            covered += material.pdf(&ray, &record, direction) * 2.0 * PI;
+        }
+
+        let (sampled, uniform, covered) = (sampled / n as f64, uniform / n as f64, covered / n as f64);
+        assert!((sampled - uniform).abs() < 0.02, "{} {}", sampled, uniform);
+        assert!(sampled < 1.0 && sampled > 0.8, "{}", sampled);
+        //the pdf integrates to one less the samples reflected below the surface
+        assert!(covered <= 1.02 && covered > 0.8, "{}", covered);
+    }
+
+    #[test]
+    fn test_anisotropic_highlight() {
+        //smooth along the tangent x, rough across it, so reflections spread along y
+        let material = Conductor::anisotropic(ConductorPreset::Silver.ior(), 0.05, 0.6).with_tangent(Vec3::new(1.0, 0.0, 0.0));
+        let record = hit_record(&material);
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let mut rng = rand::rngs::StdRng::seed_from_u64(20);
+
+        let (mut spread_x, mut spread_y) = (0.0, 0.0);
+        for _ in 0..10000 {
+            if let Some(scatter) = material.scatter(&ray, &record, &mut rng) {
+                let d = scatter.scattered.direction().unit_vector();
+                assert!(d.z() > 0.0);
+                spread_x += d.x().abs();
+                spread_y += d.y().abs();
+            }
+        }
+        assert!(spread_y > 5.0 * spread_x, "{} {}", spread_x, spread_y);
+        assert!(!material.is_specular());
+
+        //a mirror direction is bright, the same direction seen from below the surface is black
+        let mirror = material.eval(&ray, &record, Vec3::new(0.0, 0.0, 1.0));
+        assert!(mirror.x() > 1.0);
+        assert_eq!(material.eval(&ray, &record, Vec3::new(0.0, 0.0, -1.0)), Vec3::new(0.0, 0.0, 0.0));
+    }
+}
@@ -0,0 +1,165 @@
+use crate::math::Vec3;
+use crate::render::material::material::Material;
+use crate::math::Ray;
+use crate::render::renderable::HitRecord;
+use crate::render::material::material::ScatterRecord;
+use crate::render::material::material::reflect;
+use crate::render::material::material::refract;
+use crate::render::material::material::schlick;
+use crate::render::texture::ConstantTexture;
+use crate::render::texture::Texture;
+
+use rand::Rng;
+use rand::RngCore;
+
+//clear refractive material such as glass or water
+//use a sphere with a negative radius inside a regular one for hollow glass
+pub struct Dielectric {
+    refraction_index: Box<dyn Texture>
+}
+
+impl Dielectric {
+    pub fn new(refraction_index: f64) -> Self {
+        return Dielectric::textured(Box::new(ConstantTexture::scalar(refraction_index)));
+    }
+
+    pub fn textured(refraction_index: Box<dyn Texture>) -> Self {
+        Self {
+            refraction_index
+        }
+    }
+}
+
+impl Material for Dielectric {
+    fn scatter(&self, ray: &Ray, hit_record: &HitRecord, rng: &mut dyn RngCore) -> Option<ScatterRecord> {
+        let unit_direction = ray.direction().unit_vector();
+        let cos_incident = unit_direction.dot(hit_record.normal);
+        let refraction_index = self.refraction_index.scalar(hit_record.u, hit_record.v, hit_record.position);
+
+        //leaving the material if the ray travels along the normal
+        let (outward_normal, ni_over_nt, cosine) = if cos_incident > 0_f64 {
+            (-hit_record.normal, refraction_index, cos_incident)
+        } else {
+            (hit_record.normal, 1_f64 / refraction_index, -cos_incident)
+        };
+
+        //glass absorbs nothing
+        let attenuation = Vec3::new(1.0, 1.0, 1.0);
+
+        let direction = match refract(unit_direction, outward_normal, ni_over_nt) {
+            Some(refracted) => {
+                //schlick's approximation is only valid for the cosine on the less dense side
+                let outside_cosine = if ni_over_nt > 1_f64 { -refracted.dot(outward_normal) } else { cosine };
+                let reflect_probability = schlick(outside_cosine, refraction_index);
+
+                if rng.gen::<f64>() < reflect_probability {
+                    reflect(unit_direction, outward_normal)
+                } else {
+                    refracted
+                }
+            }
+            None => reflect(unit_direction, outward_normal),
+        };
+
+        return Some(
+            ScatterRecord {
+                attenuation,
+                scattered: Ray::new(hit_record.position, direction).with_time(ray.time())
+            }
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Ray;
+    use crate::math::Vec3;
+    use crate::render::*;
+
+    use rand::SeedableRng;
+
+    const EPS: f64 = 1e-9;
+
+    fn hit_record(material: &dyn Material, normal: Vec3) -> HitRecord<'_> {
+        return HitRecord {
+            t: 1.0,
+            position: Vec3::new(0.0, 0.0, 0.0),
+            normal,
+            u: 0.0,
+            v: 0.0,
+            material,
+        };
+    }
+
+    fn sin_to_normal(v: Vec3, n: Vec3) -> f64 {
+        return v.unit_vector().cross(n).length();
+    }
+
+    #[test]
+    fn test_energy_and_direction_invariants() {
+        let glass = Dielectric::new(1.5);
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+        let incident = Vec3::new(1.0, -1.0, 0.0).unit_vector();
+        let ray = Ray::new(Vec3::new(-1.0, 1.0, 0.0), incident);
+        let record = hit_record(&glass, normal);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(3);
+
+        let (mut reflected, mut refracted) = (0, 0);
+        for _ in 0..1000 {
+            let scatter = glass.scatter(&ray, &record, &mut rng).unwrap();
+            let direction = scatter.scattered.direction();
+
+            assert_eq!(scatter.attenuation, Vec3::new(1.0, 1.0, 1.0));
+            assert!((direction.length() - 1.0).abs() < EPS);
+            //tangential component keeps its direction
+            assert!(direction.x() > 0.0);
+            assert!(direction.z().abs() < EPS);
+
+            if direction.y() > 0.0 {
+                //mirror reflection keeps the angle to the normal
+                assert!((direction.y() + incident.y()).abs() < EPS);
+                reflected += 1;
+            } else {
+                //snell's law, n1 sin(theta1) = n2 sin(theta2)
+                let sin_in = sin_to_normal(incident, normal);
+                let sin_out = sin_to_normal(direction, normal);
+                assert!((sin_in - 1.5 * sin_out).abs() < EPS);
+                refracted += 1;
+            }
+        }
+
+        //schlick gives about 5% reflectance at 45 degrees for glass
+        assert!(reflected > 20 && reflected < 100, "{} reflected", reflected);
+        assert!(refracted > 900);
+    }
+
+    #[test]
+    fn test_total_internal_reflection() {
+        let glass = Dielectric::new(1.5);
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+        //inside the glass travelling outwards at 60 degrees, past the ~41.8 degree critical angle
+        let incident = Vec3::new(3_f64.sqrt(), 1.0, 0.0).unit_vector();
+        let ray = Ray::new(Vec3::new(-1.0, -1.0, 0.0), incident);
+        let record = hit_record(&glass, normal);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(5);
+
+        for _ in 0..100 {
+            let direction = glass.scatter(&ray, &record, &mut rng).unwrap().scattered.direction();
+            assert!((direction - Vec3::new(incident.x(), -incident.y(), 0.0)).length() < EPS);
+        }
+    }
+
+    #[test]
+    fn test_hollow_sphere_normal_points_inwards() {
+        let sphere = Sphere::new(Vec3::new(0.0, 0.0, 0.0), -1.0, Box::new(Dielectric::new(1.5)));
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+
+        let entry = sphere.hit(&ray, 0.0, f64::MAX).unwrap();
+        assert!((entry.t - 4.0).abs() < EPS);
+        assert_eq!(entry.normal, Vec3::new(0.0, 0.0, -1.0));
+
+        let exit = sphere.hit(&ray, 4.5, f64::MAX).unwrap();
+        assert!((exit.t - 6.0).abs() < EPS);
+        assert_eq!(exit.normal, Vec3::new(0.0, 0.0, 1.0));
+    }
+}
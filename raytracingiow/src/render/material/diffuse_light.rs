@@ -0,0 +1,79 @@
+use crate::math::Ray;
+use crate::math::Vec3;
+use crate::render::material::material::Material;
+use crate::render::material::material::ScatterRecord;
+use crate::render::renderable::HitRecord;
+use crate::render::texture::ConstantTexture;
+use crate::render::texture::Texture;
+
+use rand::RngCore;
+
+//emits the same radiance in every direction and absorbs everything that hits it
+pub struct DiffuseLight {
+    emit: Box<dyn Texture>,
+    //only emit on the side the normal points to, e.g. a ceiling panel
+    one_sided: bool,
+}
+
+impl DiffuseLight {
+    pub fn new(emit: Vec3) -> Self {
+        return DiffuseLight::textured(Box::new(ConstantTexture::new(emit)));
+    }
+
+    pub fn textured(emit: Box<dyn Texture>) -> Self {
+        Self {
+            emit,
+            one_sided: false,
+        }
+    }
+
+    pub fn one_sided(self) -> Self {
+        Self {
+            one_sided: true,
+            ..self
+        }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn scatter(&self, _ray: &Ray, _hit_record: &HitRecord, _rng: &mut dyn RngCore) -> Option<ScatterRecord> {
+        return None;
+    }
+
+    fn emitted(&self, ray: &Ray, hit_record: &HitRecord) -> Vec3 {
+        if self.one_sided && ray.direction().dot(hit_record.normal) > 0_f64 {
+            return Vec3::new(0.0, 0.0, 0.0);
+        }
+        return self.emit.value(hit_record.u, hit_record.v, hit_record.position);
+    }
+
+    fn is_emissive(&self) -> bool {
+        return true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Ray;
+    use crate::math::Vec3;
+    use crate::render::*;
+
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_emits_without_scattering() {
+        let light = Sphere::new(Vec3::new(0.0, 0.0, -2.0), 0.5, Box::new(DiffuseLight::new(Vec3::new(4.0, 4.0, 4.0)).one_sided()));
+        let mut rng = rand::rngs::StdRng::seed_from_u64(3);
+
+        //outside, the normal faces the ray
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = light.hit(&ray, 0.001, f64::MAX).unwrap();
+        assert!(hit.material.scatter(&ray, &hit, &mut rng).is_none());
+        assert_eq!(hit.material.emitted(&ray, &hit), Vec3::new(4.0, 4.0, 4.0));
+
+        //from inside the back face is dark
+        let inside = Ray::new(Vec3::new(0.0, 0.0, -2.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = light.hit(&inside, 0.001, f64::MAX).unwrap();
+        assert_eq!(hit.material.emitted(&inside, &hit), Vec3::new(0.0, 0.0, 0.0));
+    }
+}
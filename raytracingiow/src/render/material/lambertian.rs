@@ -1,13 +1,15 @@
 use crate::math::Vec3;
 use rand::Rng;
+use std::f64::consts::PI;
+use rand::RngCore;
 use crate::render::material::material::Material;
 use crate::math::Ray;
 use crate::render::renderable::HitRecord;
 use crate::render::material::material::ScatterRecord;
+use crate::render::texture::ConstantTexture;
+use crate::render::texture::Texture;
 
-pub fn random_in_unit_sphere() -> Vec3 {
-    let mut rng = rand::thread_rng();
-
+pub fn random_in_unit_sphere(rng: &mut dyn RngCore) -> Vec3 {
     loop {
         let i: f64 = rng.gen();
         let j: f64 = rng.gen();
@@ -21,12 +23,24 @@ pub fn random_in_unit_sphere() -> Vec3 {
     }
 }
 
+//uniformly distributed on the surface of the unit sphere
+pub fn random_unit_vector(rng: &mut dyn RngCore) -> Vec3 {
+    let z: f64 = 2_f64 * rng.gen::<f64>() - 1_f64;
+    let phi: f64 = 2_f64 * PI * rng.gen::<f64>();
+    let r = (1_f64 - z * z).sqrt();
+    return Vec3::new(r * phi.cos(), r * phi.sin(), z);
+}
+
 pub struct Lambertian {
-    albedo: Vec3
+    albedo: Box<dyn Texture>
 }
 
 impl Lambertian {
     pub fn new(albedo: Vec3) -> Self {
+        return Lambertian::textured(Box::new(ConstantTexture::new(albedo)));
+    }
+
+    pub fn textured(albedo: Box<dyn Texture>) -> Self {
         Self {
             albedo
         }
@@ -34,12 +48,13 @@ impl Lambertian {
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, _ray: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
-        let target = hit_record.position + hit_record.normal + random_in_unit_sphere();
-        let direction = target - hit_record.position;
+    fn scatter(&self, ray: &Ray, hit_record: &HitRecord, rng: &mut dyn RngCore) -> Option<ScatterRecord> {
+        //a point on the unit sphere touching the surface gives cosine weighted directions
+        let direction = hit_record.normal + random_unit_vector(rng);
+        let direction = if direction.length_squared() < 1e-12 { hit_record.normal } else { direction };
 
-        let scattered = Ray::new(hit_record.position, direction);
-        let attenuation = self.albedo;
+        let scattered = Ray::new(hit_record.position, direction).with_time(ray.time());
+        let attenuation = self.albedo.value(hit_record.u, hit_record.v, hit_record.position);
 
         return Some(
             ScatterRecord {
@@ -48,4 +63,17 @@ impl Material for Lambertian {
             }
         );
     }
+
+    fn is_specular(&self) -> bool {
+        return false;
+    }
+
+    fn eval(&self, _ray: &Ray, hit_record: &HitRecord, direction: Vec3) -> Vec3 {
+        let cosine = hit_record.normal.dot(direction.unit_vector()).max(0_f64);
+        return self.albedo.value(hit_record.u, hit_record.v, hit_record.position) * (cosine / PI);
+    }
+
+    fn pdf(&self, _ray: &Ray, hit_record: &HitRecord, direction: Vec3) -> f64 {
+        return hit_record.normal.dot(direction.unit_vector()).max(0_f64) / PI;
+    }
 }
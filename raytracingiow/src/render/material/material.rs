@@ -2,12 +2,64 @@ use crate::math::Ray;
 use crate::math::Vec3;
 use crate::render::renderable::HitRecord;
 
+use rand::RngCore;
+
 pub struct ScatterRecord {
     pub attenuation: Vec3,
     pub scattered: Ray
 }
 
-pub trait Material {
+pub trait Material: Send + Sync {
+
+    fn scatter(&self, ray: &Ray, hit_record: &HitRecord, rng: &mut dyn RngCore) -> Option<ScatterRecord>;
+
+    //radiance given off at the hit point, black unless the material is a light
+    fn emitted(&self, _ray: &Ray, _hit_record: &HitRecord) -> Vec3 {
+        return Vec3::new(0.0, 0.0, 0.0);
+    }
+
+    //objects with an emissive material are added to the light list and sampled directly
+    fn is_emissive(&self) -> bool {
+        return false;
+    }
+
+    //scatter picks directions that eval and pdf can't describe, like a mirror or glass,
+    //so light sampling is skipped at this hit
+    fn is_specular(&self) -> bool {
+        return true;
+    }
+
+    //bsdf times the cosine term for light arriving along -direction and leaving along -ray
+    fn eval(&self, _ray: &Ray, _hit_record: &HitRecord, _direction: Vec3) -> Vec3 {
+        return Vec3::new(0.0, 0.0, 0.0);
+    }
+
+    //solid angle density of scatter choosing direction
+    fn pdf(&self, _ray: &Ray, _hit_record: &HitRecord, _direction: Vec3) -> f64 {
+        return 0.0;
+    }
+}
+
+//mirror v about the surface normal n
+pub fn reflect(v: Vec3, n: Vec3) -> Vec3 {
+    return v - 2_f64 * v.dot(n) * n;
+}
+
+//snell's law for a unit vector uv hitting a surface with unit normal n facing against it,
+//None on total internal reflection
+pub fn refract(uv: Vec3, n: Vec3, ni_over_nt: f64) -> Option<Vec3> {
+    let dt = uv.dot(n);
+    let discriminant = 1_f64 - ni_over_nt * ni_over_nt * (1_f64 - dt * dt);
 
-    fn scatter(&self, ray: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord>;
-}
\ No newline at end of file
+    if discriminant > 0_f64 {
+        return Some(ni_over_nt * (uv - n * dt) - n * discriminant.sqrt());
+    } else {
+        return None;
+    }
+}
+
+//schlick's approximation of the fresnel reflectance
+pub fn schlick(cosine: f64, refraction_index: f64) -> f64 {
+    let r0 = ((1_f64 - refraction_index) / (1_f64 + refraction_index)).powi(2);
+    return r0 + (1_f64 - r0) * (1_f64 - cosine).powi(5);
+}
@@ -4,32 +4,40 @@ use crate::math::Ray;
 use crate::render::renderable::HitRecord;
 use crate::render::material::random_in_unit_sphere;
 use crate::render::material::material::ScatterRecord;
+use crate::render::material::material::reflect;
+use crate::render::texture::ConstantTexture;
+use crate::render::texture::Texture;
+
+use rand::RngCore;
 
 pub struct Metal {
-    albedo: Vec3,
-    fuzz: f64
+    albedo: Box<dyn Texture>,
+    //clamped to 1 where it is evaluated
+    fuzz: Box<dyn Texture>
 }
 
 impl Metal {
     pub fn new(albedo: Vec3, fuzz: f64) -> Self {
-        let fuzz_val = if fuzz < 1_f64 { fuzz} else { 1_f64 };
+        return Metal::textured(Box::new(ConstantTexture::new(albedo)), Box::new(ConstantTexture::scalar(fuzz)));
+    }
+
+    pub fn textured(albedo: Box<dyn Texture>, fuzz: Box<dyn Texture>) -> Self {
         Self {
             albedo,
-            fuzz: fuzz_val
+            fuzz
         }
     }
 }
 
 impl Material for Metal {
-    fn scatter(&self, ray: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
-        let n = ray.direction().dot(hit_record.normal);
-        let reflected = ray.direction() - 2_f64 * n * hit_record.normal;
-        let direction = reflected - hit_record.position;
-        let fuzzed_direction = direction + self.fuzz * random_in_unit_sphere();
+    fn scatter(&self, ray: &Ray, hit_record: &HitRecord, rng: &mut dyn RngCore) -> Option<ScatterRecord> {
+        let reflected = reflect(ray.direction().unit_vector(), hit_record.normal);
+        let fuzz = self.fuzz.scalar(hit_record.u, hit_record.v, hit_record.position).min(1_f64);
+        let fuzzed_direction = reflected + fuzz * random_in_unit_sphere(rng);
 
         if fuzzed_direction.dot(hit_record.normal) > 0_f64 {
-            let attenuation = self.albedo;
-            let scattered = Ray::new(hit_record.position, fuzzed_direction);
+            let attenuation = self.albedo.value(hit_record.u, hit_record.v, hit_record.position);
+            let scattered = Ray::new(hit_record.position, fuzzed_direction).with_time(ray.time());
 
             return Some(
                 ScatterRecord {
@@ -1,7 +1,16 @@
+mod conductor;
+mod dielectric;
+mod diffuse_light;
 mod lambertian;
+#[allow(clippy::module_inception)]
 mod material;
 mod metal;
+mod phase;
 
+pub use self::conductor::*;
+pub use self::dielectric::*;
+pub use self::diffuse_light::*;
 pub use self::lambertian::*;
 pub use self::material::*;
-pub use self::metal::*;
\ No newline at end of file
+pub use self::metal::*;
+pub use self::phase::*;
@@ -0,0 +1,178 @@
+use crate::math::Ray;
+use crate::math::Vec3;
+use crate::render::light::orthonormal_basis;
+use crate::render::material::material::Material;
+use crate::render::material::material::ScatterRecord;
+use crate::render::material::random_unit_vector;
+use crate::render::renderable::HitRecord;
+use crate::render::texture::ConstantTexture;
+use crate::render::texture::Texture;
+
+use rand::Rng;
+use rand::RngCore;
+use std::f64::consts::PI;
+
+//phase functions scatter inside participating media, where there is no surface and no cosine term.
+//the albedo is the fraction of light scattered rather than absorbed at each collision
+
+//scatters equally in every direction
+pub struct Isotropic {
+    albedo: Box<dyn Texture>,
+}
+
+impl Isotropic {
+    pub fn new(albedo: Vec3) -> Self {
+        return Isotropic::textured(Box::new(ConstantTexture::new(albedo)));
+    }
+
+    pub fn textured(albedo: Box<dyn Texture>) -> Self {
+        Self { albedo }
+    }
+}
+
+impl Material for Isotropic {
+    fn scatter(&self, ray: &Ray, hit_record: &HitRecord, rng: &mut dyn RngCore) -> Option<ScatterRecord> {
+        return Some(ScatterRecord {
+            attenuation: self.albedo.value(hit_record.u, hit_record.v, hit_record.position),
+            scattered: Ray::new(hit_record.position, random_unit_vector(rng)).with_time(ray.time()),
+        });
+    }
+
+    fn is_specular(&self) -> bool {
+        return false;
+    }
+
+    fn eval(&self, _ray: &Ray, hit_record: &HitRecord, _direction: Vec3) -> Vec3 {
+        return self.albedo.value(hit_record.u, hit_record.v, hit_record.position) / (4_f64 * PI);
+    }
+
+    fn pdf(&self, _ray: &Ray, _hit_record: &HitRecord, _direction: Vec3) -> f64 {
+        return 1_f64 / (4_f64 * PI);
+    }
+}
+
+//g in (-1, 1) from back scattering through isotropic at 0 to forward scattering, like haze and clouds
+pub struct HenyeyGreenstein {
+    albedo: Box<dyn Texture>,
+    g: f64,
+}
+
+impl HenyeyGreenstein {
+    pub fn new(albedo: Vec3, g: f64) -> Self {
+        return HenyeyGreenstein::textured(Box::new(ConstantTexture::new(albedo)), g);
+    }
+
+    //g is clamped away from +-1, where all the light would go in a single direction
+    pub fn textured(albedo: Box<dyn Texture>, g: f64) -> Self {
+        Self {
+            albedo,
+            g: g.clamp(-0.999, 0.999),
+        }
+    }
+}
+
+//density of turning by an angle with cosine cos_theta from the direction of travel
+pub fn henyey_greenstein(cos_theta: f64, g: f64) -> f64 {
+    let denominator = 1_f64 + g * g - 2_f64 * g * cos_theta;
+    return (1_f64 - g * g) / (4_f64 * PI * denominator * denominator.sqrt());
+}
+
+//cosine of the angle to the direction of travel, by inverting the cumulative distribution
+pub fn sample_henyey_greenstein(g: f64, rng: &mut dyn RngCore) -> f64 {
+    let xi: f64 = rng.gen();
+    if g.abs() < 1e-3 {
+        return 1_f64 - 2_f64 * xi;
+    }
+    let s = (1_f64 - g * g) / (1_f64 - g + 2_f64 * g * xi);
+    return ((1_f64 + g * g - s * s) / (2_f64 * g)).clamp(-1_f64, 1_f64);
+}
+
+impl Material for HenyeyGreenstein {
+    fn scatter(&self, ray: &Ray, hit_record: &HitRecord, rng: &mut dyn RngCore) -> Option<ScatterRecord> {
+        let forward = ray.direction().unit_vector();
+        let cos_theta = sample_henyey_greenstein(self.g, rng);
+        let sin_theta = (1_f64 - cos_theta * cos_theta).max(0_f64).sqrt();
+        let phi = 2_f64 * PI * rng.gen::<f64>();
+        let (u, v) = orthonormal_basis(forward);
+        let direction = sin_theta * phi.cos() * u + sin_theta * phi.sin() * v + cos_theta * forward;
+
+        return Some(ScatterRecord {
+            attenuation: self.albedo.value(hit_record.u, hit_record.v, hit_record.position),
+            scattered: Ray::new(hit_record.position, direction).with_time(ray.time()),
+        });
+    }
+
+    fn is_specular(&self) -> bool {
+        return false;
+    }
+
+    fn eval(&self, ray: &Ray, hit_record: &HitRecord, direction: Vec3) -> Vec3 {
+        let albedo = self.albedo.value(hit_record.u, hit_record.v, hit_record.position);
+        return albedo * self.pdf(ray, hit_record, direction);
+    }
+
+    fn pdf(&self, ray: &Ray, _hit_record: &HitRecord, direction: Vec3) -> f64 {
+        let cos_theta = ray.direction().unit_vector().dot(direction.unit_vector());
+        return henyey_greenstein(cos_theta, self.g);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Ray;
+    use crate::math::Vec3;
+    use crate::render::*;
+
+    use rand::SeedableRng;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn test_henyey_greenstein_integrates_to_one() {
+        for &g in &[-0.7, 0.0, 0.3, 0.9] {
+            //midpoint rule over cos theta, phi contributes 2 pi
+            let n = 20000;
+            let total: f64 = (0..n)
+                .map(|i| {
+                    let cos_theta = -1.0 + (i as f64 + 0.5) * 2.0 / n as f64;
+                    henyey_greenstein(cos_theta, g) * 2.0 * PI * 2.0 / n as f64
+                })
+                .sum();
+            assert!((total - 1.0).abs() < 1e-3, "g {} integrates to {}", g, total);
+        }
+        assert!((henyey_greenstein(0.3, 0.0) - 1.0 / (4.0 * PI)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_samples_match_pdf() {
+        //the mean cosine of henyey greenstein is g
+        let material = HenyeyGreenstein::new(Vec3::new(0.8, 0.8, 0.8), 0.6);
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -2.0));
+        let hit_record = HitRecord {
+            t: 1.0,
+            position: Vec3::new(0.0, 0.0, -2.0),
+            normal: Vec3::new(1.0, 0.0, 0.0),
+            u: 0.0,
+            v: 0.0,
+            material: &material,
+        };
+        let mut rng = rand::rngs::StdRng::seed_from_u64(15);
+
+        let n = 100000;
+        let mut mean_cosine = 0.0;
+        let mut inverse_pdf = 0.0;
+        for _ in 0..n {
+            let scattered = material.scatter(&ray, &hit_record, &mut rng).unwrap().scattered;
+            let direction = scattered.direction();
+            assert!((direction.length() - 1.0).abs() < 1e-9);
+            mean_cosine += -direction.z();
+            inverse_pdf += 1.0 / material.pdf(&ray, &hit_record, direction);
+        }
+        assert!((mean_cosine / n as f64 - 0.6).abs() < 0.01);
+        //every direction is reachable, so 1 / pdf averages to the whole sphere
+        assert!((inverse_pdf / n as f64 - 4.0 * PI).abs() < 0.05 * 4.0 * PI);
+
+        let eval = material.eval(&ray, &hit_record, Vec3::new(0.0, 0.0, -1.0));
+        assert!((eval.x() - 0.8 * henyey_greenstein(1.0, 0.6)).abs() < 1e-12);
+        assert_eq!(Isotropic::new(Vec3::new(1.0, 1.0, 1.0)).pdf(&ray, &hit_record, Vec3::new(0.0, 1.0, 0.0)), 1.0 / (4.0 * PI));
+    }
+}
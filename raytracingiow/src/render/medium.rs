@@ -0,0 +1,44 @@
+use crate::math::Ray;
+
+//random numbers for media. hit has no random generator, so they come from hashing the ray: the same
+//numbers however often a ray is tested, which keeps a medium consistent while a bvh narrows t_max,
+//and renders repeatable
+pub struct RayRandom {
+    state: u64,
+}
+
+impl RayRandom {
+    //media that overlap along the same ray need different salts, see medium_salt
+    pub fn new(ray: &Ray, salt: u64) -> Self {
+        let (o, d) = (ray.origin(), ray.direction());
+        let mut state = salt;
+        for value in [o.x(), o.y(), o.z(), d.x(), d.y(), d.z(), ray.time()] {
+            state = mix(state ^ value.to_bits());
+        }
+        Self { state }
+    }
+
+    //uniform in [0, 1), splitmix64
+    pub fn next_f64(&mut self) -> f64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        return (mix(self.state) >> 11) as f64 / (1_u64 << 53) as f64;
+    }
+
+    //distance to the next collision where collisions happen density times per unit of distance
+    pub fn free_flight(&mut self, density: f64) -> f64 {
+        return -(1_f64 - self.next_f64()).ln() / density;
+    }
+}
+
+//from whatever describes a medium, usually its bounds and density
+pub fn medium_salt(values: &[f64]) -> u64 {
+    return values.iter().fold(0, |salt, value| mix(salt ^ value.to_bits()));
+}
+
+//splitmix64 finalizer
+fn mix(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    return z ^ (z >> 31);
+}
@@ -0,0 +1,314 @@
+use crate::math::Aabb;
+use crate::math::Ray;
+use crate::math::Vec3;
+use crate::render::bvh::Bvh;
+use crate::render::light::area_to_solid_angle;
+use crate::render::light::sample_towards;
+use crate::render::light::Light;
+use crate::render::light::LightSample;
+use crate::render::material::Material;
+use crate::render::quad::FLAT_BOX_PADDING;
+use crate::render::renderable::HitRecord;
+use crate::render::renderable::Renderable;
+use crate::render::triangle::interpolate;
+use crate::render::triangle::interpolate_uv;
+use crate::render::triangle::intersect_triangle;
+use crate::render::triangle::sample_triangle;
+
+use rand::RngCore;
+use std::sync::Arc;
+
+//vertex data shared by the faces of a mesh
+#[derive(Default, Clone, Debug)]
+pub struct MeshBuffers {
+    pub positions: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
+    pub uvs: Vec<(f64, f64)>,
+    pub faces: Vec<MeshFace>,
+}
+
+//one triangle as indices into the buffers. every attribute has its own indices,
+//faces without normals are shaded flat and faces without uvs use their barycentric coordinates
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MeshFace {
+    pub positions: [usize; 3],
+    pub normals: Option<[usize; 3]>,
+    pub uvs: Option<[usize; 3]>,
+}
+
+impl MeshFace {
+    pub fn new(positions: [usize; 3]) -> Self {
+        Self {
+            positions,
+            normals: None,
+            uvs: None,
+        }
+    }
+
+    pub fn with_normals(self, normals: [usize; 3]) -> Self {
+        Self {
+            normals: Some(normals),
+            ..self
+        }
+    }
+
+    pub fn with_uvs(self, uvs: [usize; 3]) -> Self {
+        Self {
+            uvs: Some(uvs),
+            ..self
+        }
+    }
+}
+
+struct MeshData {
+    buffers: MeshBuffers,
+    material: Box<dyn Material>,
+}
+
+impl MeshData {
+    fn vertices(&self, face: usize) -> [Vec3; 3] {
+        let [a, b, c] = self.buffers.faces[face].positions;
+        let positions = &self.buffers.positions;
+        return [positions[a], positions[b], positions[c]];
+    }
+
+    //unnormalized, twice the area long
+    fn face_normal(&self, face: usize) -> Vec3 {
+        let [p0, p1, p2] = self.vertices(face);
+        return (p1 - p0).cross(p2 - p0);
+    }
+}
+
+//a face of the mesh as stored in its bvh
+struct FaceRef {
+    mesh: Arc<MeshData>,
+    index: usize,
+}
+
+impl Renderable for FaceRef {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let vertices = self.mesh.vertices(self.index);
+        let [p0, p1, p2] = vertices;
+        let hit = intersect_triangle(ray, p0, p1, p2, t_min, t_max)?;
+
+        let face = &self.mesh.buffers.faces[self.index];
+        let flat = self.mesh.face_normal(self.index).unit_vector();
+        let normal = match face.normals {
+            Some([a, b, c]) => {
+                let normals = &self.mesh.buffers.normals;
+                let shading = interpolate([normals[a], normals[b], normals[c]], hit.barycentric);
+                //opposite vertex normals can cancel out
+                if shading.length_squared() > 1e-24 { shading.unit_vector() } else { flat }
+            }
+            None => flat,
+        };
+        let (u, v) = match face.uvs {
+            Some([a, b, c]) => {
+                let uvs = &self.mesh.buffers.uvs;
+                interpolate_uv([uvs[a], uvs[b], uvs[c]], hit.barycentric)
+            }
+            None => (hit.barycentric[1], hit.barycentric[2]),
+        };
+
+        return Some(HitRecord {
+            t: hit.t,
+            position: interpolate(vertices, hit.barycentric),
+            normal,
+            u,
+            v,
+            material: &*self.mesh.material,
+        });
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let [p0, p1, p2] = self.mesh.vertices(self.index);
+        return Some(Aabb::from_points(p0, p1).surrounding(&Aabb::new(p2, p2)).padded(FLAT_BOX_PADDING));
+    }
+
+    fn collect_lights<'a>(&'a self, lights: &mut Vec<&'a dyn Light>) {
+        if self.mesh.material.is_emissive() && self.mesh.face_normal(self.index).length_squared() > 0_f64 {
+            lights.push(self);
+        }
+    }
+}
+
+impl Light for FaceRef {
+    fn sample(&self, origin: Vec3, rng: &mut dyn RngCore) -> Option<LightSample> {
+        let n = self.mesh.face_normal(self.index);
+        let point = interpolate(self.mesh.vertices(self.index), sample_triangle(rng));
+        return sample_towards(origin, point, area_to_solid_angle(origin, point, n.unit_vector(), 0.5 * n.length()));
+    }
+
+    fn pdf(&self, origin: Vec3, direction: Vec3) -> f64 {
+        let vertices = self.mesh.vertices(self.index);
+        let [p0, p1, p2] = vertices;
+        let n = self.mesh.face_normal(self.index);
+        return match intersect_triangle(&Ray::new(origin, direction), p0, p1, p2, 1e-9, f64::MAX) {
+            Some(hit) => area_to_solid_angle(origin, interpolate(vertices, hit.barycentric), n.unit_vector(), 0.5 * n.length()),
+            None => 0_f64,
+        };
+    }
+}
+
+//indexed triangles sharing one material, with their own bvh over the faces
+pub struct TriangleMesh {
+    faces: Bvh<FaceRef>,
+}
+
+impl TriangleMesh {
+    //fails if a face refers past the end of a buffer
+    pub fn new(buffers: MeshBuffers, material: Box<dyn Material>) -> Result<Self, String> {
+        for (i, face) in buffers.faces.iter().enumerate() {
+            check_indices(i, "position", &face.positions, buffers.positions.len())?;
+            if let Some(normals) = &face.normals {
+                check_indices(i, "normal", normals, buffers.normals.len())?;
+            }
+            if let Some(uvs) = &face.uvs {
+                check_indices(i, "uv", uvs, buffers.uvs.len())?;
+            }
+        }
+
+        let count = buffers.faces.len();
+        let mesh = Arc::new(MeshData { buffers, material });
+        let faces = (0..count)
+            .map(|index| FaceRef {
+                mesh: Arc::clone(&mesh),
+                index,
+            })
+            .collect();
+
+        return Ok(Self { faces: Bvh::new(faces) });
+    }
+
+    pub fn len(&self) -> usize {
+        return self.faces.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.faces.is_empty();
+    }
+}
+
+fn check_indices(face: usize, kind: &str, indices: &[usize; 3], len: usize) -> Result<(), String> {
+    for index in indices {
+        if *index >= len {
+            return Err(format!("face {} uses {} {} but there are only {}", face, kind, index, len));
+        }
+    }
+    return Ok(());
+}
+
+impl Renderable for TriangleMesh {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        return self.faces.hit(ray, t_min, t_max);
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        return self.faces.bounding_box();
+    }
+
+    fn collect_lights<'a>(&'a self, lights: &mut Vec<&'a dyn Light>) {
+        self.faces.collect_lights(lights);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Ray;
+    use crate::math::Vec3;
+    use crate::render::*;
+
+    use rand::Rng;
+    use rand::SeedableRng;
+
+    const EPS: f64 = 1e-9;
+
+    fn grey() -> Box<dyn Material> {
+        return Box::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)));
+    }
+
+    //unit cube around the origin, two outward facing triangles per side
+    fn cube() -> MeshBuffers {
+        let positions = (0..8)
+            .map(|i| Vec3::new((i & 1) as f64 - 0.5, ((i >> 1) & 1) as f64 - 0.5, ((i >> 2) & 1) as f64 - 0.5))
+            .collect();
+        let quads = [[0, 2, 3, 1], [4, 5, 7, 6], [0, 1, 5, 4], [2, 6, 7, 3], [0, 4, 6, 2], [1, 3, 7, 5]];
+        let faces = quads
+            .iter()
+            .flat_map(|q| vec![MeshFace::new([q[0], q[1], q[2]]), MeshFace::new([q[0], q[2], q[3]])])
+            .collect();
+        return MeshBuffers {
+            positions,
+            faces,
+            ..MeshBuffers::default()
+        };
+    }
+
+    #[test]
+    fn test_cube_is_closed() {
+        let mesh = TriangleMesh::new(cube(), grey()).unwrap();
+        assert_eq!(mesh.len(), 12);
+        let bbox = mesh.bounding_box().unwrap();
+        assert!((bbox.min() - Vec3::new(-0.5, -0.5, -0.5)).length() < 1e-3);
+
+        //every ray from inside leaves through exactly one outward face, even through edges and corners
+        let mut rng = rand::rngs::StdRng::seed_from_u64(8);
+        for i in 0..5000 {
+            let direction = if i < 8 {
+                Vec3::new((i & 1) as f64 - 0.5, ((i >> 1) & 1) as f64 - 0.5, ((i >> 2) & 1) as f64 - 0.5)
+            } else {
+                Vec3::new(rng.gen_range(-1.0, 1.0), rng.gen_range(-1.0, 1.0), rng.gen_range(-1.0, 1.0))
+            };
+            let ray = Ray::new(Vec3::new(0.0, 0.0, 0.0), direction);
+            let hit = mesh.hit(&ray, 0.0, f64::MAX).expect("ray escaped the cube");
+            assert!(hit.normal.dot(direction) > 0.0);
+            assert!((hit.position.x().abs().max(hit.position.y().abs()).max(hit.position.z().abs()) - 0.5).abs() < EPS);
+        }
+    }
+
+    #[test]
+    fn test_interpolates_shared_normals_and_uvs() {
+        let buffers = MeshBuffers {
+            positions: vec![Vec3::new(0.0, 0.0, 0.0), Vec3::new(2.0, 0.0, 0.0), Vec3::new(0.0, 2.0, 0.0), Vec3::new(2.0, 2.0, 0.0)],
+            normals: vec![Vec3::new(0.0, 0.0, 1.0), Vec3::new(1.0, 0.0, 1.0)],
+            uvs: vec![(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)],
+            faces: vec![
+                MeshFace::new([0, 1, 2]).with_normals([0, 1, 0]).with_uvs([0, 1, 2]),
+                MeshFace::new([1, 3, 2]).with_uvs([1, 3, 2]),
+            ],
+        };
+        let mesh = TriangleMesh::new(buffers, grey()).unwrap();
+
+        let hit = mesh.hit(&Ray::new(Vec3::new(1.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0)), 0.0, f64::MAX).unwrap();
+        let expected = (Vec3::new(0.0, 0.0, 1.0) + Vec3::new(1.0, 0.0, 1.0)).unit_vector();
+        assert!((hit.normal - expected).length() < EPS);
+        assert!((hit.u - 0.5).abs() < EPS && hit.v.abs() < EPS);
+
+        //the second face has no normals and is shaded flat
+        let hit = mesh.hit(&Ray::new(Vec3::new(1.5, 1.5, 1.0), Vec3::new(0.0, 0.0, -1.0)), 0.0, f64::MAX).unwrap();
+        assert!((hit.normal - Vec3::new(0.0, 0.0, 1.0)).length() < EPS);
+        assert!((hit.u - 0.75).abs() < EPS && (hit.v - 0.75).abs() < EPS);
+    }
+
+    #[test]
+    fn test_rejects_bad_indices() {
+        let mut buffers = cube();
+        buffers.faces.push(MeshFace::new([0, 1, 2]).with_uvs([0, 1, 2]));
+        let message = TriangleMesh::new(buffers, grey()).err().unwrap();
+        assert!(message.contains("face 12") && message.contains("uv 0"), "{}", message);
+    }
+
+    #[test]
+    fn test_emissive_faces_are_lights() {
+        let mesh = TriangleMesh::new(cube(), Box::new(DiffuseLight::new(Vec3::new(1.0, 1.0, 1.0)))).unwrap();
+        let lights = LightList::new(&mesh);
+        assert_eq!(lights.len(), 12);
+
+        let origin = Vec3::new(0.1, 3.0, 0.2);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(4);
+        for _ in 0..200 {
+            let sample = lights.sample(origin, &mut rng).unwrap();
+            assert!((lights.pdf(origin, sample.direction) - sample.pdf).abs() < 1e-6 * sample.pdf);
+        }
+    }
+}
@@ -1,9 +1,45 @@
 mod renderable;
 mod sphere;
+mod moving_sphere;
+mod plane;
+mod rect;
+mod quad;
+mod triangle;
+mod mesh;
+mod transformed;
+mod keyframed;
+mod medium;
+mod constant_medium;
+mod density_grid;
+mod grid_medium;
 mod camera;
+mod aperture;
 mod material;
+mod integrator;
+mod renderer;
+mod bvh;
+mod light;
+mod texture;
 
 pub use self::renderable::*;
 pub use self::sphere::*;
+pub use self::moving_sphere::*;
+pub use self::plane::*;
+pub use self::rect::*;
+pub use self::quad::*;
+pub use self::triangle::*;
+pub use self::mesh::*;
+pub use self::transformed::*;
+pub use self::keyframed::*;
+pub use self::medium::*;
+pub use self::constant_medium::*;
+pub use self::density_grid::*;
+pub use self::grid_medium::*;
 pub use self::camera::*;
-pub use self::material::*;
\ No newline at end of file
+pub use self::aperture::*;
+pub use self::material::*;
+pub use self::integrator::*;
+pub use self::renderer::*;
+pub use self::bvh::*;
+pub use self::light::*;
+pub use self::texture::*;
@@ -0,0 +1,91 @@
+use crate::math::Aabb;
+use crate::math::Ray;
+use crate::math::Vec3;
+use crate::render::material::Material;
+use crate::render::renderable::HitRecord;
+use crate::render::renderable::Renderable;
+use crate::render::sphere::hit_sphere;
+
+//sphere moving in a straight line from center0 at time0 to center1 at time1, resting at either end
+//outside that interval. it is never sampled as a light, lights have no notion of time
+pub struct MovingSphere {
+    center0: Vec3,
+    center1: Vec3,
+    time0: f64,
+    time1: f64,
+    radius: f64,
+    material: Box<dyn Material>,
+}
+
+impl MovingSphere {
+    pub fn new(center0: Vec3, center1: Vec3, time0: f64, time1: f64, radius: f64, material: Box<dyn Material>) -> Self {
+        Self {
+            center0,
+            center1,
+            time0,
+            time1,
+            radius,
+            material,
+        }
+    }
+
+    pub fn center(&self, time: f64) -> Vec3 {
+        if self.time1 <= self.time0 {
+            return self.center0;
+        }
+        let f = ((time - self.time0) / (self.time1 - self.time0)).clamp(0_f64, 1_f64);
+        return self.center0 + f * (self.center1 - self.center0);
+    }
+}
+
+impl Renderable for MovingSphere {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        return hit_sphere(self.center(ray.time()), self.radius, &*self.material, ray, t_min, t_max);
+    }
+
+    //the path is a line, so the boxes at both ends cover everything in between
+    fn bounding_box(&self) -> Option<Aabb> {
+        let r = self.radius.abs();
+        let offset = Vec3::new(r, r, r);
+        let start = Aabb::new(self.center0 - offset, self.center0 + offset);
+        let end = Aabb::new(self.center1 - offset, self.center1 + offset);
+        return Some(start.surrounding(&end));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Ray;
+    use crate::math::Vec3;
+    use crate::render::*;
+
+    const EPS: f64 = 1e-9;
+
+    #[test]
+    fn test_position_follows_ray_time() {
+        let sphere = MovingSphere::new(
+            Vec3::new(0.0, 0.0, -5.0),
+            Vec3::new(4.0, 0.0, -5.0),
+            0.0,
+            1.0,
+            1.0,
+            Box::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5))),
+        );
+        let ray = |x: f64, time: f64| Ray::new(Vec3::new(x, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0)).with_time(time);
+
+        assert!(sphere.hit(&ray(0.0, 0.0), 0.0, f64::MAX).is_some());
+        assert!(sphere.hit(&ray(4.0, 0.0), 0.0, f64::MAX).is_none());
+        let hit = sphere.hit(&ray(2.0, 0.5), 0.0, f64::MAX).unwrap();
+        assert!((hit.t - 4.0).abs() < EPS);
+        assert!((hit.normal - Vec3::new(0.0, 0.0, 1.0)).length() < EPS);
+        assert!(sphere.hit(&ray(0.0, 0.5), 0.0, f64::MAX).is_none());
+
+        //rests at the end of its path outside the interval
+        assert!(sphere.hit(&ray(4.0, 3.0), 0.0, f64::MAX).is_some());
+        assert!(sphere.hit(&ray(0.0, -1.0), 0.0, f64::MAX).is_some());
+
+        let bbox = sphere.bounding_box().unwrap();
+        assert!((bbox.min() - Vec3::new(-1.0, -1.0, -6.0)).length() < EPS);
+        assert!((bbox.max() - Vec3::new(5.0, 1.0, -4.0)).length() < EPS);
+    }
+}
@@ -0,0 +1,100 @@
+use crate::math::Aabb;
+use crate::math::Ray;
+use crate::math::Vec3;
+use crate::render::light::orthonormal_basis;
+use crate::render::material::Material;
+use crate::render::renderable::HitRecord;
+use crate::render::renderable::Renderable;
+
+//infinite plane through point, e.g. a ground that reaches the horizon
+pub struct Plane {
+    point: Vec3,
+    normal: Vec3,
+    //in plane axes for the texture coordinates
+    tangent: Vec3,
+    bitangent: Vec3,
+    material: Box<dyn Material>,
+}
+
+impl Plane {
+    pub fn new(point: Vec3, normal: Vec3, material: Box<dyn Material>) -> Self {
+        let normal = normal.unit_vector();
+        let (tangent, bitangent) = orthonormal_basis(normal);
+        Self {
+            point,
+            normal,
+            tangent,
+            bitangent,
+            material,
+        }
+    }
+}
+
+impl Renderable for Plane {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let denominator = self.normal.dot(ray.direction());
+        if denominator.abs() < 1e-12 {
+            return None;
+        }
+
+        let t = (self.point - ray.origin()).dot(self.normal) / denominator;
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        //the texture repeats every world unit
+        let position = ray.point_at_distance(t);
+        let offset = position - self.point;
+        return Some(HitRecord {
+            t,
+            position,
+            normal: self.normal,
+            u: offset.dot(self.tangent).rem_euclid(1_f64),
+            v: offset.dot(self.bitangent).rem_euclid(1_f64),
+            material: &*self.material,
+        });
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        return None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Ray;
+    use crate::math::Vec3;
+    use crate::render::*;
+
+    const EPS: f64 = 1e-9;
+
+    fn ground() -> Plane {
+        return Plane::new(Vec3::new(0.0, -0.5, 0.0), Vec3::new(0.0, 2.0, 0.0), Box::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5))));
+    }
+
+    #[test]
+    fn test_hit() {
+        let plane = ground();
+        let ray = Ray::new(Vec3::new(3.0, 1.5, -7.0), Vec3::new(1.0, -1.0, 0.0));
+        let hit = plane.hit(&ray, 0.0, f64::MAX).unwrap();
+        assert!((hit.t - 2.0).abs() < EPS);
+        assert!((hit.position - Vec3::new(5.0, -0.5, -7.0)).length() < EPS);
+        assert!((hit.normal - Vec3::new(0.0, 1.0, 0.0)).length() < EPS);
+        assert!((0.0..1.0).contains(&hit.u) && (0.0..1.0).contains(&hit.v));
+
+        let parallel = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(plane.hit(&parallel, 0.0, f64::MAX).is_none());
+        let away = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        assert!(plane.hit(&away, 0.0, f64::MAX).is_none());
+        assert!(plane.bounding_box().is_none());
+    }
+
+    #[test]
+    fn test_uv_tiles_every_unit() {
+        let plane = ground();
+        let down = |x: f64, z: f64| Ray::new(Vec3::new(x, 1.0, z), Vec3::new(0.0, -1.0, 0.0));
+        let a = plane.hit(&down(0.3, 0.6), 0.0, f64::MAX).unwrap();
+        let b = plane.hit(&down(2.3, -4.4), 0.0, f64::MAX).unwrap();
+        assert!((a.u - b.u).abs() < EPS && (a.v - b.v).abs() < EPS);
+    }
+}
@@ -0,0 +1,190 @@
+use crate::math::Aabb;
+use crate::math::Ray;
+use crate::math::Vec3;
+use crate::render::light::area_to_solid_angle;
+use crate::render::light::sample_towards;
+use crate::render::light::Light;
+use crate::render::light::LightSample;
+use crate::render::material::Material;
+use crate::render::renderable::HitRecord;
+use crate::render::renderable::Renderable;
+
+use rand::Rng;
+use rand::RngCore;
+
+//thickness given to the bounding boxes of flat shapes
+pub const FLAT_BOX_PADDING: f64 = 1e-4;
+
+//parallelogram spanned by the edges u and v from corner, facing u x v
+pub struct Quad {
+    corner: Vec3,
+    u: Vec3,
+    v: Vec3,
+    material: Box<dyn Material>,
+    normal: Vec3,
+    //n / (n . n) for the unnormalized n = u x v, turns plane offsets into edge coordinates
+    w: Vec3,
+    //plane equation normal . p = d
+    d: f64,
+    area: f64,
+}
+
+impl Quad {
+    pub fn new(corner: Vec3, u: Vec3, v: Vec3, material: Box<dyn Material>) -> Self {
+        let n = u.cross(v);
+        let normal = n.unit_vector();
+        Self {
+            corner,
+            u,
+            v,
+            material,
+            normal,
+            w: n / n.dot(n),
+            d: normal.dot(corner),
+            area: n.length(),
+        }
+    }
+
+    //faces the other way, e.g. for the inside walls of a box. the plane stays where it is
+    pub fn flipped(self) -> Self {
+        Self {
+            normal: -self.normal,
+            d: -self.d,
+            ..self
+        }
+    }
+
+    pub fn area(&self) -> f64 {
+        return self.area;
+    }
+
+    pub fn normal(&self) -> Vec3 {
+        return self.normal;
+    }
+}
+
+impl Renderable for Quad {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let denominator = self.normal.dot(ray.direction());
+        if denominator.abs() < 1e-12 {
+            return None;
+        }
+
+        let t = (self.d - self.normal.dot(ray.origin())) / denominator;
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        let position = ray.point_at_distance(t);
+        let planar = position - self.corner;
+        let alpha = self.w.dot(planar.cross(self.v));
+        let beta = self.w.dot(self.u.cross(planar));
+        if !(0_f64..=1_f64).contains(&alpha) || !(0_f64..=1_f64).contains(&beta) {
+            return None;
+        }
+
+        return Some(HitRecord {
+            t,
+            position,
+            normal: self.normal,
+            u: alpha,
+            v: beta,
+            material: &*self.material,
+        });
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let diagonal = Aabb::from_points(self.corner, self.corner + self.u + self.v);
+        let other = Aabb::from_points(self.corner + self.u, self.corner + self.v);
+        return Some(diagonal.surrounding(&other).padded(FLAT_BOX_PADDING));
+    }
+
+    fn collect_lights<'a>(&'a self, lights: &mut Vec<&'a dyn Light>) {
+        if self.material.is_emissive() {
+            lights.push(self);
+        }
+    }
+}
+
+impl Light for Quad {
+    fn sample(&self, origin: Vec3, rng: &mut dyn RngCore) -> Option<LightSample> {
+        let point = self.corner + rng.gen::<f64>() * self.u + rng.gen::<f64>() * self.v;
+        return sample_towards(origin, point, area_to_solid_angle(origin, point, self.normal, self.area));
+    }
+
+    fn pdf(&self, origin: Vec3, direction: Vec3) -> f64 {
+        return match self.hit(&Ray::new(origin, direction), 1e-9, f64::MAX) {
+            Some(hit) => area_to_solid_angle(origin, hit.position, self.normal, self.area),
+            None => 0_f64,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Ray;
+    use crate::math::Vec3;
+    use crate::render::*;
+
+    use rand::SeedableRng;
+
+    const EPS: f64 = 1e-9;
+
+    fn slanted_quad() -> Quad {
+        //a 2 x 1 rectangle tilted about the x axis, plus a shear along x
+        return Quad::new(
+            Vec3::new(-1.0, 0.0, -1.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(0.5, 1.0, 0.0),
+            Box::new(DiffuseLight::new(Vec3::new(1.0, 1.0, 1.0))),
+        );
+    }
+
+    #[test]
+    fn test_hit_normal_and_uv() {
+        let quad = slanted_quad();
+        let ray = Ray::new(Vec3::new(0.25, 0.5, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = quad.hit(&ray, 0.0, f64::MAX).unwrap();
+
+        assert!((hit.t - 6.0).abs() < EPS);
+        assert!((hit.normal - Vec3::new(0.0, 0.0, 1.0)).length() < EPS);
+        //0.25 = -1 + 2 alpha + 0.5 beta with beta = 0.5
+        assert!((hit.u - 0.5).abs() < EPS);
+        assert!((hit.v - 0.5).abs() < EPS);
+
+        let flipped = slanted_quad().flipped();
+        let flipped_hit = flipped.hit(&ray, 0.0, f64::MAX).unwrap();
+        assert!((flipped_hit.normal - Vec3::new(0.0, 0.0, -1.0)).length() < EPS);
+        assert!((flipped_hit.t - hit.t).abs() < EPS);
+        assert!((flipped_hit.position - hit.position).length() < EPS);
+        assert!((quad.area() - 2.0).abs() < EPS);
+    }
+
+    #[test]
+    fn test_misses_outside_edges() {
+        let quad = slanted_quad();
+        //inside the bounding box but past the sheared edge
+        let ray = Ray::new(Vec3::new(-0.9, 0.9, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(quad.hit(&ray, 0.0, f64::MAX).is_none());
+        let parallel = Ray::new(Vec3::new(0.0, 0.5, -1.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(quad.hit(&parallel, 0.0, f64::MAX).is_none());
+
+        let bbox = quad.bounding_box().unwrap();
+        assert!(bbox.max().z() > bbox.min().z());
+    }
+
+    #[test]
+    fn test_light_sample_matches_pdf() {
+        let quad = slanted_quad();
+        let origin = Vec3::new(0.3, 0.2, 2.0);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(17);
+
+        for _ in 0..1000 {
+            let sample = quad.sample(origin, &mut rng).unwrap();
+            let pdf = quad.pdf(origin, sample.direction);
+            assert!((pdf - sample.pdf).abs() < 1e-6 * sample.pdf);
+            assert!((sample.distance * sample.direction.z() + 3.0).abs() < 1e-9);
+        }
+        assert_eq!(quad.pdf(origin, Vec3::new(0.0, 0.0, 1.0)), 0.0);
+    }
+}
@@ -0,0 +1,214 @@
+use crate::math::Aabb;
+use crate::math::Ray;
+use crate::math::Vec3;
+use crate::render::light::area_to_solid_angle;
+use crate::render::light::sample_towards;
+use crate::render::light::Light;
+use crate::render::light::LightSample;
+use crate::render::material::Material;
+use crate::render::quad::FLAT_BOX_PADDING;
+use crate::render::renderable::HitRecord;
+use crate::render::renderable::Renderable;
+
+use rand::Rng;
+use rand::RngCore;
+
+//rectangle at AXIS = k, spanning [a0, a1] x [b0, b1] along the other two axes in order.
+//cheaper than a Quad since the plane test is a single division
+pub struct AxisRect<const AXIS: i32> {
+    a0: f64,
+    a1: f64,
+    b0: f64,
+    b1: f64,
+    k: f64,
+    material: Box<dyn Material>,
+    //+1 faces along the positive axis, -1 the negative one
+    facing: f64,
+}
+
+pub type XYRect = AxisRect<2>;
+pub type XZRect = AxisRect<1>;
+pub type YZRect = AxisRect<0>;
+
+impl XYRect {
+    pub fn new(x0: f64, x1: f64, y0: f64, y1: f64, k: f64, material: Box<dyn Material>) -> Self {
+        return AxisRect::from_bounds(x0, x1, y0, y1, k, material);
+    }
+}
+
+impl XZRect {
+    pub fn new(x0: f64, x1: f64, z0: f64, z1: f64, k: f64, material: Box<dyn Material>) -> Self {
+        return AxisRect::from_bounds(x0, x1, z0, z1, k, material);
+    }
+}
+
+impl YZRect {
+    pub fn new(y0: f64, y1: f64, z0: f64, z1: f64, k: f64, material: Box<dyn Material>) -> Self {
+        return AxisRect::from_bounds(y0, y1, z0, z1, k, material);
+    }
+}
+
+impl<const AXIS: i32> AxisRect<AXIS> {
+    //the two in plane axes, in the order of the constructor arguments
+    const A: i32 = if AXIS == 0 { 1 } else { 0 };
+    const B: i32 = if AXIS == 2 { 1 } else { 2 };
+
+    fn from_bounds(a0: f64, a1: f64, b0: f64, b1: f64, k: f64, material: Box<dyn Material>) -> Self {
+        Self {
+            a0: a0.min(a1),
+            a1: a0.max(a1),
+            b0: b0.min(b1),
+            b1: b0.max(b1),
+            k,
+            material,
+            facing: 1_f64,
+        }
+    }
+
+    //faces the negative axis, e.g. for a ceiling light shining down
+    pub fn flipped(self) -> Self {
+        Self {
+            facing: -self.facing,
+            ..self
+        }
+    }
+
+    pub fn area(&self) -> f64 {
+        return (self.a1 - self.a0) * (self.b1 - self.b0);
+    }
+
+    pub fn normal(&self) -> Vec3 {
+        let mut normal = Vec3::new(0.0, 0.0, 0.0);
+        normal[AXIS] = self.facing;
+        return normal;
+    }
+
+    fn point(&self, a: f64, b: f64) -> Vec3 {
+        let mut point = Vec3::new(0.0, 0.0, 0.0);
+        point[AXIS] = self.k;
+        point[Self::A] = a;
+        point[Self::B] = b;
+        return point;
+    }
+}
+
+impl<const AXIS: i32> Renderable for AxisRect<AXIS> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let origin = ray.origin();
+        let direction = ray.direction();
+        let t = (self.k - origin[AXIS]) / direction[AXIS];
+        //also rejects the NaN of a ray lying in the plane
+        if !(t >= t_min && t <= t_max) {
+            return None;
+        }
+
+        let a = origin[Self::A] + t * direction[Self::A];
+        let b = origin[Self::B] + t * direction[Self::B];
+        if a < self.a0 || a > self.a1 || b < self.b0 || b > self.b1 {
+            return None;
+        }
+
+        return Some(HitRecord {
+            t,
+            position: self.point(a, b),
+            normal: self.normal(),
+            u: (a - self.a0) / (self.a1 - self.a0),
+            v: (b - self.b0) / (self.b1 - self.b0),
+            material: &*self.material,
+        });
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let bbox = Aabb::from_points(self.point(self.a0, self.b0), self.point(self.a1, self.b1));
+        return Some(bbox.padded(FLAT_BOX_PADDING));
+    }
+
+    fn collect_lights<'a>(&'a self, lights: &mut Vec<&'a dyn Light>) {
+        if self.material.is_emissive() {
+            lights.push(self);
+        }
+    }
+}
+
+impl<const AXIS: i32> Light for AxisRect<AXIS> {
+    fn sample(&self, origin: Vec3, rng: &mut dyn RngCore) -> Option<LightSample> {
+        let a = rng.gen_range(self.a0, self.a1);
+        let b = rng.gen_range(self.b0, self.b1);
+        let point = self.point(a, b);
+        return sample_towards(origin, point, area_to_solid_angle(origin, point, self.normal(), self.area()));
+    }
+
+    fn pdf(&self, origin: Vec3, direction: Vec3) -> f64 {
+        return match self.hit(&Ray::new(origin, direction), 1e-9, f64::MAX) {
+            Some(hit) => area_to_solid_angle(origin, hit.position, hit.normal, self.area()),
+            None => 0_f64,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Ray;
+    use crate::math::Vec3;
+    use crate::render::*;
+
+    use rand::SeedableRng;
+
+    const EPS: f64 = 1e-9;
+
+    fn grey() -> Box<dyn Material> {
+        return Box::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)));
+    }
+
+    #[test]
+    fn test_each_axis() {
+        let xy = XYRect::new(0.0, 2.0, 0.0, 4.0, -1.0, grey());
+        let hit = xy.hit(&Ray::new(Vec3::new(0.5, 1.0, 3.0), Vec3::new(0.0, 0.0, -1.0)), 0.0, f64::MAX).unwrap();
+        assert!((hit.t - 4.0).abs() < EPS);
+        assert!((hit.position - Vec3::new(0.5, 1.0, -1.0)).length() < EPS);
+        assert_eq!(hit.normal, Vec3::new(0.0, 0.0, 1.0));
+        assert!((hit.u - 0.25).abs() < EPS && (hit.v - 0.25).abs() < EPS);
+
+        let xz = XZRect::new(-1.0, 1.0, -1.0, 1.0, 2.0, grey()).flipped();
+        let hit = xz.hit(&Ray::new(Vec3::new(0.5, 0.0, -0.5), Vec3::new(0.0, 1.0, 0.0)), 0.0, f64::MAX).unwrap();
+        assert_eq!(hit.normal, Vec3::new(0.0, -1.0, 0.0));
+        assert!((hit.u - 0.75).abs() < EPS && (hit.v - 0.25).abs() < EPS);
+
+        let yz = YZRect::new(0.0, 1.0, 0.0, 1.0, 3.0, grey());
+        let hit = yz.hit(&Ray::new(Vec3::new(0.0, 0.2, 0.9), Vec3::new(1.0, 0.0, 0.0)), 0.0, f64::MAX).unwrap();
+        assert_eq!(hit.normal, Vec3::new(1.0, 0.0, 0.0));
+        assert!((hit.position - Vec3::new(3.0, 0.2, 0.9)).length() < EPS);
+        assert!((hit.u - 0.2).abs() < EPS && (hit.v - 0.9).abs() < EPS);
+    }
+
+    #[test]
+    fn test_misses() {
+        let xz = XZRect::new(-1.0, 1.0, -1.0, 1.0, 0.0, grey());
+        assert!(xz.hit(&Ray::new(Vec3::new(1.5, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0)), 0.0, f64::MAX).is_none());
+        //lying in the plane
+        assert!(xz.hit(&Ray::new(Vec3::new(-2.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0)), 0.0, f64::MAX).is_none());
+        //behind the origin
+        assert!(xz.hit(&Ray::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 0.0)), 0.0, f64::MAX).is_none());
+
+        let bbox = xz.bounding_box().unwrap();
+        assert!(bbox.min().y() < 0.0 && bbox.max().y() > 0.0);
+        assert_eq!(bbox.max().x(), 1.0);
+    }
+
+    #[test]
+    fn test_matches_quad() {
+        //the same square as a general quad gives the same light density
+        let light = || -> Box<dyn Material> { Box::new(DiffuseLight::new(Vec3::new(1.0, 1.0, 1.0))) };
+        let rect = XZRect::new(-0.5, 0.5, -0.5, 0.5, 2.0, light()).flipped();
+        let quad = Quad::new(Vec3::new(-0.5, 2.0, -0.5), Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0), light());
+        assert_eq!(quad.normal(), rect.normal());
+
+        let origin = Vec3::new(0.2, 0.0, -0.3);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(3);
+        for _ in 0..1000 {
+            let sample = rect.sample(origin, &mut rng).unwrap();
+            assert!((rect.pdf(origin, sample.direction) - sample.pdf).abs() < 1e-6 * sample.pdf);
+            assert!((quad.pdf(origin, sample.direction) - sample.pdf).abs() < 1e-6 * sample.pdf);
+        }
+    }
+}
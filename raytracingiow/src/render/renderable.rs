@@ -1,5 +1,7 @@
+use crate::math::Aabb;
 use crate::math::Ray;
 use crate::math::Vec3;
+use crate::render::light::Light;
 use crate::render::material::Material;
 
 pub struct HitRecord<'a> {
@@ -7,16 +9,49 @@ pub struct HitRecord<'a> {
     pub t: f64,
     pub position: Vec3,
     pub normal: Vec3,
+    //surface coordinates in [0, 1] for looking up textures
+    pub u: f64,
+    pub v: f64,
     pub material: &'a dyn Material,
 }
 
-pub trait Renderable {
+pub trait Renderable: Send + Sync {
     //if the ray hits the renderable between t_min and t_max
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord>;
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>>;
+
+    //box enclosing the renderable, None if it is unbounded
+    fn bounding_box(&self) -> Option<Aabb>;
+
+    //adds every emissive part that can be sampled directly
+    fn collect_lights<'a>(&'a self, _lights: &mut Vec<&'a dyn Light>) {}
+
+    //fraction of light getting through between t_min and t_max, for shadow rays. surfaces stop it all,
+    //media estimate how much of it their particles let through instead of stopping at a collision
+    fn transmittance(&self, ray: &Ray, t_min: f64, t_max: f64) -> f64 {
+        return if self.hit(ray, t_min, t_max).is_some() { 0_f64 } else { 1_f64 };
+    }
 }
 
-impl<T: Renderable> Renderable for Vec<T> {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+impl<T: Renderable + ?Sized> Renderable for Box<T> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        return (**self).hit(ray, t_min, t_max);
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        return (**self).bounding_box();
+    }
+
+    fn collect_lights<'a>(&'a self, lights: &mut Vec<&'a dyn Light>) {
+        (**self).collect_lights(lights);
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f64, t_max: f64) -> f64 {
+        return (**self).transmittance(ray, t_min, t_max);
+    }
+}
+
+impl<T: Renderable> Renderable for [T] {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
         let mut max = t_max;
         let mut curr: Option<HitRecord> = Option::None;
 
@@ -32,4 +67,53 @@ impl<T: Renderable> Renderable for Vec<T> {
 
         return curr;
     }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let mut bbox: Option<Aabb> = None;
+
+        for item in self {
+            let item_box = item.bounding_box()?;
+            bbox = match bbox {
+                Some(b) => Some(b.surrounding(&item_box)),
+                None => Some(item_box),
+            };
+        }
+
+        return bbox;
+    }
+
+    fn collect_lights<'a>(&'a self, lights: &mut Vec<&'a dyn Light>) {
+        for item in self {
+            item.collect_lights(lights);
+        }
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f64, t_max: f64) -> f64 {
+        let mut transmittance = 1_f64;
+        for item in self {
+            transmittance *= item.transmittance(ray, t_min, t_max);
+            if transmittance == 0_f64 {
+                break;
+            }
+        }
+        return transmittance;
+    }
+}
+
+impl<T: Renderable> Renderable for Vec<T> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        return self.as_slice().hit(ray, t_min, t_max);
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        return self.as_slice().bounding_box();
+    }
+
+    fn collect_lights<'a>(&'a self, lights: &mut Vec<&'a dyn Light>) {
+        self.as_slice().collect_lights(lights);
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f64, t_max: f64) -> f64 {
+        return self.as_slice().transmittance(ray, t_min, t_max);
+    }
 }
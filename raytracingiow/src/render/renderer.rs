@@ -0,0 +1,260 @@
+use crate::color::RGB;
+use crate::math::Vec3;
+use crate::output::Image;
+use crate::output::ToneMap;
+use crate::render::camera::Camera;
+use crate::render::integrator::Background;
+use crate::render::integrator::ColorFn;
+use crate::render::integrator::Integrator;
+use crate::render::light::LightList;
+use crate::render::renderable::Renderable;
+
+use rand::rngs::StdRng;
+use rand::Rng;
+use rand::SeedableRng;
+use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
+
+pub struct RenderSettings {
+    pub width: usize,
+    pub height: usize,
+    pub samples_per_pixel: u32,
+    pub max_bounces: u32,
+    pub integrator: Integrator,
+    pub background: Background,
+    //the same seed always produces the same image, whatever the thread count or tile size
+    pub seed: u64,
+    //edge length in pixels of the square tiles handed out to worker threads
+    pub tile_size: usize,
+    //number of worker threads, None uses every core
+    pub threads: Option<usize>,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            width: 600,
+            height: 300,
+            samples_per_pixel: 100,
+            max_bounces: 50,
+            integrator: Integrator::Path,
+            background: Background::Sky,
+            seed: 0,
+            tile_size: 32,
+            threads: None,
+        }
+    }
+}
+
+//rectangle of the output buffer, x and y are the top left corner in buffer coordinates
+struct Tile {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+}
+
+fn create_tiles(x_size: usize, y_size: usize, tile_size: usize) -> Vec<Tile> {
+    let tile_size = tile_size.max(1);
+    let mut tiles: Vec<Tile> = Vec::new();
+
+    for y in (0..y_size).step_by(tile_size) {
+        for x in (0..x_size).step_by(tile_size) {
+            tiles.push(Tile {
+                x,
+                y,
+                width: tile_size.min(x_size - x),
+                height: tile_size.min(y_size - y),
+            });
+        }
+    }
+
+    return tiles;
+}
+
+//every pixel gets its own random stream so the result does not depend on which thread renders it
+fn pixel_rng(seed: u64, pixel_index: usize) -> StdRng {
+    return StdRng::seed_from_u64(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ pixel_index as u64);
+}
+
+fn render_tile(world: &dyn Renderable, lights: &LightList, camera: &Camera, settings: &RenderSettings, tile: &Tile) -> Vec<Vec3> {
+    let (x_size, y_size) = (settings.width, settings.height);
+    let mut buffer: Vec<Vec3> = Vec::with_capacity(tile.width * tile.height);
+
+    for row in tile.y..tile.y + tile.height {
+        //buffer rows run top to bottom, v runs bottom to top
+        let j = y_size - 1 - row;
+
+        for i in tile.x..tile.x + tile.width {
+            let mut rng = pixel_rng(settings.seed, row * x_size + i);
+
+            let mut color = Vec3::new(0.0, 0.0, 0.0);
+            for _s in 0..settings.samples_per_pixel {
+
+                let rand_u: f64 = rng.gen();
+                let rand_v: f64 = rng.gen();
+
+                let u = (i as f64 + rand_u) / (x_size as f64);
+                let v = (j as f64 + rand_v) / (y_size as f64);
+                let ray = camera.get_ray(u, v, &mut rng);
+
+                let color_sample = settings.integrator.color(&ray, world, lights, settings, &mut rng);
+
+                color += color_sample;
+            }
+
+            color /= settings.samples_per_pixel as f64;
+            buffer.push(color);
+        }
+    }
+
+    return buffer;
+}
+
+//renders the world as seen by the camera into a row-major 0xRRGGBB buffer, top row first
+pub fn render(world: &dyn Renderable, camera: &Camera, settings: &RenderSettings) -> Vec<u32> {
+    return render_image(world, camera, settings).to_u32_buffer(&ToneMap::default());
+}
+
+//renders the linear radiance seen by the camera
+pub fn render_image(world: &dyn Renderable, camera: &Camera, settings: &RenderSettings) -> Image {
+    let (x_size, y_size) = (settings.width, settings.height);
+    let tiles = create_tiles(x_size, y_size, settings.tile_size);
+    let lights = LightList::new(world);
+
+    let render_tiles = || -> Vec<Vec<Vec3>> {
+        return tiles
+            .par_iter()
+            .map(|tile| render_tile(world, &lights, camera, settings, tile))
+            .collect();
+    };
+
+    let rendered = match settings.threads {
+        Some(threads) => ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .expect("failed to create render thread pool")
+            .install(render_tiles),
+        None => render_tiles(),
+    };
+
+    let mut image = Image::new(x_size, y_size);
+    for (tile, pixels) in tiles.iter().zip(rendered) {
+        for (row, tile_row) in pixels.chunks(tile.width).enumerate() {
+            let start = (tile.y + row) * x_size + tile.x;
+            image.pixels_mut()[start..start + tile.width].copy_from_slice(tile_row);
+        }
+    }
+
+    return image;
+}
+
+//at the origin looking down -z with a 90 degree vertical fov,
+//for a 2:1 image this is the book's x: [-2, 2], y[-1, 1] image plane at z = -1
+pub fn default_camera(aspect: f64) -> Camera {
+    let look_from = Vec3::new(0.0, 0.0, 0.0);
+    let look_at = Vec3::new(0.0, 0.0, -1.0);
+    let vup = Vec3::new(0.0, 1.0, 0.0);
+    return Camera::new(look_from, look_at, vup, 90.0, aspect);
+}
+
+//chapter 1
+pub fn create_buffer(x_size: usize, y_size: usize) -> Vec<u32> {
+    let mut buffer: Vec<u32> = Vec::new();
+
+    for j in (0..y_size).rev() {
+        for i in 0..x_size {
+            //cast into range from 0 to 1.0
+            let rgb_vec = Vec3::new(
+                (i as f64) / (x_size as f64),
+                (j as f64) / (y_size as f64),
+                0.2,
+            );
+
+            let rgb = rgb_vec.to_u32_rgb();
+            buffer.push(rgb);
+        }
+    }
+
+    return buffer;
+}
+
+pub fn create_ray_buffer(x_size: usize, y_size: usize, world: &dyn Renderable, ray_fn: ColorFn) -> Vec<u32> {
+    let mut buffer: Vec<u32> = Vec::new();
+    let camera = default_camera(x_size as f64 / y_size as f64);
+    let mut rng = rand::thread_rng();
+
+    for j in (0..y_size).rev() {
+        for i in 0..x_size {
+            let u = (i as f64) / (x_size as f64);
+            let v = (j as f64) / (y_size as f64);
+            let ray = camera.get_ray(u, v, &mut rng);
+            let color = ray_fn(&ray, world);
+            let rgb = color.to_u32_rgb();
+            buffer.push(rgb);
+        }
+    }
+
+    return buffer;
+}
+
+//chapter 6
+pub fn create_ray_buffer_antialias(x_size: usize, y_size: usize, world: &dyn Renderable, color_fn: ColorFn, alias_num: u32) -> Vec<u32> {
+    let settings = RenderSettings {
+        width: x_size,
+        height: y_size,
+        samples_per_pixel: alias_num,
+        integrator: Integrator::Custom(color_fn),
+        ..RenderSettings::default()
+    };
+    let camera = default_camera(x_size as f64 / y_size as f64);
+    return render(world, &camera, &settings);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Vec3;
+    use crate::render::*;
+
+    #[test]
+    fn test_render_background() {
+        let world: Vec<Sphere> = Vec::new();
+        let settings = RenderSettings {
+            width: 4,
+            height: 2,
+            samples_per_pixel: 2,
+            integrator: Integrator::Background,
+            ..RenderSettings::default()
+        };
+        let buffer = render(&world, &default_camera(2.0), &settings);
+        assert_eq!(buffer.len(), 8);
+
+        //sky is bluer at the top of the image
+        assert!(buffer[0] & 0xFF0000 < buffer[7] & 0xFF0000);
+    }
+
+    #[test]
+    fn test_render_is_deterministic() {
+        let material = || Box::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)));
+        let world = vec![
+            Sphere::new(Vec3::new(0.0, 0.0, -1.0), 0.5, material()),
+            Sphere::new(Vec3::new(0.0, -100.5, -1.0), 100.0, material()),
+        ];
+        let settings = |threads, tile_size| RenderSettings {
+            width: 23,
+            height: 11,
+            samples_per_pixel: 4,
+            threads: Some(threads),
+            tile_size,
+            seed: 42,
+            ..RenderSettings::default()
+        };
+
+        let expected = render(&world, &default_camera(2.0), &settings(1, 64));
+        assert_eq!(expected, render(&world, &default_camera(2.0), &settings(4, 5)));
+        assert_eq!(expected, render(&world, &default_camera(2.0), &settings(3, 1)));
+
+        let reseeded = RenderSettings { seed: 43, ..settings(1, 64) };
+        assert_ne!(expected, render(&world, &default_camera(2.0), &reseeded));
+    }
+}
@@ -1,10 +1,20 @@
 
+use crate::math::Aabb;
 use crate::math::Vec3;
 use crate::math::Ray;
+use crate::render::light::area_to_solid_angle;
+use crate::render::light::cone_extent;
+use crate::render::light::sample_cone;
+use crate::render::light::sample_sphere_area;
+use crate::render::light::Light;
+use crate::render::light::LightSample;
 use crate::render::renderable::HitRecord;
 use crate::render::renderable::Renderable;
 use crate::render::material::Material;
 
+use rand::RngCore;
+use std::f64::consts::PI;
+
 pub struct Sphere {
     center: Vec3,
     radius: f64,
@@ -15,45 +25,178 @@ impl Sphere {
     pub fn new(center: Vec3, radius: f64, material: Box<dyn Material>) -> Self {
         Self { center, radius, material }
     }
+}
+
+impl Renderable for Sphere {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        return hit_sphere(self.center, self.radius, &*self.material, ray, t_min, t_max);
+    }
 
-    fn create_hit_record(&self, ray: &Ray, t: f64) -> HitRecord {
-        let position = ray.point_at_distance(t);
-        let normal = (position - self.center).unit_vector();
-        HitRecord {
-            t,
-            position,
-            normal,
-            material: &*self.material
+    fn bounding_box(&self) -> Option<Aabb> {
+        let r = self.radius.abs();
+        let offset = Vec3::new(r, r, r);
+        return Some(Aabb::new(self.center - offset, self.center + offset));
+    }
+
+    fn collect_lights<'a>(&'a self, lights: &mut Vec<&'a dyn Light>) {
+        if self.material.is_emissive() {
+            lights.push(self);
         }
     }
 }
 
-impl Renderable for Sphere {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
-        let ac = ray.origin() - self.center;
-        let a = ray.direction().dot(ray.direction());
-        let b = 2.0 * ray.direction().dot(ac);
-        let c = ac.dot(ac) - self.radius * self.radius;
-
-        let discriminant = b.powi(2) - 4_f64 * a * c;
-
-        if discriminant < 0.0 {
-            return Option::None;
-        } else {
-            //quadratic formula
-            let t = (-b - discriminant.sqrt()) / (2.0 * a);
-            if t >= t_min && t <= t_max {
-                let hit_record = self.create_hit_record(ray, t);
-                return Option::Some(hit_record);
-            }
+//shared with moving spheres, which pass the center they have at the time of the ray
+pub fn hit_sphere<'a>(center: Vec3, radius: f64, material: &'a dyn Material, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'a>> {
+    let ac = ray.origin() - center;
+    let a = ray.direction().dot(ray.direction());
+    let b = 2.0 * ray.direction().dot(ac);
+    let c = ac.dot(ac) - radius * radius;
 
-            let t2 = (-b + discriminant.sqrt()) / (2.0 * a);
-            if t2 >= t_min && t2 <= t_max {
-                let hit_record = self.create_hit_record(ray, t);
-                return Option::Some(hit_record);
+    let discriminant = b.powi(2) - 4_f64 * a * c;
+
+    if discriminant < 0.0 {
+        return Option::None;
+    }
+
+    //quadratic formula, the nearer root first
+    for t in [(-b - discriminant.sqrt()) / (2.0 * a), (-b + discriminant.sqrt()) / (2.0 * a)] {
+        if t >= t_min && t <= t_max {
+            let position = ray.point_at_distance(t);
+            //dividing by the signed radius makes the normal point inwards for negative radii,
+            //which turns a sphere into a hollow bubble
+            let normal = (position - center) / radius;
+            let (u, v) = sphere_uv((position - center) / radius.abs());
+            return Some(HitRecord {
+                t,
+                position,
+                normal,
+                u,
+                v,
+                material,
+            });
+        }
+    }
+
+    return Option::None;
+}
+
+//u runs around the y axis starting from -x, v from the bottom pole to the top one
+fn sphere_uv(p: Vec3) -> (f64, f64) {
+    let theta = (-p.y()).clamp(-1_f64, 1_f64).acos();
+    let phi = (-p.z()).atan2(p.x()) + PI;
+    return (phi / (2_f64 * PI), theta / PI);
+}
+
+impl Light for Sphere {
+    //from outside only the visible cap matters, sample the cone it subtends.
+    //from inside every direction reaches the sphere, sample its area
+    fn sample(&self, origin: Vec3, rng: &mut dyn RngCore) -> Option<LightSample> {
+        let radius = self.radius.abs();
+        let to_center = self.center - origin;
+        let d2 = to_center.length_squared();
+
+        if d2 <= radius * radius {
+            let point = sample_sphere_area(self.center, radius, rng);
+            let normal = (point - self.center) / radius;
+            let pdf = area_to_solid_angle(origin, point, normal, 4_f64 * PI * radius * radius);
+            if pdf <= 0_f64 {
+                return None;
             }
+            let to_point = point - origin;
+            return Some(LightSample {
+                direction: to_point.unit_vector(),
+                distance: to_point.length(),
+                pdf,
+            });
+        }
+
+        let extent = cone_extent(radius, d2);
+        let direction = sample_cone(to_center.unit_vector(), extent, rng);
+
+        //nearest intersection, a grazing direction falls back to the closest approach
+        let b = direction.dot(to_center);
+        let discriminant = b * b - (d2 - radius * radius);
+        let distance = b - discriminant.max(0_f64).sqrt();
+
+        return Some(LightSample {
+            direction,
+            distance,
+            pdf: 1_f64 / (2_f64 * PI * extent),
+        });
+    }
+
+    fn pdf(&self, origin: Vec3, direction: Vec3) -> f64 {
+        let radius = self.radius.abs();
+        let to_center = self.center - origin;
+        let d2 = to_center.length_squared();
+        let b = direction.dot(to_center);
+        let discriminant = b * b - (d2 - radius * radius);
+
+        if d2 <= radius * radius {
+            //the far root is the only one in front of a point inside
+            let point = origin + (b + discriminant.max(0_f64).sqrt()) * direction;
+            let normal = (point - self.center) / radius;
+            return area_to_solid_angle(origin, point, normal, 4_f64 * PI * radius * radius);
+        }
+
+        let extent = cone_extent(radius, d2);
+        let cos_max = 1_f64 - extent;
+        if b <= 0_f64 || b / d2.sqrt() < cos_max {
+            return 0_f64;
+        }
+        return 1_f64 / (2_f64 * PI * extent);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Ray;
+    use crate::math::Vec3;
+    use crate::render::*;
+
+    const EPS: f64 = 1e-9;
+
+    fn uv_towards(sphere: &Sphere, direction: Vec3) -> (f64, f64) {
+        //shoot from outside through the center so the first hit is on the side facing direction
+        let ray = Ray::new(Vec3::new(1.0, 2.0, 3.0) + 10.0 * direction, -direction);
+        let hit = sphere.hit(&ray, 0.0, f64::MAX).unwrap();
+        return (hit.u, hit.v);
+    }
+
+    #[test]
+    fn test_uv() {
+        let sphere = Sphere::new(Vec3::new(1.0, 2.0, 3.0), 2.0, Box::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5))));
+
+        let (_, v) = uv_towards(&sphere, Vec3::new(0.0, 1.0, 0.0));
+        assert!((v - 1.0).abs() < EPS);
+        let (_, v) = uv_towards(&sphere, Vec3::new(0.0, -1.0, 0.0));
+        assert!(v.abs() < EPS);
+
+        let expected = [
+            (Vec3::new(-1.0, 0.0, 0.0), 0.0),
+            (Vec3::new(0.0, 0.0, 1.0), 0.25),
+            (Vec3::new(1.0, 0.0, 0.0), 0.5),
+            (Vec3::new(0.0, 0.0, -1.0), 0.75),
+        ];
+        for (direction, u) in &expected {
+            let (hit_u, hit_v) = uv_towards(&sphere, *direction);
+            //the seam at u = 0 may come out as 1
+            let seam = *u == 0.0 && (hit_u - 1.0).abs() < EPS;
+            assert!((hit_u - u).abs() < EPS || seam, "{:?} {}", direction, hit_u);
+            assert!((hit_v - 0.5).abs() < EPS);
+        }
+    }
+
+    #[test]
+    fn test_hollow_sphere_uv_matches_outer() {
+        //the flipped normal of a negative radius must not mirror the texture
+        let material = || -> Box<dyn Material> { Box::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5))) };
+        let outer = Sphere::new(Vec3::new(0.0, 0.0, 0.0), 1.0, material());
+        let inner = Sphere::new(Vec3::new(0.0, 0.0, 0.0), -1.0, material());
+        let ray = Ray::new(Vec3::new(0.3, 5.0, 0.4), Vec3::new(0.0, -1.0, 0.0));
 
-            return Option::None;
-        };
+        let (a, b) = (outer.hit(&ray, 0.0, f64::MAX).unwrap(), inner.hit(&ray, 0.0, f64::MAX).unwrap());
+        assert!((a.u - b.u).abs() < EPS && (a.v - b.v).abs() < EPS);
+        assert!((a.normal + b.normal).length() < EPS);
     }
 }
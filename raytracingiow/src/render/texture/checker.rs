@@ -0,0 +1,88 @@
+use crate::math::Vec3;
+use crate::render::texture::texture::Texture;
+
+enum CheckerSpace {
+    //u_cells by v_cells squares over the surface coordinates
+    Uv { u_cells: f64, v_cells: f64 },
+    //cubes of the given edge length filling space, the surface shows where it cuts them
+    Solid { size: f64 },
+}
+
+//alternates between two textures
+pub struct CheckerTexture {
+    even: Box<dyn Texture>,
+    odd: Box<dyn Texture>,
+    space: CheckerSpace,
+}
+
+impl CheckerTexture {
+    pub fn uv(even: Box<dyn Texture>, odd: Box<dyn Texture>, u_cells: f64, v_cells: f64) -> Self {
+        Self {
+            even,
+            odd,
+            space: CheckerSpace::Uv { u_cells, v_cells },
+        }
+    }
+
+    pub fn solid(even: Box<dyn Texture>, odd: Box<dyn Texture>, size: f64) -> Self {
+        Self {
+            even,
+            odd,
+            space: CheckerSpace::Solid { size },
+        }
+    }
+}
+
+impl Texture for CheckerTexture {
+    fn value(&self, u: f64, v: f64, position: Vec3) -> Vec3 {
+        //floor instead of truncation so the cells don't double up around 0
+        let cell = match self.space {
+            CheckerSpace::Uv { u_cells, v_cells } => (u * u_cells).floor() + (v * v_cells).floor(),
+            CheckerSpace::Solid { size } => {
+                (position.x() / size).floor() + (position.y() / size).floor() + (position.z() / size).floor()
+            }
+        };
+
+        if cell.rem_euclid(2_f64) < 1_f64 {
+            return self.even.value(u, v, position);
+        } else {
+            return self.odd.value(u, v, position);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Vec3;
+    use crate::render::*;
+
+    fn black_white() -> (Box<dyn Texture>, Box<dyn Texture>) {
+        return (
+            Box::new(ConstantTexture::new(Vec3::new(0.0, 0.0, 0.0))),
+            Box::new(ConstantTexture::new(Vec3::new(1.0, 1.0, 1.0))),
+        );
+    }
+
+    #[test]
+    fn test_uv_checker() {
+        let (even, odd) = black_white();
+        let checker = CheckerTexture::uv(even, odd, 4.0, 2.0);
+        let origin = Vec3::new(0.0, 0.0, 0.0);
+
+        assert_eq!(checker.value(0.1, 0.1, origin).r(), 0.0);
+        assert_eq!(checker.value(0.3, 0.1, origin).r(), 1.0);
+        assert_eq!(checker.value(0.3, 0.6, origin).r(), 0.0);
+        assert_eq!(checker.value(0.9, 0.9, origin).r(), 0.0);
+    }
+
+    #[test]
+    fn test_solid_checker_is_symmetric_around_zero() {
+        let (even, odd) = black_white();
+        let checker = CheckerTexture::solid(even, odd, 0.5);
+
+        assert_eq!(checker.value(0.0, 0.0, Vec3::new(0.25, 0.25, 0.25)).r(), 0.0);
+        assert_eq!(checker.value(0.0, 0.0, Vec3::new(-0.25, 0.25, 0.25)).r(), 1.0);
+        assert_eq!(checker.value(0.0, 0.0, Vec3::new(-0.25, -0.25, 0.25)).r(), 0.0);
+        assert_eq!(checker.value(0.0, 0.0, Vec3::new(0.75, 0.25, 0.25)).r(), 1.0);
+    }
+}
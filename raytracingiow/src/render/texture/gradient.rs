@@ -0,0 +1,63 @@
+use crate::math::Vec3;
+use crate::render::texture::texture::Texture;
+
+pub enum GradientAxis {
+    U,
+    V,
+    //along the line between two world space points
+    Between(Vec3, Vec3),
+}
+
+//linear blend from start to end, clamped beyond the ends
+pub struct GradientTexture {
+    start: Vec3,
+    end: Vec3,
+    axis: GradientAxis,
+}
+
+impl GradientTexture {
+    pub fn new(start: Vec3, end: Vec3, axis: GradientAxis) -> Self {
+        Self { start, end, axis }
+    }
+}
+
+impl Texture for GradientTexture {
+    fn value(&self, u: f64, v: f64, position: Vec3) -> Vec3 {
+        let t = match self.axis {
+            GradientAxis::U => u,
+            GradientAxis::V => v,
+            GradientAxis::Between(from, to) => {
+                let line = to - from;
+                let length_squared = line.length_squared();
+                if length_squared > 0_f64 {
+                    (position - from).dot(line) / length_squared
+                } else {
+                    0_f64
+                }
+            }
+        };
+
+        let t = t.clamp(0_f64, 1_f64);
+        return (1_f64 - t) * self.start + t * self.end;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Vec3;
+    use crate::render::*;
+
+    #[test]
+    fn test_gradient() {
+        let (black, white) = (Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 1.0));
+        let origin = Vec3::new(0.0, 0.0, 0.0);
+
+        let along_v = GradientTexture::new(black, white, GradientAxis::V);
+        assert_eq!(along_v.value(0.9, 0.25, origin), Vec3::new(0.25, 0.25, 0.25));
+
+        let between = GradientTexture::new(black, white, GradientAxis::Between(Vec3::new(0.0, -1.0, 0.0), Vec3::new(0.0, 1.0, 0.0)));
+        assert_eq!(between.value(0.0, 0.0, Vec3::new(5.0, 0.0, 3.0)), Vec3::new(0.5, 0.5, 0.5));
+        assert_eq!(between.value(0.0, 0.0, Vec3::new(0.0, 4.0, 0.0)), white);
+        assert_eq!(between.value(0.0, 0.0, Vec3::new(0.0, -4.0, 0.0)), black);
+    }
+}
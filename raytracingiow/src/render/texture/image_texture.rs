@@ -0,0 +1,328 @@
+use crate::math::Vec3;
+use crate::output::load_image;
+use crate::output::Image;
+use crate::output::ImageFormat;
+use crate::output::TransferFunction;
+use crate::render::texture::texture::Texture;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+
+//how texels are combined for a lookup between their centers
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TextureFilter {
+    Nearest,
+    Bilinear,
+    //catmull-rom over a 4x4 neighbourhood, sharper than bilinear
+    Bicubic,
+}
+
+//what a lookup outside [0, 1] sees
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AddressMode {
+    //tiles the image
+    Wrap,
+    //repeats the edge texels
+    Clamp,
+    //tiles with every other copy flipped, so there are no seams
+    Mirror,
+}
+
+//an image stretched over the surface coordinates, v = 0 is the bottom row
+pub struct ImageTexture {
+    image: Arc<Image>,
+    filter: TextureFilter,
+    address: AddressMode,
+}
+
+impl ImageTexture {
+    //pixels must already be linear, see ImageCache for loading files
+    pub fn new(image: Arc<Image>) -> Self {
+        Self {
+            image,
+            filter: TextureFilter::Bilinear,
+            address: AddressMode::Wrap,
+        }
+    }
+
+    pub fn with_filter(self, filter: TextureFilter) -> Self {
+        Self { filter, ..self }
+    }
+
+    pub fn with_address(self, address: AddressMode) -> Self {
+        Self { address, ..self }
+    }
+
+    fn texel(&self, x: i64, y: i64) -> Vec3 {
+        let x = address(x, self.image.width(), self.address);
+        let y = address(y, self.image.height(), self.address);
+        return self.image.pixel(x, y);
+    }
+}
+
+fn address(i: i64, size: usize, mode: AddressMode) -> usize {
+    let n = size as i64;
+    let i = match mode {
+        AddressMode::Wrap => i.rem_euclid(n),
+        AddressMode::Clamp => i.clamp(0, n - 1),
+        AddressMode::Mirror => {
+            let m = i.rem_euclid(2 * n);
+            if m < n {
+                m
+            } else {
+                2 * n - 1 - m
+            }
+        }
+    };
+    return i as usize;
+}
+
+fn catmull_rom_weights(t: f64) -> [f64; 4] {
+    let (t2, t3) = (t * t, t * t * t);
+    return [
+        0.5 * (-t3 + 2_f64 * t2 - t),
+        0.5 * (3_f64 * t3 - 5_f64 * t2 + 2_f64),
+        0.5 * (-3_f64 * t3 + 4_f64 * t2 + t),
+        0.5 * (t3 - t2),
+    ];
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _position: Vec3) -> Vec3 {
+        if self.image.width() == 0 || self.image.height() == 0 {
+            return Vec3::new(0.0, 0.0, 0.0);
+        }
+
+        //continuous texel coordinates, texel centers sit at half integers
+        let x = u * self.image.width() as f64;
+        let y = (1_f64 - v) * self.image.height() as f64;
+
+        match self.filter {
+            TextureFilter::Nearest => {
+                return self.texel(x.floor() as i64, y.floor() as i64);
+            }
+            TextureFilter::Bilinear => {
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (x0, y0) = (x.floor(), y.floor());
+                let (fx, fy) = (x - x0, y - y0);
+                let (x0, y0) = (x0 as i64, y0 as i64);
+
+                let top = (1_f64 - fx) * self.texel(x0, y0) + fx * self.texel(x0 + 1, y0);
+                let bottom = (1_f64 - fx) * self.texel(x0, y0 + 1) + fx * self.texel(x0 + 1, y0 + 1);
+                return (1_f64 - fy) * top + fy * bottom;
+            }
+            TextureFilter::Bicubic => {
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (x0, y0) = (x.floor(), y.floor());
+                let (wx, wy) = (catmull_rom_weights(x - x0), catmull_rom_weights(y - y0));
+                let (x0, y0) = (x0 as i64, y0 as i64);
+
+                let mut color = Vec3::new(0.0, 0.0, 0.0);
+                for (j, wy) in wy.iter().enumerate() {
+                    for (i, wx) in wx.iter().enumerate() {
+                        color += (wx * wy) * self.texel(x0 + i as i64 - 1, y0 + j as i64 - 1);
+                    }
+                }
+                //the negative lobes can overshoot below zero next to sharp edges
+                return Vec3::new(color.r().max(0_f64), color.g().max(0_f64), color.b().max(0_f64));
+            }
+        }
+    }
+}
+
+//loads every file once, however many textures use it
+pub struct ImageCache {
+    images: HashMap<(PathBuf, TransferFunction), Arc<Image>>,
+}
+
+impl ImageCache {
+    pub fn new() -> Self {
+        Self { images: HashMap::new() }
+    }
+
+    //encoding is how the file stores its values, srgb for color maps and linear for data
+    //such as roughness or normals. hdr files are always linear
+    pub fn load(&mut self, path: impl AsRef<Path>, encoding: TransferFunction) -> io::Result<Arc<Image>> {
+        let path = path.as_ref();
+        let encoding = if ImageFormat::from_path(path) == Some(ImageFormat::Hdr) {
+            TransferFunction::Linear
+        } else {
+            encoding
+        };
+
+        let key = (fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()), encoding);
+        if let Some(image) = self.images.get(&key) {
+            return Ok(image.clone());
+        }
+
+        let mut image = load_image(path)?;
+        decode_pixels(&mut image, encoding);
+
+        let image = Arc::new(image);
+        self.images.insert(key, image.clone());
+        return Ok(image);
+    }
+
+    //number of distinct images loaded
+    pub fn len(&self) -> usize {
+        return self.images.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.images.is_empty();
+    }
+}
+
+//turns the stored values of an image into linear ones
+pub fn decode_pixels(image: &mut Image, encoding: TransferFunction) {
+    if encoding == TransferFunction::Linear {
+        return;
+    }
+    for p in image.pixels_mut() {
+        *p = Vec3::new(encoding.decode(p.r()), encoding.decode(p.g()), encoding.decode(p.b()));
+    }
+}
+
+impl Default for ImageCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FromStr for TextureFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        return match s {
+            "nearest" => Ok(TextureFilter::Nearest),
+            "bilinear" => Ok(TextureFilter::Bilinear),
+            "bicubic" => Ok(TextureFilter::Bicubic),
+            _ => Err(format!("unknown texture filter '{}', expected one of: nearest, bilinear, bicubic", s)),
+        };
+    }
+}
+
+impl fmt::Display for TextureFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            TextureFilter::Nearest => "nearest",
+            TextureFilter::Bilinear => "bilinear",
+            TextureFilter::Bicubic => "bicubic",
+        };
+        return write!(f, "{}", name);
+    }
+}
+
+impl FromStr for AddressMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        return match s {
+            "wrap" | "repeat" => Ok(AddressMode::Wrap),
+            "clamp" => Ok(AddressMode::Clamp),
+            "mirror" => Ok(AddressMode::Mirror),
+            _ => Err(format!("unknown address mode '{}', expected one of: wrap, clamp, mirror", s)),
+        };
+    }
+}
+
+impl fmt::Display for AddressMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            AddressMode::Wrap => "wrap",
+            AddressMode::Clamp => "clamp",
+            AddressMode::Mirror => "mirror",
+        };
+        return write!(f, "{}", name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Vec3;
+    use crate::output::*;
+    use crate::render::*;
+
+    use std::sync::Arc;
+
+    const EPS: f64 = 1e-9;
+
+    fn grey(v: f64) -> Vec3 {
+        return Vec3::new(v, v, v);
+    }
+
+    //a 4x1 ramp 0, 1, 2, 3
+    fn ramp() -> Arc<Image> {
+        return Arc::new(Image::from_pixels(4, 1, (0..4).map(|i| grey(i as f64)).collect()));
+    }
+
+    fn at(texture: &ImageTexture, u: f64) -> f64 {
+        return texture.value(u, 0.5, Vec3::new(0.0, 0.0, 0.0)).r();
+    }
+
+    #[test]
+    fn test_filters() {
+        let nearest = ImageTexture::new(ramp()).with_filter(TextureFilter::Nearest);
+        assert_eq!(at(&nearest, 0.3), 1.0);
+
+        //texel centers are exact for every filter, halfway between them bilinear averages
+        let bilinear = ImageTexture::new(ramp()).with_address(AddressMode::Clamp);
+        let bicubic = ImageTexture::new(ramp()).with_filter(TextureFilter::Bicubic).with_address(AddressMode::Clamp);
+        for i in 0..4 {
+            let center = (i as f64 + 0.5) / 4.0;
+            assert!((at(&bilinear, center) - i as f64).abs() < EPS);
+            assert!((at(&bicubic, center) - i as f64).abs() < EPS);
+        }
+        assert!((at(&bilinear, 0.5) - 1.5).abs() < EPS);
+        //catmull-rom reproduces a linear ramp away from the clamped edges
+        assert!((at(&bicubic, 0.5) - 1.5).abs() < EPS);
+    }
+
+    #[test]
+    fn test_address_modes() {
+        let texture = |address: AddressMode| -> ImageTexture {
+            return ImageTexture::new(ramp()).with_filter(TextureFilter::Nearest).with_address(address);
+        };
+
+        assert_eq!(at(&texture(AddressMode::Wrap), 1.1), 0.0);
+        assert_eq!(at(&texture(AddressMode::Wrap), -0.1), 3.0);
+        assert_eq!(at(&texture(AddressMode::Clamp), 1.1), 3.0);
+        assert_eq!(at(&texture(AddressMode::Clamp), -5.0), 0.0);
+        assert_eq!(at(&texture(AddressMode::Mirror), 1.1), 3.0);
+        assert_eq!(at(&texture(AddressMode::Mirror), 1.9), 0.0);
+        assert_eq!(at(&texture(AddressMode::Mirror), -0.1), 0.0);
+
+        //wrapped bilinear blends the last texel with the first across the seam
+        let wrapped = ImageTexture::new(ramp());
+        assert!((at(&wrapped, 1.0) - 1.5).abs() < EPS);
+    }
+
+    #[test]
+    fn test_cache_loads_once_and_decodes_srgb() {
+        let path = std::env::temp_dir().join(format!("raytracing_iow_texture_{}.png", std::process::id()));
+        let image = Image::from_pixels(1, 1, vec![grey(0.5)]);
+        let linear = ToneMap {
+            transfer: TransferFunction::Linear,
+            ..ToneMap::default()
+        };
+        save_image(&path, &image, Some(ImageFormat::Png16), linear).unwrap();
+
+        let mut cache = ImageCache::new();
+        let srgb = cache.load(&path, TransferFunction::Srgb).unwrap();
+        let again = cache.load(&path, TransferFunction::Srgb).unwrap();
+        let raw = cache.load(&path, TransferFunction::Linear).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(Arc::ptr_eq(&srgb, &again));
+        assert_eq!(cache.len(), 2);
+        assert!((raw.pixel(0, 0).r() - 0.5).abs() < 1e-4);
+        assert!((srgb.pixel(0, 0).r() - 0.2140).abs() < 1e-3);
+        assert!(cache.load("missing.png", TransferFunction::Srgb).is_err());
+    }
+}
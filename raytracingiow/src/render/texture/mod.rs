@@ -0,0 +1,16 @@
+mod checker;
+mod gradient;
+mod image_texture;
+mod perlin;
+mod procedural;
+#[allow(clippy::module_inception)]
+mod texture;
+mod worley;
+
+pub use self::checker::*;
+pub use self::gradient::*;
+pub use self::image_texture::*;
+pub use self::perlin::*;
+pub use self::procedural::*;
+pub use self::texture::*;
+pub use self::worley::*;
@@ -0,0 +1,148 @@
+use crate::math::Vec3;
+use crate::render::material::random_unit_vector;
+
+use rand::rngs::StdRng;
+use rand::Rng;
+use rand::SeedableRng;
+
+const POINT_COUNT: usize = 256;
+
+//gradient noise from ken perlin, the same seed always gives the same field
+pub struct Perlin {
+    gradients: Vec<Vec3>,
+    perm_x: Vec<usize>,
+    perm_y: Vec<usize>,
+    perm_z: Vec<usize>,
+}
+
+fn permutation(rng: &mut StdRng) -> Vec<usize> {
+    let mut p: Vec<usize> = (0..POINT_COUNT).collect();
+    for i in (1..POINT_COUNT).rev() {
+        let target = rng.gen_range(0, i + 1);
+        p.swap(i, target);
+    }
+    return p;
+}
+
+impl Perlin {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let gradients = (0..POINT_COUNT).map(|_| random_unit_vector(&mut rng)).collect();
+        let perm_x = permutation(&mut rng);
+        let perm_y = permutation(&mut rng);
+        let perm_z = permutation(&mut rng);
+
+        return Self {
+            gradients,
+            perm_x,
+            perm_y,
+            perm_z,
+        };
+    }
+
+    fn gradient(&self, i: i64, j: i64, k: i64) -> Vec3 {
+        let mask = POINT_COUNT as i64 - 1;
+        let index = self.perm_x[(i & mask) as usize] ^ self.perm_y[(j & mask) as usize] ^ self.perm_z[(k & mask) as usize];
+        return self.gradients[index];
+    }
+
+    //smooth noise in roughly [-1, 1], zero at every integer lattice point
+    pub fn noise(&self, p: Vec3) -> f64 {
+        let (fx, fy, fz) = (p.x().floor(), p.y().floor(), p.z().floor());
+        let (u, v, w) = (p.x() - fx, p.y() - fy, p.z() - fz);
+        let (i, j, k) = (fx as i64, fy as i64, fz as i64);
+
+        //hermite smoothing hides the lattice
+        let (uu, vv, ww) = (u * u * (3.0 - 2.0 * u), v * v * (3.0 - 2.0 * v), w * w * (3.0 - 2.0 * w));
+
+        let mut accum = 0_f64;
+        for di in 0..2 {
+            for dj in 0..2 {
+                for dk in 0..2 {
+                    let (a, b, c) = (di as f64, dj as f64, dk as f64);
+                    let weight = Vec3::new(u - a, v - b, w - c);
+                    accum += (a * uu + (1.0 - a) * (1.0 - uu))
+                        * (b * vv + (1.0 - b) * (1.0 - vv))
+                        * (c * ww + (1.0 - c) * (1.0 - ww))
+                        * self.gradient(i + di, j + dj, k + dk).dot(weight);
+                }
+            }
+        }
+
+        return accum;
+    }
+
+    //sum of the absolute value of octaves at doubling frequency and halving amplitude, in [0, 2)
+    pub fn turbulence(&self, p: Vec3, octaves: u32) -> f64 {
+        let mut accum = 0_f64;
+        let mut point = p;
+        let mut weight = 1_f64;
+
+        for _ in 0..octaves {
+            accum += weight * self.noise(point).abs();
+            weight *= 0.5;
+            point *= 2.0;
+        }
+
+        return accum;
+    }
+
+    //fractal brownian motion, signed octaves scaled by lacunarity in frequency and gain in amplitude,
+    //normalized back into roughly [-1, 1]
+    pub fn fbm(&self, p: Vec3, octaves: u32, lacunarity: f64, gain: f64) -> f64 {
+        let mut accum = 0_f64;
+        let mut total_weight = 0_f64;
+        let mut point = p;
+        let mut weight = 1_f64;
+
+        for _ in 0..octaves {
+            accum += weight * self.noise(point);
+            total_weight += weight;
+            weight *= gain;
+            point *= lacunarity;
+        }
+
+        if total_weight <= 0_f64 {
+            return 0_f64;
+        }
+        return accum / total_weight;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Vec3;
+    use crate::render::*;
+
+    use rand::Rng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_seeded() {
+        let p = Vec3::new(1.3, -2.7, 0.45);
+        assert_eq!(Perlin::new(4).noise(p), Perlin::new(4).noise(p));
+        assert_ne!(Perlin::new(4).noise(p), Perlin::new(5).noise(p));
+    }
+
+    #[test]
+    fn test_range_and_continuity() {
+        let perlin = Perlin::new(1);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(2);
+        let mut spread = (0_f64, 0_f64);
+
+        for _ in 0..10000 {
+            let p = Vec3::new(rng.gen_range(-50.0, 50.0), rng.gen_range(-50.0, 50.0), rng.gen_range(-50.0, 50.0));
+            let n = perlin.noise(p);
+            assert!(n.abs() <= 1.0 + 1e-9, "{}", n);
+            assert!((perlin.noise(p + Vec3::new(1e-6, 0.0, 0.0)) - n).abs() < 1e-4);
+            spread = (spread.0.min(n), spread.1.max(n));
+
+            let t = perlin.turbulence(p, 7);
+            assert!((0.0..2.0).contains(&t));
+            assert!(perlin.fbm(p, 5, 2.0, 0.5).abs() <= 1.0 + 1e-9);
+        }
+        //not a flat field
+        assert!(spread.0 < -0.3 && spread.1 > 0.3, "{:?}", spread);
+        assert_eq!(perlin.noise(Vec3::new(3.0, -7.0, 12.0)), 0.0);
+    }
+}
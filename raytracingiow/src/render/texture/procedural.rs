@@ -0,0 +1,245 @@
+use crate::math::Vec3;
+use crate::render::texture::perlin::Perlin;
+use crate::render::texture::texture::ConstantTexture;
+use crate::render::texture::texture::Texture;
+use crate::render::texture::worley::Worley;
+
+//what a NoiseTexture evaluates at the scaled hit position
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum NoisePattern {
+    Perlin,
+    Turbulence { octaves: u32 },
+    Fbm { octaves: u32, lacunarity: f64, gain: f64 },
+    //distance to the nearest feature point, dark cell centers
+    Worley,
+    //second minus first distance, dark lines along the cell borders like cracks or scales
+    WorleyEdges,
+}
+
+//blends between two textures by a noise value in [0, 1]
+pub struct NoiseTexture {
+    pattern: NoisePattern,
+    perlin: Perlin,
+    worley: Worley,
+    //noise features per world unit
+    scale: f64,
+    low: Box<dyn Texture>,
+    high: Box<dyn Texture>,
+}
+
+fn black() -> Box<dyn Texture> {
+    return Box::new(ConstantTexture::new(Vec3::new(0.0, 0.0, 0.0)));
+}
+
+fn white() -> Box<dyn Texture> {
+    return Box::new(ConstantTexture::new(Vec3::new(1.0, 1.0, 1.0)));
+}
+
+fn blend(low: &dyn Texture, high: &dyn Texture, t: f64, u: f64, v: f64, position: Vec3) -> Vec3 {
+    let t = t.clamp(0_f64, 1_f64);
+    return (1_f64 - t) * low.value(u, v, position) + t * high.value(u, v, position);
+}
+
+impl NoiseTexture {
+    //black to white at one feature per unit
+    pub fn new(pattern: NoisePattern, seed: u64) -> Self {
+        Self {
+            pattern,
+            perlin: Perlin::new(seed),
+            worley: Worley::new(seed),
+            scale: 1.0,
+            low: black(),
+            high: white(),
+        }
+    }
+
+    pub fn with_scale(self, scale: f64) -> Self {
+        Self { scale, ..self }
+    }
+
+    //color where the pattern is 0
+    pub fn with_low(self, low: Box<dyn Texture>) -> Self {
+        Self { low, ..self }
+    }
+
+    //color where the pattern is 1
+    pub fn with_high(self, high: Box<dyn Texture>) -> Self {
+        Self { high, ..self }
+    }
+
+    //the pattern remapped into [0, 1]
+    pub fn amount(&self, position: Vec3) -> f64 {
+        let p = self.scale * position;
+        return match self.pattern {
+            NoisePattern::Perlin => 0.5 * (1_f64 + self.perlin.noise(p)),
+            NoisePattern::Turbulence { octaves } => self.perlin.turbulence(p, octaves),
+            NoisePattern::Fbm { octaves, lacunarity, gain } => 0.5 * (1_f64 + self.perlin.fbm(p, octaves, lacunarity, gain)),
+            NoisePattern::Worley => self.worley.distances(p).0,
+            NoisePattern::WorleyEdges => {
+                let (f1, f2) = self.worley.distances(p);
+                f2 - f1
+            }
+        };
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, u: f64, v: f64, position: Vec3) -> Vec3 {
+        return blend(&*self.low, &*self.high, self.amount(position), u, v, position);
+    }
+}
+
+//veins running across z, bent by turbulence
+pub struct MarbleTexture {
+    perlin: Perlin,
+    scale: f64,
+    //how far the veins get pushed around
+    turbulence: f64,
+    octaves: u32,
+    vein: Box<dyn Texture>,
+    base: Box<dyn Texture>,
+}
+
+impl MarbleTexture {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            perlin: Perlin::new(seed),
+            scale: 4.0,
+            turbulence: 10.0,
+            octaves: 7,
+            vein: black(),
+            base: white(),
+        }
+    }
+
+    pub fn with_scale(self, scale: f64) -> Self {
+        Self { scale, ..self }
+    }
+
+    pub fn with_turbulence(self, turbulence: f64) -> Self {
+        Self { turbulence, ..self }
+    }
+
+    pub fn with_octaves(self, octaves: u32) -> Self {
+        Self { octaves, ..self }
+    }
+
+    pub fn with_vein(self, vein: Box<dyn Texture>) -> Self {
+        Self { vein, ..self }
+    }
+
+    pub fn with_base(self, base: Box<dyn Texture>) -> Self {
+        Self { base, ..self }
+    }
+}
+
+impl Texture for MarbleTexture {
+    fn value(&self, u: f64, v: f64, position: Vec3) -> Vec3 {
+        let p = self.scale * position;
+        let phase = p.z() + self.turbulence * self.perlin.turbulence(p, self.octaves);
+        let t = 0.5 * (1_f64 + phase.sin());
+        return blend(&*self.vein, &*self.base, t, u, v, position);
+    }
+}
+
+//growth rings around the y axis, made irregular with fbm
+pub struct WoodTexture {
+    perlin: Perlin,
+    //rings per world unit
+    ring_frequency: f64,
+    //how much the noise warps the rings, in rings
+    distortion: f64,
+    octaves: u32,
+    light: Box<dyn Texture>,
+    dark: Box<dyn Texture>,
+}
+
+impl WoodTexture {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            perlin: Perlin::new(seed),
+            ring_frequency: 8.0,
+            distortion: 1.0,
+            octaves: 4,
+            light: Box::new(ConstantTexture::new(Vec3::new(0.75, 0.55, 0.33))),
+            dark: Box::new(ConstantTexture::new(Vec3::new(0.45, 0.27, 0.12))),
+        }
+    }
+
+    pub fn with_ring_frequency(self, ring_frequency: f64) -> Self {
+        Self { ring_frequency, ..self }
+    }
+
+    pub fn with_distortion(self, distortion: f64) -> Self {
+        Self { distortion, ..self }
+    }
+
+    pub fn with_octaves(self, octaves: u32) -> Self {
+        Self { octaves, ..self }
+    }
+
+    pub fn with_light(self, light: Box<dyn Texture>) -> Self {
+        Self { light, ..self }
+    }
+
+    pub fn with_dark(self, dark: Box<dyn Texture>) -> Self {
+        Self { dark, ..self }
+    }
+}
+
+impl Texture for WoodTexture {
+    fn value(&self, u: f64, v: f64, position: Vec3) -> Vec3 {
+        let radius = (position.x() * position.x() + position.z() * position.z()).sqrt();
+        //the grain is stretched along the trunk
+        let warp = self.perlin.fbm(Vec3::new(position.x(), 0.1 * position.y(), position.z()) * self.ring_frequency, self.octaves, 2.0, 0.5);
+        let rings = radius * self.ring_frequency + self.distortion * warp;
+
+        //sharp dark late wood at the end of every ring
+        let t = rings - rings.floor();
+        return blend(&*self.light, &*self.dark, t * t * t, u, v, position);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Vec3;
+    use crate::render::*;
+
+    use rand::Rng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_textures_are_seeded_and_bounded() {
+        let patterns = [
+            NoisePattern::Perlin,
+            NoisePattern::Turbulence { octaves: 7 },
+            NoisePattern::Fbm { octaves: 5, lacunarity: 2.0, gain: 0.5 },
+            NoisePattern::Worley,
+            NoisePattern::WorleyEdges,
+        ];
+        let mut rng = rand::rngs::StdRng::seed_from_u64(6);
+
+        let mut textures: Vec<(Box<dyn Texture>, Box<dyn Texture>)> = patterns
+            .iter()
+            .map(|pattern| -> (Box<dyn Texture>, Box<dyn Texture>) {
+                (Box::new(NoiseTexture::new(*pattern, 11).with_scale(3.0)), Box::new(NoiseTexture::new(*pattern, 11).with_scale(3.0)))
+            })
+            .collect();
+        textures.push((Box::new(MarbleTexture::new(11)), Box::new(MarbleTexture::new(11))));
+        textures.push((Box::new(WoodTexture::new(11)), Box::new(WoodTexture::new(11))));
+
+        for (a, b) in &textures {
+            for _ in 0..500 {
+                let p = Vec3::new(rng.gen_range(-5.0, 5.0), rng.gen_range(-5.0, 5.0), rng.gen_range(-5.0, 5.0));
+                let value = a.value(0.0, 0.0, p);
+                assert_eq!(value, b.value(0.0, 0.0, p));
+                for c in 0..3 {
+                    assert!((0.0..=1.0).contains(&value[c]), "{:?}", value);
+                }
+            }
+        }
+
+        let p = Vec3::new(0.3, 0.7, 0.1);
+        assert_ne!(MarbleTexture::new(1).value(0.0, 0.0, p), MarbleTexture::new(2).value(0.0, 0.0, p));
+    }
+}
@@ -0,0 +1,88 @@
+use crate::math::Vec3;
+
+use std::sync::Arc;
+
+//a material input that varies over a surface
+pub trait Texture: Send + Sync {
+    //u and v in [0, 1] are the surface coordinates of the hit, position is in world space
+    fn value(&self, u: f64, v: f64, position: Vec3) -> Vec3;
+
+    //scalar inputs such as a roughness read the first channel
+    fn scalar(&self, u: f64, v: f64, position: Vec3) -> f64 {
+        return self.value(u, v, position).r();
+    }
+}
+
+//the same value everywhere, what a plain color or number becomes
+pub struct ConstantTexture {
+    value: Vec3,
+}
+
+impl ConstantTexture {
+    pub fn new(value: Vec3) -> Self {
+        Self { value }
+    }
+
+    pub fn scalar(value: f64) -> Self {
+        Self {
+            value: Vec3::new(value, value, value),
+        }
+    }
+}
+
+impl Texture for ConstantTexture {
+    fn value(&self, _u: f64, _v: f64, _position: Vec3) -> Vec3 {
+        return self.value;
+    }
+}
+
+//another texture multiplied by a constant color, e.g. an image tinted by a factor
+pub struct ScaledTexture {
+    texture: Box<dyn Texture>,
+    factor: Vec3,
+}
+
+impl ScaledTexture {
+    pub fn new(texture: Box<dyn Texture>, factor: Vec3) -> Self {
+        Self { texture, factor }
+    }
+}
+
+impl Texture for ScaledTexture {
+    fn value(&self, u: f64, v: f64, position: Vec3) -> Vec3 {
+        return self.factor * self.texture.value(u, v, position);
+    }
+}
+
+//one channel of another texture as grey, for maps that pack several values into one image
+pub struct ChannelTexture {
+    texture: Box<dyn Texture>,
+    channel: i32,
+}
+
+impl ChannelTexture {
+    //channel 0 is red, 1 green and 2 blue
+    pub fn new(texture: Box<dyn Texture>, channel: i32) -> Self {
+        Self { texture, channel }
+    }
+}
+
+impl Texture for ChannelTexture {
+    fn value(&self, u: f64, v: f64, position: Vec3) -> Vec3 {
+        let value = self.texture.value(u, v, position)[self.channel];
+        return Vec3::new(value, value, value);
+    }
+}
+
+impl<T: Texture + ?Sized> Texture for Box<T> {
+    fn value(&self, u: f64, v: f64, position: Vec3) -> Vec3 {
+        return (**self).value(u, v, position);
+    }
+}
+
+//one texture feeding several inputs, such as both roughnesses of a conductor
+impl<T: Texture + ?Sized> Texture for Arc<T> {
+    fn value(&self, u: f64, v: f64, position: Vec3) -> Vec3 {
+        return (**self).value(u, v, position);
+    }
+}
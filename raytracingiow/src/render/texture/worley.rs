@@ -0,0 +1,79 @@
+use crate::math::Vec3;
+
+//cellular noise from steven worley, one feature point scattered in every unit cell
+pub struct Worley {
+    seed: u64,
+}
+
+//splitmix64 finalizer, decorrelates nearby cell indices
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    return x ^ (x >> 31);
+}
+
+fn unit(bits: u64) -> f64 {
+    //top 53 bits as a float in [0, 1)
+    return (bits >> 11) as f64 / (1_u64 << 53) as f64;
+}
+
+impl Worley {
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    fn feature_point(&self, i: i64, j: i64, k: i64) -> Vec3 {
+        let h = mix(self.seed ^ mix(i as u64 ^ mix(j as u64 ^ mix(k as u64))));
+        let (a, b, c) = (mix(h), mix(h ^ 1), mix(h ^ 2));
+        return Vec3::new(i as f64 + unit(a), j as f64 + unit(b), k as f64 + unit(c));
+    }
+
+    //distances to the nearest and second nearest feature points
+    pub fn distances(&self, p: Vec3) -> (f64, f64) {
+        let (i, j, k) = (p.x().floor() as i64, p.y().floor() as i64, p.z().floor() as i64);
+        let (mut f1, mut f2) = (f64::MAX, f64::MAX);
+
+        //with one point per cell the two nearest are always within the 3x3x3 neighbourhood
+        for di in -1..=1 {
+            for dj in -1..=1 {
+                for dk in -1..=1 {
+                    let d = (self.feature_point(i + di, j + dj, k + dk) - p).length();
+                    if d < f1 {
+                        f2 = f1;
+                        f1 = d;
+                    } else if d < f2 {
+                        f2 = d;
+                    }
+                }
+            }
+        }
+
+        return (f1, f2);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Vec3;
+    use crate::render::*;
+
+    use rand::Rng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_distances() {
+        let worley = Worley::new(3);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(4);
+
+        for _ in 0..2000 {
+            let p = Vec3::new(rng.gen_range(-20.0, 20.0), rng.gen_range(-20.0, 20.0), rng.gen_range(-20.0, 20.0));
+            let (f1, f2) = worley.distances(p);
+            assert!(f1 <= f2);
+            //the point of the cell containing p is at most a cell diagonal away
+            assert!(f1 <= 3_f64.sqrt());
+            assert_eq!(worley.distances(p), Worley::new(3).distances(p));
+        }
+        let p = Vec3::new(0.5, 0.5, 0.5);
+        assert_ne!(worley.distances(p), Worley::new(8).distances(p));
+    }
+}
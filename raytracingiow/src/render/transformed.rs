@@ -0,0 +1,232 @@
+use crate::math::Aabb;
+use crate::math::Mat4;
+use crate::math::Ray;
+use crate::math::Vec3;
+use crate::render::light::Light;
+use crate::render::light::LightList;
+use crate::render::light::LightSample;
+use crate::render::renderable::HitRecord;
+use crate::render::renderable::Renderable;
+
+use rand::RngCore;
+use std::sync::Arc;
+
+//an object placed in the world by an affine transform. the object is shared, so one heavy mesh
+//can be instanced many times for the memory of a single copy
+pub struct Transformed {
+    object: Arc<dyn Renderable>,
+    to_world: Mat4,
+    to_object: Mat4,
+    //inverse transpose of to_world, keeps normals perpendicular under non uniform scaling
+    normal_matrix: Mat4,
+    bbox: Option<Aabb>,
+    //collected once, so instances of objects without lights never rebuild the list when sampled
+    has_lights: bool,
+}
+
+impl Transformed {
+    pub fn new(object: Arc<dyn Renderable>, transform: Mat4) -> Result<Self, String> {
+        if !transform.is_finite() {
+            return Err("transform is not finite".to_string());
+        }
+        let to_object = match transform.inverse() {
+            Some(inverse) if transform.determinant3() != 0_f64 => inverse,
+            _ => return Err("transform is singular".to_string()),
+        };
+        let bbox = object.bounding_box().map(|b| transform_box(&b, &transform));
+        let has_lights = !LightList::new(&*object).is_empty();
+
+        return Ok(Self {
+            object,
+            to_world: transform,
+            to_object,
+            normal_matrix: to_object.transpose(),
+            bbox,
+            has_lights,
+        });
+    }
+}
+
+//box around the eight transformed corners
+pub fn transform_box(bbox: &Aabb, transform: &Mat4) -> Aabb {
+    let (min, max) = (bbox.min(), bbox.max());
+    let mut result: Option<Aabb> = None;
+    for corner in 0..8 {
+        let p = Vec3::new(
+            if corner & 1 == 0 { min.x() } else { max.x() },
+            if corner & 2 == 0 { min.y() } else { max.y() },
+            if corner & 4 == 0 { min.z() } else { max.z() },
+        );
+        let p = transform.transform_point(p);
+        let point = Aabb::new(p, p);
+        result = Some(result.map_or(point, |r| r.surrounding(&point)));
+    }
+    return result.unwrap();
+}
+
+impl Transformed {
+    //the object space direction is not normalized, so t is the same in both spaces
+    fn to_local(&self, ray: &Ray) -> Ray {
+        return Ray::new(self.to_object.transform_point(ray.origin()), self.to_object.transform_vector(ray.direction())).with_time(ray.time());
+    }
+}
+
+impl Renderable for Transformed {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let hit = self.object.hit(&self.to_local(ray), t_min, t_max)?;
+
+        return Some(HitRecord {
+            position: ray.point_at_distance(hit.t),
+            normal: self.normal_matrix.transform_vector(hit.normal).unit_vector(),
+            ..hit
+        });
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        return self.bbox;
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f64, t_max: f64) -> f64 {
+        return self.object.transmittance(&self.to_local(ray), t_min, t_max);
+    }
+
+    //the emissive parts of the object are sampled together as one light
+    fn collect_lights<'a>(&'a self, lights: &mut Vec<&'a dyn Light>) {
+        if self.has_lights {
+            lights.push(self);
+        }
+    }
+}
+
+//directions are sampled in object space. a linear map A turns the unit direction d into A d / |A d|,
+//which stretches solid angle by |det A| / |A d|^3, so the pdf is divided by that. the lights of the
+//object are gathered through the shared Arc on every call, the list can't outlive the borrow of it
+impl Light for Transformed {
+    fn sample(&self, origin: Vec3, rng: &mut dyn RngCore) -> Option<LightSample> {
+        let sample = LightList::new(&*self.object).sample(self.to_object.transform_point(origin), rng)?;
+        let direction = self.to_world.transform_vector(sample.direction);
+        let stretch = direction.length();
+
+        return Some(LightSample {
+            direction: direction / stretch,
+            distance: sample.distance * stretch,
+            pdf: sample.pdf * stretch.powi(3) / self.to_world.determinant3().abs(),
+        });
+    }
+
+    fn pdf(&self, origin: Vec3, direction: Vec3) -> f64 {
+        let local = self.to_object.transform_vector(direction);
+        let shrink = local.length();
+        let pdf = LightList::new(&*self.object).pdf(self.to_object.transform_point(origin), local / shrink);
+        return pdf / (shrink.powi(3) * self.to_world.determinant3().abs());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Mat4;
+    use crate::math::Ray;
+    use crate::math::Vec3;
+    use crate::render::*;
+
+    use rand::SeedableRng;
+    use std::f64::consts::PI;
+    use std::sync::Arc;
+
+    const EPS: f64 = 1e-9;
+
+    fn unit_sphere(material: Box<dyn Material>) -> Arc<dyn Renderable> {
+        return Arc::new(Sphere::new(Vec3::new(0.0, 0.0, 0.0), 1.0, material));
+    }
+
+    #[test]
+    fn test_scaled_sphere() {
+        //an ellipsoid 4 wide, 1 tall and 2 deep around (0 0 -5)
+        let transform = Mat4::translation(Vec3::new(0.0, 0.0, -5.0)) * Mat4::scaling(Vec3::new(2.0, 0.5, 1.0));
+        let ellipsoid = Transformed::new(unit_sphere(Box::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)))), transform).unwrap();
+
+        let hit = ellipsoid.hit(&Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0)), 0.0, f64::MAX).unwrap();
+        assert!((hit.t - 4.0).abs() < EPS);
+        assert!((hit.position - Vec3::new(0.0, 0.0, -4.0)).length() < EPS);
+        assert!(ellipsoid.hit(&Ray::new(Vec3::new(1.9, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0)), 0.0, f64::MAX).is_some());
+        assert!(ellipsoid.hit(&Ray::new(Vec3::new(0.0, 0.6, 0.0), Vec3::new(0.0, 0.0, -1.0)), 0.0, f64::MAX).is_none());
+
+        //on the slanted side the normal of the surface, not the scaled sphere normal
+        let p = Vec3::new(2.0 * 0.6, 0.5 * 0.8, -5.0);
+        let hit = ellipsoid.hit(&Ray::new(p + Vec3::new(0.0, 0.0, 3.0), Vec3::new(0.0, 0.0, -1.0)), 0.0, f64::MAX).unwrap();
+        let expected = Vec3::new(0.6 / 2.0, 0.8 / 0.5, 0.0).unit_vector();
+        assert!((hit.normal - expected).length() < 1e-6);
+
+        let bbox = ellipsoid.bounding_box().unwrap();
+        assert!((bbox.min() - Vec3::new(-2.0, -0.5, -6.0)).length() < EPS);
+        assert!((bbox.max() - Vec3::new(2.0, 0.5, -4.0)).length() < EPS);
+
+        let sphere = unit_sphere(Box::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5))));
+        assert!(Transformed::new(sphere, Mat4::scaling(Vec3::new(1.0, f64::NAN, 1.0))).is_err());
+    }
+
+    #[test]
+    fn test_shared_instances() {
+        let sphere = unit_sphere(Box::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5))));
+        let instances: Vec<Transformed> = (0..3)
+            .map(|i| Transformed::new(sphere.clone(), Mat4::translation(Vec3::new(3.0 * i as f64, 0.0, -5.0))).unwrap())
+            .collect();
+        assert_eq!(Arc::strong_count(&sphere), 4);
+
+        let world = Bvh::new(instances);
+        let hit = world.hit(&Ray::new(Vec3::new(6.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0)), 0.0, f64::MAX).unwrap();
+        assert!((hit.t - 4.0).abs() < EPS);
+        assert!(world.hit(&Ray::new(Vec3::new(1.5, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0)), 0.0, f64::MAX).is_none());
+
+        //a rotation turns the normal with the object, a quad facing +z ends up facing +x
+        let quad: Arc<dyn Renderable> = Arc::new(XYRect::new(-1.0, 1.0, -1.0, 1.0, 0.0, Box::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)))));
+        let rotated = Transformed::new(quad, Mat4::rotation(Vec3::new(0.0, 1.0, 0.0), 90.0)).unwrap();
+        let hit = rotated.hit(&Ray::new(Vec3::new(5.0, 0.5, 0.5), Vec3::new(-1.0, 0.0, 0.0)), 0.0, f64::MAX).unwrap();
+        assert!((hit.t - 5.0).abs() < EPS);
+        assert!((hit.normal - Vec3::new(1.0, 0.0, 0.0)).length() < EPS);
+
+        let message = Transformed::new(unit_sphere(Box::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)))), Mat4::scaling(Vec3::new(1.0, 0.0, 1.0)))
+            .err()
+            .unwrap();
+        assert!(message.contains("singular"), "{}", message);
+    }
+
+    #[test]
+    fn test_light_pdf_covers_every_direction() {
+        //from inside a stretched and turned light every direction hits it, so 1 / pdf averages to 4 pi
+        let transform = Mat4::rotation(Vec3::new(1.0, 1.0, 0.0), 30.0) * Mat4::scaling(Vec3::new(3.0, 1.0, 0.5));
+        let light = Transformed::new(unit_sphere(Box::new(DiffuseLight::new(Vec3::new(1.0, 1.0, 1.0)))), transform).unwrap();
+        let origin = Vec3::new(0.3, 0.2, -0.1);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(12);
+
+        let n = 100000;
+        let mut total = 0.0;
+        for _ in 0..n {
+            let sample = light.sample(origin, &mut rng).unwrap();
+            assert!((light.pdf(origin, sample.direction) - sample.pdf).abs() < 1e-6 * sample.pdf);
+            //the sampled point is on the surface the rays hit
+            let hit = light.hit(&Ray::new(origin, sample.direction), 1e-9, f64::MAX).unwrap();
+            assert!((hit.t - sample.distance).abs() < 1e-6);
+            total += 1.0 / sample.pdf;
+        }
+        let solid_angle = total / n as f64;
+        assert!((solid_angle - 4.0 * PI).abs() < 0.05 * 4.0 * PI, "{}", solid_angle);
+        assert_eq!(LightList::new(&light).len(), 1);
+    }
+
+    #[test]
+    fn test_light_seen_from_outside() {
+        //a sphere of radius 2 is a unit sphere scaled by 2, and its pdf must not change
+        let transform = Mat4::translation(Vec3::new(0.0, 0.0, -6.0)) * Mat4::scaling(Vec3::new(2.0, 2.0, 2.0));
+        let light = Transformed::new(unit_sphere(Box::new(DiffuseLight::new(Vec3::new(1.0, 1.0, 1.0)))), transform).unwrap();
+        let sphere = Sphere::new(Vec3::new(0.0, 0.0, -6.0), 2.0, Box::new(DiffuseLight::new(Vec3::new(1.0, 1.0, 1.0))));
+        let origin = Vec3::new(0.0, 0.0, 0.0);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(13);
+
+        for _ in 0..100 {
+            let sample = light.sample(origin, &mut rng).unwrap();
+            assert!((sample.pdf - sphere.pdf(origin, sample.direction)).abs() < 1e-6 * sample.pdf);
+        }
+        assert_eq!(light.pdf(origin, Vec3::new(0.0, 1.0, 0.0)), 0.0);
+    }
+}
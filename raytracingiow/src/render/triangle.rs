@@ -0,0 +1,288 @@
+use crate::math::Aabb;
+use crate::math::Ray;
+use crate::math::Vec3;
+use crate::render::light::area_to_solid_angle;
+use crate::render::light::sample_towards;
+use crate::render::light::Light;
+use crate::render::light::LightSample;
+use crate::render::material::Material;
+use crate::render::quad::FLAT_BOX_PADDING;
+use crate::render::renderable::HitRecord;
+use crate::render::renderable::Renderable;
+
+use rand::Rng;
+use rand::RngCore;
+
+//where a ray crosses a triangle, weights are for p0, p1 and p2 and add up to 1
+pub struct TriangleHit {
+    pub t: f64,
+    pub barycentric: [f64; 3],
+}
+
+//watertight ray triangle test (Woop, Benthin and Wald 2013). the ray is sheared so it runs along +z,
+//then the edge functions are evaluated in 2d. a ray through a shared edge or vertex hits at least one
+//of the triangles around it, unlike with Moller-Trumbore where it can slip through the crack
+pub fn intersect_triangle(ray: &Ray, p0: Vec3, p1: Vec3, p2: Vec3, t_min: f64, t_max: f64) -> Option<TriangleHit> {
+    let origin = ray.origin();
+    let direction = ray.direction();
+
+    //the dominant axis of the direction becomes z, swapping x and y keeps the winding
+    let kz = if direction.x().abs() > direction.y().abs() {
+        if direction.x().abs() > direction.z().abs() { 0 } else { 2 }
+    } else if direction.y().abs() > direction.z().abs() {
+        1
+    } else {
+        2
+    };
+    let (kx, ky) = if direction[kz] < 0_f64 { ((kz + 2) % 3, (kz + 1) % 3) } else { ((kz + 1) % 3, (kz + 2) % 3) };
+
+    let shear_x = direction[kx] / direction[kz];
+    let shear_y = direction[ky] / direction[kz];
+    let shear_z = 1_f64 / direction[kz];
+
+    let a = p0 - origin;
+    let b = p1 - origin;
+    let c = p2 - origin;
+    let (ax, ay) = (a[kx] - shear_x * a[kz], a[ky] - shear_y * a[kz]);
+    let (bx, by) = (b[kx] - shear_x * b[kz], b[ky] - shear_y * b[kz]);
+    let (cx, cy) = (c[kx] - shear_x * c[kz], c[ky] - shear_y * c[kz]);
+
+    let u = cx * by - cy * bx;
+    let v = ax * cy - ay * cx;
+    let w = bx * ay - by * ax;
+    if (u < 0_f64 || v < 0_f64 || w < 0_f64) && (u > 0_f64 || v > 0_f64 || w > 0_f64) {
+        return None;
+    }
+
+    let det = u + v + w;
+    if det == 0_f64 {
+        return None;
+    }
+
+    let t = (u * shear_z * a[kz] + v * shear_z * b[kz] + w * shear_z * c[kz]) / det;
+    if !(t >= t_min && t <= t_max) {
+        return None;
+    }
+
+    return Some(TriangleHit {
+        t,
+        barycentric: [u / det, v / det, w / det],
+    });
+}
+
+//uniform point on a triangle as barycentric weights
+pub fn sample_triangle(rng: &mut dyn RngCore) -> [f64; 3] {
+    let r1 = rng.gen::<f64>().sqrt();
+    let r2 = rng.gen::<f64>();
+    return [1_f64 - r1, r1 * (1_f64 - r2), r1 * r2];
+}
+
+//a single triangle, counter clockwise vertices face towards the viewer
+pub struct Triangle {
+    vertices: [Vec3; 3],
+    //per vertex shading normals, the flat face normal when missing
+    normals: Option<[Vec3; 3]>,
+    //per vertex texture coordinates, (0, 0), (1, 0) and (0, 1) when missing
+    uvs: Option<[(f64, f64); 3]>,
+    normal: Vec3,
+    area: f64,
+    material: Box<dyn Material>,
+}
+
+impl Triangle {
+    pub fn new(p0: Vec3, p1: Vec3, p2: Vec3, material: Box<dyn Material>) -> Self {
+        let n = (p1 - p0).cross(p2 - p0);
+        Self {
+            vertices: [p0, p1, p2],
+            normals: None,
+            uvs: None,
+            normal: n.unit_vector(),
+            area: 0.5 * n.length(),
+            material,
+        }
+    }
+
+    pub fn with_normals(self, normals: [Vec3; 3]) -> Self {
+        Self {
+            normals: Some([normals[0].unit_vector(), normals[1].unit_vector(), normals[2].unit_vector()]),
+            ..self
+        }
+    }
+
+    pub fn with_uvs(self, uvs: [(f64, f64); 3]) -> Self {
+        Self {
+            uvs: Some(uvs),
+            ..self
+        }
+    }
+
+    pub fn area(&self) -> f64 {
+        return self.area;
+    }
+}
+
+//weighted sum of three per vertex values
+pub fn interpolate(values: [Vec3; 3], barycentric: [f64; 3]) -> Vec3 {
+    return barycentric[0] * values[0] + barycentric[1] * values[1] + barycentric[2] * values[2];
+}
+
+pub fn interpolate_uv(uvs: [(f64, f64); 3], barycentric: [f64; 3]) -> (f64, f64) {
+    let [b0, b1, b2] = barycentric;
+    return (
+        b0 * uvs[0].0 + b1 * uvs[1].0 + b2 * uvs[2].0,
+        b0 * uvs[0].1 + b1 * uvs[1].1 + b2 * uvs[2].1,
+    );
+}
+
+impl Renderable for Triangle {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let [p0, p1, p2] = self.vertices;
+        let hit = intersect_triangle(ray, p0, p1, p2, t_min, t_max)?;
+
+        let normal = match self.normals {
+            Some(normals) => {
+                let shading = interpolate(normals, hit.barycentric);
+                //opposite vertex normals can cancel out
+                if shading.length_squared() > 1e-24 { shading.unit_vector() } else { self.normal }
+            }
+            None => self.normal,
+        };
+        let (u, v) = match self.uvs {
+            Some(uvs) => interpolate_uv(uvs, hit.barycentric),
+            None => (hit.barycentric[1], hit.barycentric[2]),
+        };
+
+        return Some(HitRecord {
+            t: hit.t,
+            position: interpolate(self.vertices, hit.barycentric),
+            normal,
+            u,
+            v,
+            material: &*self.material,
+        });
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let [p0, p1, p2] = self.vertices;
+        return Some(Aabb::from_points(p0, p1).surrounding(&Aabb::new(p2, p2)).padded(FLAT_BOX_PADDING));
+    }
+
+    fn collect_lights<'a>(&'a self, lights: &mut Vec<&'a dyn Light>) {
+        if self.material.is_emissive() && self.area > 0_f64 {
+            lights.push(self);
+        }
+    }
+}
+
+impl Light for Triangle {
+    fn sample(&self, origin: Vec3, rng: &mut dyn RngCore) -> Option<LightSample> {
+        let point = interpolate(self.vertices, sample_triangle(rng));
+        return sample_towards(origin, point, area_to_solid_angle(origin, point, self.normal, self.area));
+    }
+
+    fn pdf(&self, origin: Vec3, direction: Vec3) -> f64 {
+        let [p0, p1, p2] = self.vertices;
+        return match intersect_triangle(&Ray::new(origin, direction), p0, p1, p2, 1e-9, f64::MAX) {
+            Some(hit) => area_to_solid_angle(origin, interpolate(self.vertices, hit.barycentric), self.normal, self.area),
+            None => 0_f64,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Ray;
+    use crate::math::Vec3;
+    use crate::render::*;
+
+    use rand::Rng;
+    use rand::SeedableRng;
+
+    const EPS: f64 = 1e-9;
+
+    fn grey() -> Box<dyn Material> {
+        return Box::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)));
+    }
+
+    fn unit_triangle() -> Triangle {
+        return Triangle::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), grey());
+    }
+
+    #[test]
+    fn test_hit_barycentric() {
+        let triangle = unit_triangle();
+        let ray = Ray::new(Vec3::new(0.2, 0.3, 2.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = triangle.hit(&ray, 0.0, f64::MAX).unwrap();
+        assert!((hit.t - 2.0).abs() < EPS);
+        assert!((hit.position - Vec3::new(0.2, 0.3, 0.0)).length() < EPS);
+        assert!((hit.normal - Vec3::new(0.0, 0.0, 1.0)).length() < EPS);
+        assert!((hit.u - 0.2).abs() < EPS && (hit.v - 0.3).abs() < EPS);
+
+        //from behind hits too, with the same face normal
+        let back = Ray::new(Vec3::new(0.2, 0.3, -2.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!((triangle.hit(&back, 0.0, f64::MAX).unwrap().normal - Vec3::new(0.0, 0.0, 1.0)).length() < EPS);
+
+        let outside = Ray::new(Vec3::new(0.6, 0.6, 2.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(triangle.hit(&outside, 0.0, f64::MAX).is_none());
+        let edge_on = Ray::new(Vec3::new(-1.0, 0.2, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(triangle.hit(&edge_on, 0.0, f64::MAX).is_none());
+    }
+
+    #[test]
+    fn test_interpolated_attributes() {
+        let triangle = unit_triangle()
+            .with_normals([Vec3::new(0.0, 0.0, 1.0), Vec3::new(1.0, 0.0, 1.0), Vec3::new(0.0, 1.0, 1.0)])
+            .with_uvs([(0.5, 0.5), (1.0, 0.5), (0.5, 1.0)]);
+        let ray = Ray::new(Vec3::new(0.5, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = triangle.hit(&ray, 0.0, f64::MAX).unwrap();
+
+        //halfway along the edge p0 p1
+        let expected = (Vec3::new(0.0, 0.0, 1.0) + Vec3::new(1.0, 0.0, 1.0).unit_vector()).unit_vector();
+        assert!((hit.normal - expected).length() < EPS);
+        assert!((hit.u - 0.75).abs() < EPS && (hit.v - 0.5).abs() < EPS);
+
+        //vertex normals that cancel out fall back to the face normal
+        let up = Vec3::new(0.0, 0.0, 1.0);
+        let triangle = unit_triangle().with_normals([up, -up, up]);
+        let hit = triangle.hit(&Ray::new(Vec3::new(0.5, 0.25, 1.0), Vec3::new(0.0, 0.0, -1.0)), 0.0, f64::MAX).unwrap();
+        assert!((hit.normal - up).length() < EPS);
+    }
+
+    #[test]
+    fn test_watertight_shared_edge() {
+        //two triangles of a square, rays aimed exactly at the diagonal must hit one of them
+        let a = Vec3::new(0.0, 0.0, 0.0);
+        let b = Vec3::new(1.0, 0.0, 0.3);
+        let c = Vec3::new(1.0, 1.0, 0.0);
+        let d = Vec3::new(0.0, 1.0, 0.7);
+        let origin = Vec3::new(0.3, 0.1, 5.0);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(5);
+
+        for _ in 0..10000 {
+            let s = rng.gen::<f64>();
+            let target = s * a + (1.0 - s) * c;
+            let ray = Ray::new(origin, target - origin);
+            let first = intersect_triangle(&ray, a, b, c, 0.0, f64::MAX);
+            let second = intersect_triangle(&ray, a, c, d, 0.0, f64::MAX);
+            assert!(first.is_some() || second.is_some(), "slipped through at {:?}", target);
+        }
+    }
+
+    #[test]
+    fn test_light_sample_matches_pdf() {
+        let light = Triangle::new(
+            Vec3::new(-1.0, 2.0, -1.0),
+            Vec3::new(1.0, 2.0, -1.0),
+            Vec3::new(0.0, 2.5, 1.0),
+            Box::new(DiffuseLight::new(Vec3::new(1.0, 1.0, 1.0))),
+        );
+        let origin = Vec3::new(0.1, 0.0, 0.2);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(6);
+
+        for _ in 0..1000 {
+            let sample = light.sample(origin, &mut rng).unwrap();
+            assert!((light.pdf(origin, sample.direction) - sample.pdf).abs() < 1e-6 * sample.pdf);
+        }
+        assert_eq!(light.pdf(origin, Vec3::new(0.0, -1.0, 0.0)), 0.0);
+    }
+}
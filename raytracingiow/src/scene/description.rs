@@ -0,0 +1,1146 @@
+use crate::import::GltfCamera;
+use crate::import::GltfDocument;
+use crate::import::ObjModel;
+use crate::import::load_density_grid;
+use crate::math::Aabb;
+use crate::math::Mat4;
+use crate::math::Vec3;
+use crate::output::ToneMap;
+use crate::output::ToneMapOperator;
+use crate::output::TransferFunction;
+use crate::render::*;
+use crate::scene::Scene;
+use crate::scene::SceneError;
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+use toml::Spanned;
+
+//mirrors the layout of a scene file, see scenes/chapter8.toml for an example
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SceneDescription {
+    #[serde(default)]
+    render: RenderDescription,
+    #[serde(default)]
+    camera: CameraDescription,
+    #[serde(default)]
+    tone_map: ToneMapDescription,
+    #[serde(default)]
+    textures: HashMap<String, TextureDescription>,
+    #[serde(default)]
+    materials: HashMap<String, MaterialDescription>,
+    #[serde(default)]
+    objects: Vec<Spanned<ObjectDescription>>,
+    fog: Option<Spanned<FogDescription>>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct RenderDescription {
+    width: Option<Spanned<usize>>,
+    height: Option<Spanned<usize>>,
+    samples_per_pixel: Option<Spanned<u32>>,
+    max_bounces: Option<u32>,
+    integrator: Option<Spanned<String>>,
+    //constant color seen by rays that leave the scene, the sky gradient when missing
+    background: Option<Spanned<[f64; 3]>>,
+    seed: Option<u64>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct ToneMapDescription {
+    operator: Option<Spanned<String>>,
+    //stops
+    exposure: Option<Spanned<f64>>,
+    white_point: Option<Spanned<f64>>,
+    transfer: Option<Spanned<String>>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct CameraDescription {
+    //(0 0 0) looking at (0 0 -1) with y up by default
+    look_from: Option<Spanned<[f64; 3]>>,
+    look_at: Option<Spanned<[f64; 3]>>,
+    vup: Option<Spanned<[f64; 3]>>,
+    //vertical field of view in degrees, 90 by default
+    vfov: Option<Spanned<f64>>,
+    //lens diameter, 0 keeps everything in focus
+    aperture: Option<Spanned<f64>>,
+    //defaults to the distance between look_from and look_at
+    focus_distance: Option<Spanned<f64>>,
+    aperture_shape: Option<Spanned<ApertureDescription>>,
+    //open and close times, rays are spread over them for motion blur
+    shutter: Option<Spanned<[f64; 2]>>,
+    //takes look_from, look_at, vup and vfov from a camera of a glTF file instead
+    gltf: Option<Spanned<GltfCameraDescription>>,
+}
+
+//homogeneous medium filling the whole scene, inside a sphere around the camera
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FogDescription {
+    density: f64,
+    #[serde(default = "default_fog_albedo")]
+    albedo: [f64; 3],
+    //henyey greenstein asymmetry, 0 scatters the same way in every direction
+    #[serde(default)]
+    g: f64,
+    //just past the farthest bounded object by default, rays that leave it see the background unfogged
+    radius: Option<f64>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GltfCameraDescription {
+    path: String,
+    //which of the cameras of the file, in the order of its node hierarchy
+    #[serde(default)]
+    index: usize,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum ApertureDescription {
+    Circle,
+    Polygon {
+        blades: u32,
+        //degrees
+        #[serde(default)]
+        rotation: f64,
+    },
+    Custom { points: Vec<[f64; 2]> },
+}
+
+//material parameters take a number, a color, the name of a texture or an inline texture table
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TextureInput {
+    Value(f64),
+    Color([f64; 3]),
+    Named(String),
+    Inline(Box<TextureDescription>),
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum TextureDescription {
+    Constant {
+        color: [f64; 3],
+    },
+    Checker {
+        even: TextureInput,
+        odd: TextureInput,
+        //twice as many cells around a sphere as from pole to pole makes them square
+        #[serde(default = "default_u_cells")]
+        u_cells: f64,
+        #[serde(default = "default_v_cells")]
+        v_cells: f64,
+    },
+    SolidChecker {
+        even: TextureInput,
+        odd: TextureInput,
+        //edge length of the cubes
+        #[serde(default = "default_checker_size")]
+        size: f64,
+    },
+    Image {
+        //relative to the scene file
+        path: String,
+        //nearest, bilinear or bicubic
+        filter: Option<String>,
+        //wrap, clamp or mirror
+        address: Option<String>,
+        //srgb for colors, linear for data like roughness
+        encoding: Option<String>,
+    },
+    Gradient {
+        start: [f64; 3],
+        end: [f64; 3],
+        //"u" or "v", or from and to points in world space
+        axis: Option<String>,
+        from: Option<[f64; 3]>,
+        to: Option<[f64; 3]>,
+    },
+    Noise {
+        //perlin, turbulence, fbm, worley or worley_edges
+        pattern: String,
+        #[serde(default)]
+        seed: u64,
+        #[serde(default = "default_noise_scale")]
+        scale: f64,
+        #[serde(default = "default_octaves")]
+        octaves: u32,
+        #[serde(default = "default_lacunarity")]
+        lacunarity: f64,
+        #[serde(default = "default_gain")]
+        gain: f64,
+        //colors at 0 and 1, black and white when left out
+        low: Option<TextureInput>,
+        high: Option<TextureInput>,
+    },
+    Marble {
+        #[serde(default)]
+        seed: u64,
+        scale: Option<f64>,
+        turbulence: Option<f64>,
+        octaves: Option<u32>,
+        vein: Option<TextureInput>,
+        base: Option<TextureInput>,
+    },
+    Wood {
+        #[serde(default)]
+        seed: u64,
+        //rings per world unit
+        ring_frequency: Option<f64>,
+        distortion: Option<f64>,
+        octaves: Option<u32>,
+        light: Option<TextureInput>,
+        dark: Option<TextureInput>,
+    },
+}
+
+fn default_u_cells() -> f64 {
+    return 20.0;
+}
+
+fn default_v_cells() -> f64 {
+    return 10.0;
+}
+
+fn default_checker_size() -> f64 {
+    return 1.0;
+}
+
+fn default_noise_scale() -> f64 {
+    return 1.0;
+}
+
+fn default_roughness() -> TextureInput {
+    return TextureInput::Value(0.3);
+}
+
+fn default_scale() -> f64 {
+    return 1.0;
+}
+
+fn default_octaves() -> u32 {
+    return 7;
+}
+
+fn default_lacunarity() -> f64 {
+    return 2.0;
+}
+
+fn default_gain() -> f64 {
+    return 0.5;
+}
+
+//deeper than any sensible scene, stops textures that refer to each other in a loop
+const MAX_TEXTURE_DEPTH: u32 = 32;
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum MaterialDescription {
+    Lambertian { albedo: TextureInput },
+    Metal { albedo: TextureInput, fuzz: TextureInput },
+    Dielectric { refraction_index: TextureInput },
+    DiffuseLight {
+        emit: TextureInput,
+        #[serde(default)]
+        one_sided: bool,
+    },
+    //ggx microfacet metal from a preset metal, a complex index of refraction eta + i k, or the
+    //reflectance at normal incidence
+    Conductor {
+        metal: Option<String>,
+        eta: Option<[f64; 3]>,
+        k: Option<[f64; 3]>,
+        reflectance: Option<TextureInput>,
+        #[serde(default = "default_roughness")]
+        roughness: TextureInput,
+        //along and across the tangent, both the roughness unless given
+        roughness_u: Option<TextureInput>,
+        roughness_v: Option<TextureInput>,
+        tangent: Option<[f64; 3]>,
+    },
+    //phase functions, only meaningful inside a constant_medium or fog
+    Isotropic { albedo: TextureInput },
+    HenyeyGreenstein { albedo: TextureInput, g: f64 },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum ObjectDescription {
+    Sphere {
+        center: [f64; 3],
+        radius: f64,
+        material: String,
+    },
+    Plane {
+        point: [f64; 3],
+        normal: [f64; 3],
+        material: String,
+    },
+    //axis aligned rectangles give the two ranges they span and the position on the third axis
+    XyRect {
+        x: [f64; 2],
+        y: [f64; 2],
+        z: f64,
+        material: String,
+        //face the negative axis instead of the positive one
+        #[serde(default)]
+        flip: bool,
+    },
+    XzRect {
+        x: [f64; 2],
+        z: [f64; 2],
+        y: f64,
+        material: String,
+        #[serde(default)]
+        flip: bool,
+    },
+    YzRect {
+        y: [f64; 2],
+        z: [f64; 2],
+        x: f64,
+        material: String,
+        #[serde(default)]
+        flip: bool,
+    },
+    //parallelogram with edges u and v from corner, facing u x v unless flipped
+    Quad {
+        corner: [f64; 3],
+        u: [f64; 3],
+        v: [f64; 3],
+        material: String,
+        #[serde(default)]
+        flip: bool,
+    },
+    //counter clockwise vertices face the viewer, normals and uvs are per vertex
+    Triangle {
+        vertices: [[f64; 3]; 3],
+        normals: Option<[[f64; 3]; 3]>,
+        uvs: Option<[[f64; 2]; 3]>,
+        material: String,
+    },
+    //wavefront obj file relative to the scene, with the materials of its mtl files unless one is given
+    Obj {
+        path: String,
+        material: Option<String>,
+        //moves the model after scaling it about its origin
+        #[serde(default)]
+        position: [f64; 3],
+        #[serde(default = "default_scale")]
+        scale: f64,
+    },
+    //moves in a straight line from center0 at time0 to center1 at time1, for motion blur
+    MovingSphere {
+        center0: [f64; 3],
+        center1: [f64; 3],
+        #[serde(default)]
+        time0: f64,
+        #[serde(default = "default_time1")]
+        time1: f64,
+        radius: f64,
+        material: String,
+    },
+    //an object placed by interpolating between keyframes at the time of each ray
+    Keyframed {
+        object: Box<ObjectDescription>,
+        keyframes: Vec<KeyframeDescription>,
+    },
+    //copies of one object sharing its geometry, one per transform
+    Instance {
+        object: Box<ObjectDescription>,
+        #[serde(default = "default_transforms")]
+        transforms: Vec<TransformDescription>,
+    },
+    //smoke filling a closed convex object, scattering by a phase function material
+    ConstantMedium {
+        boundary: Box<ObjectDescription>,
+        //collisions per unit of distance
+        density: f64,
+        material: String,
+    },
+    //clouds and smoke from a density grid file relative to the scene, stretched from min to max
+    GridMedium {
+        path: String,
+        //of a headerless raw volume, grid files carry their own
+        resolution: Option<[usize; 3]>,
+        min: [f64; 3],
+        max: [f64; 3],
+        //collisions per unit of distance where the grid value is 1
+        #[serde(default = "default_density")]
+        density: f64,
+        material: String,
+    },
+    //glTF 2.0 file (.gltf or .glb) relative to the scene, with its materials and punctual lights
+    Gltf {
+        path: String,
+        //of the spheres standing in for point and spot lights, a 200th of the model size by default
+        light_radius: Option<f64>,
+    },
+}
+
+//scales, then rotates about x, y and z in that order, then translates
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TransformDescription {
+    #[serde(default)]
+    translate: [f64; 3],
+    //degrees
+    #[serde(default)]
+    rotate: [f64; 3],
+    #[serde(default = "default_scale_input")]
+    scale: ScaleInput,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ScaleInput {
+    Uniform(f64),
+    PerAxis([f64; 3]),
+}
+
+fn default_scale_input() -> ScaleInput {
+    return ScaleInput::Uniform(1.0);
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KeyframeDescription {
+    time: f64,
+    #[serde(default)]
+    translate: [f64; 3],
+    //degrees about x, then y, then z
+    #[serde(default)]
+    rotate: [f64; 3],
+    #[serde(default = "default_scale_input")]
+    scale: ScaleInput,
+}
+
+impl KeyframeDescription {
+    fn build(&self) -> Result<Keyframe, String> {
+        if !self.time.is_finite() || !all_finite(&self.translate) || !all_finite(&self.rotate) || !self.scale.is_finite() {
+            return Err(format!("keyframe at time {} needs finite translate, rotate and scale", self.time));
+        }
+        return Ok(Keyframe::new(self.time)
+            .with_translation(vec3(self.translate))
+            .with_rotation(Vec3::new(1.0, 0.0, 0.0), self.rotate[0])
+            .with_rotation(Vec3::new(0.0, 1.0, 0.0), self.rotate[1])
+            .with_rotation(Vec3::new(0.0, 0.0, 1.0), self.rotate[2])
+            .with_scale(self.scale.build()));
+    }
+}
+
+fn default_density() -> f64 {
+    return 1.0;
+}
+
+fn default_fog_albedo() -> [f64; 3] {
+    return [1.0, 1.0, 1.0];
+}
+
+fn default_time1() -> f64 {
+    return 1.0;
+}
+
+fn default_transforms() -> Vec<TransformDescription> {
+    return vec![TransformDescription {
+        translate: [0.0, 0.0, 0.0],
+        rotate: [0.0, 0.0, 0.0],
+        scale: default_scale_input(),
+    }];
+}
+
+impl ScaleInput {
+    fn is_finite(&self) -> bool {
+        return match self {
+            ScaleInput::Uniform(s) => s.is_finite(),
+            ScaleInput::PerAxis(s) => all_finite(s),
+        };
+    }
+
+    fn build(&self) -> Vec3 {
+        return match *self {
+            ScaleInput::Uniform(s) => Vec3::new(s, s, s),
+            ScaleInput::PerAxis(s) => vec3(s),
+        };
+    }
+}
+
+impl TransformDescription {
+    fn build(&self) -> Result<Mat4, String> {
+        if !all_finite(&self.translate) || !all_finite(&self.rotate) || !self.scale.is_finite() {
+            return Err("translate, rotate and scale must be finite".to_string());
+        }
+        let rotate = Mat4::rotation(Vec3::new(0.0, 0.0, 1.0), self.rotate[2])
+            * Mat4::rotation(Vec3::new(0.0, 1.0, 0.0), self.rotate[1])
+            * Mat4::rotation(Vec3::new(1.0, 0.0, 0.0), self.rotate[0]);
+        return Ok(Mat4::translation(vec3(self.translate)) * rotate * Mat4::scaling(self.scale.build()));
+    }
+}
+
+fn vec3(v: [f64; 3]) -> Vec3 {
+    return Vec3::new(v[0], v[1], v[2]);
+}
+
+fn all_finite(values: &[f64]) -> bool {
+    return values.iter().all(|v| v.is_finite());
+}
+
+//1-based line number of a byte offset into the source
+fn line_of(source: &str, offset: usize) -> usize {
+    let end = offset.min(source.len());
+    return source[..end].matches('\n').count() + 1;
+}
+
+fn invalid(source: &str, span: Range<usize>, message: String) -> SceneError {
+    return SceneError::Invalid {
+        line: line_of(source, span.start),
+        message,
+    };
+}
+
+//the value of an optional field, or the default when it is missing
+fn field_or<T: Copy>(field: &Option<Spanned<T>>, default: T) -> T {
+    return field.as_ref().map_or(default, |value| *value.get_ref());
+}
+
+//numbers that would turn every pixel into nan
+fn finite_field(source: &str, name: &str, field: &Option<Spanned<f64>>) -> Result<(), SceneError> {
+    return match field {
+        Some(value) if !value.get_ref().is_finite() => Err(invalid(source, value.span(), format!("{} must be finite, got {}", name, value.get_ref()))),
+        _ => Ok(()),
+    };
+}
+
+fn finite_vector_field(source: &str, name: &str, field: &Option<Spanned<[f64; 3]>>) -> Result<(), SceneError> {
+    return match field {
+        Some(value) if !all_finite(value.get_ref()) => Err(invalid(source, value.span(), format!("{} must be finite, got {:?}", name, value.get_ref()))),
+        _ => Ok(()),
+    };
+}
+
+//sizes and sample counts, 0 makes an empty or nan image
+fn nonzero_field<T: Copy + PartialEq + Default>(source: &str, name: &str, field: &Option<Spanned<T>>, default: T) -> Result<T, SceneError> {
+    return match field {
+        Some(value) if *value.get_ref() == T::default() => Err(invalid(source, value.span(), format!("{} must be at least 1", name))),
+        _ => Ok(field_or(field, default)),
+    };
+}
+
+impl SceneDescription {
+    //relative paths in the scene, such as image textures, start at base_dir
+    pub fn build(self, source: &str, base_dir: &Path) -> Result<Scene, SceneError> {
+        let settings = self.render.build(source)?;
+        let camera = self.camera.build(source, settings.width as f64 / settings.height as f64, base_dir)?;
+        let tone_map = self.tone_map.build(source)?;
+
+        let mut context = TextureContext {
+            textures: &self.textures,
+            images: ImageCache::new(),
+            base_dir,
+        };
+
+        let mut world: Vec<Box<dyn Renderable>> = Vec::new();
+        for object in &self.objects {
+            let renderable = object
+                .get_ref()
+                .build(&self.materials, &mut context)
+                .map_err(|e| invalid(source, object.span(), e))?;
+            world.push(renderable);
+        }
+
+        if let Some(fog) = &self.fog {
+            let medium = fog.get_ref().build(camera.look_from(), &world).map_err(|e| invalid(source, fog.span(), e))?;
+            world.push(medium);
+        }
+
+        return Ok(Scene {
+            world: Box::new(Bvh::new(world)),
+            camera,
+            settings,
+            tone_map,
+        });
+    }
+}
+
+impl RenderDescription {
+    fn build(&self, source: &str) -> Result<RenderSettings, SceneError> {
+        let defaults = RenderSettings::default();
+        let integrator = match &self.integrator {
+            Some(name) => name
+                .get_ref()
+                .parse::<Integrator>()
+                .map_err(|e| invalid(source, name.span(), e))?,
+            None => defaults.integrator,
+        };
+
+        finite_vector_field(source, "background", &self.background)?;
+
+        return Ok(RenderSettings {
+            width: nonzero_field(source, "width", &self.width, defaults.width)?,
+            height: nonzero_field(source, "height", &self.height, defaults.height)?,
+            samples_per_pixel: nonzero_field(source, "samples_per_pixel", &self.samples_per_pixel, defaults.samples_per_pixel)?,
+            max_bounces: self.max_bounces.unwrap_or(defaults.max_bounces),
+            integrator,
+            background: self.background.as_ref().map_or(defaults.background, |c| Background::Color(vec3(*c.get_ref()))),
+            seed: self.seed.unwrap_or(defaults.seed),
+            ..defaults
+        });
+    }
+}
+
+impl ToneMapDescription {
+    fn build(&self, source: &str) -> Result<ToneMap, SceneError> {
+        let defaults = ToneMap::default();
+        let operator = match &self.operator {
+            Some(name) => name
+                .get_ref()
+                .parse::<ToneMapOperator>()
+                .map_err(|e| invalid(source, name.span(), e))?,
+            None => defaults.operator,
+        };
+        let transfer = match &self.transfer {
+            Some(name) => name
+                .get_ref()
+                .parse::<TransferFunction>()
+                .map_err(|e| invalid(source, name.span(), e))?,
+            None => defaults.transfer,
+        };
+
+        finite_field(source, "exposure", &self.exposure)?;
+        finite_field(source, "white_point", &self.white_point)?;
+
+        return Ok(ToneMap {
+            operator,
+            exposure: field_or(&self.exposure, defaults.exposure),
+            white_point: field_or(&self.white_point, defaults.white_point),
+            transfer,
+        });
+    }
+}
+
+impl CameraDescription {
+    fn build(&self, source: &str, aspect: f64, base_dir: &Path) -> Result<Camera, SceneError> {
+        finite_vector_field(source, "look_from", &self.look_from)?;
+        finite_vector_field(source, "look_at", &self.look_at)?;
+        finite_vector_field(source, "vup", &self.vup)?;
+        finite_field(source, "vfov", &self.vfov)?;
+        finite_field(source, "aperture", &self.aperture)?;
+        finite_field(source, "focus_distance", &self.focus_distance)?;
+
+        //a glTF camera is blamed on the gltf line, the others on the first of the fields that is given
+        let span_of = |fields: &[&Option<Spanned<[f64; 3]>>]| match &self.gltf {
+            Some(gltf) => gltf.span(),
+            None => fields.iter().find_map(|field| field.as_ref()).map_or(0..0, |value| value.span()),
+        };
+        let (look_from, look_at, vup, vfov) = match &self.gltf {
+            Some(gltf) => {
+                let camera = gltf.get_ref().build(base_dir).map_err(|e| invalid(source, gltf.span(), e))?;
+                (camera.look_from, camera.look_at, camera.vup, camera.vfov)
+            }
+            None => (
+                vec3(field_or(&self.look_from, [0.0, 0.0, 0.0])),
+                vec3(field_or(&self.look_at, [0.0, 0.0, -1.0])),
+                vec3(field_or(&self.vup, [0.0, 1.0, 0.0])),
+                field_or(&self.vfov, 90.0),
+            ),
+        };
+
+        let direction = look_at - look_from;
+        if direction.length_squared() == 0_f64 {
+            return Err(invalid(source, span_of(&[&self.look_at, &self.look_from]), "look_at must differ from look_from".to_string()));
+        }
+        if direction.cross(vup).length_squared() <= 1e-12 * direction.length_squared() * vup.length_squared() {
+            return Err(invalid(source, span_of(&[&self.vup, &self.look_at, &self.look_from]), "vup must not be zero or parallel to the view direction".to_string()));
+        }
+        if !(vfov > 0_f64 && vfov < 180_f64) {
+            let span = match (&self.gltf, &self.vfov) {
+                (Some(gltf), _) => gltf.span(),
+                (None, Some(vfov)) => vfov.span(),
+                (None, None) => 0..0,
+            };
+            return Err(invalid(source, span, format!("vfov must be between 0 and 180 degrees, got {}", vfov)));
+        }
+
+        let aperture = match &self.aperture {
+            Some(aperture) if *aperture.get_ref() < 0_f64 => {
+                return Err(invalid(source, aperture.span(), format!("aperture must not be negative, got {}", aperture.get_ref())));
+            }
+            Some(aperture) => *aperture.get_ref(),
+            None => 0_f64,
+        };
+        let focus_distance = match &self.focus_distance {
+            Some(distance) if *distance.get_ref() <= 0_f64 => {
+                return Err(invalid(source, distance.span(), format!("focus_distance must be positive, got {}", distance.get_ref())));
+            }
+            Some(distance) => *distance.get_ref(),
+            None => direction.length(),
+        };
+
+        let aperture_shape = match &self.aperture_shape {
+            Some(shape) => shape.get_ref().build().map_err(|e| invalid(source, shape.span(), e))?,
+            None => ApertureShape::circle(),
+        };
+
+        let [open, close] = match &self.shutter {
+            Some(shutter) if !all_finite(shutter.get_ref()) => {
+                return Err(invalid(source, shutter.span(), format!("shutter must be finite, got {:?}", shutter.get_ref())));
+            }
+            Some(shutter) if shutter.get_ref()[1] < shutter.get_ref()[0] => {
+                let [open, close] = *shutter.get_ref();
+                return Err(invalid(source, shutter.span(), format!("shutter closes at {} before it opens at {}", close, open)));
+            }
+            Some(shutter) => *shutter.get_ref(),
+            None => [0.0, 0.0],
+        };
+
+        let camera = Camera::new(look_from, look_at, vup, vfov, aspect)
+            .with_lens(aperture, focus_distance)
+            .with_aperture_shape(aperture_shape)
+            .with_shutter(open, close);
+        return Ok(camera);
+    }
+}
+
+impl FogDescription {
+    fn build(&self, center: Vec3, world: &[Box<dyn Renderable>]) -> Result<Box<dyn Renderable>, String> {
+        if self.density <= 0_f64 {
+            return Err(format!("fog density must be positive, got {}", self.density));
+        }
+        if !(self.g > -1_f64 && self.g < 1_f64) {
+            return Err(format!("fog g must be between -1 and 1, got {}", self.g));
+        }
+
+        let radius = match self.radius {
+            Some(radius) if radius <= 0_f64 => return Err(format!("fog radius must be positive, got {}", radius)),
+            Some(radius) => radius,
+            None => {
+                //past the box of every bounded object, planes and the like don't count
+                let mut farthest = 1_f64;
+                for bbox in world.iter().filter_map(|object| object.bounding_box()) {
+                    let middle = 0.5 * (bbox.min() + bbox.max());
+                    let reach = (middle - center).length() + 0.5 * (bbox.max() - bbox.min()).length();
+                    farthest = farthest.max(reach);
+                }
+                farthest * 1.01
+            }
+        };
+
+        let albedo = vec3(self.albedo);
+        let phase: Box<dyn Material> = if self.g == 0_f64 {
+            Box::new(Isotropic::new(albedo))
+        } else {
+            Box::new(HenyeyGreenstein::new(albedo, self.g))
+        };
+        let boundary = Sphere::new(center, radius, Box::new(Lambertian::new(albedo)));
+        return Ok(Box::new(ConstantMedium::new(Box::new(boundary), self.density, phase)));
+    }
+}
+
+impl GltfCameraDescription {
+    fn build(&self, base_dir: &Path) -> Result<GltfCamera, String> {
+        let full_path = base_dir.join(&self.path);
+        let cameras = GltfDocument::load(&full_path).and_then(|d| d.cameras()).map_err(|e| e.to_string())?;
+        return match cameras.get(self.index) {
+            Some(camera) => Ok(*camera),
+            None => Err(format!("{} has {} perspective cameras, there is no camera {}", full_path.display(), cameras.len(), self.index)),
+        };
+    }
+}
+
+impl ApertureDescription {
+    fn build(&self) -> Result<ApertureShape, String> {
+        return match self {
+            ApertureDescription::Circle => Ok(ApertureShape::circle()),
+            ApertureDescription::Polygon { blades, rotation } => ApertureShape::polygon(*blades, *rotation),
+            ApertureDescription::Custom { points } => ApertureShape::custom(points.iter().map(|p| (p[0], p[1])).collect()),
+        };
+    }
+}
+
+type Textures = HashMap<String, TextureDescription>;
+
+//what building a texture needs besides its own description
+struct TextureContext<'a> {
+    textures: &'a Textures,
+    //images shared between every texture of the scene that uses them
+    images: ImageCache,
+    base_dir: &'a Path,
+}
+
+fn parse_option<T: std::str::FromStr<Err = String>>(value: &Option<String>, default: T) -> Result<T, String> {
+    return match value {
+        Some(value) => value.parse::<T>(),
+        None => Ok(default),
+    };
+}
+
+impl TextureInput {
+    fn build(&self, context: &mut TextureContext, depth: u32) -> Result<Box<dyn Texture>, String> {
+        return match self {
+            TextureInput::Value(value) => Ok(Box::new(ConstantTexture::scalar(*value))),
+            TextureInput::Color(color) => Ok(Box::new(ConstantTexture::new(vec3(*color)))),
+            TextureInput::Named(name) => lookup_texture(context, name, depth),
+            TextureInput::Inline(texture) => texture.build(context, depth),
+        };
+    }
+}
+
+impl TextureDescription {
+    fn build(&self, context: &mut TextureContext, depth: u32) -> Result<Box<dyn Texture>, String> {
+        return match self {
+            TextureDescription::Constant { color } => Ok(Box::new(ConstantTexture::new(vec3(*color)))),
+            TextureDescription::Checker { even, odd, u_cells, v_cells } => Ok(Box::new(CheckerTexture::uv(
+                even.build(context, depth + 1)?,
+                odd.build(context, depth + 1)?,
+                *u_cells,
+                *v_cells,
+            ))),
+            TextureDescription::SolidChecker { even, odd, size } => {
+                if *size <= 0.0 {
+                    return Err(format!("solid checker size must be positive, got {}", size));
+                }
+                Ok(Box::new(CheckerTexture::solid(even.build(context, depth + 1)?, odd.build(context, depth + 1)?, *size)))
+            }
+            TextureDescription::Image { path, filter, address, encoding } => {
+                let filter = parse_option(filter, TextureFilter::Bilinear)?;
+                let address = parse_option(address, AddressMode::Wrap)?;
+                let encoding = parse_option(encoding, TransferFunction::Srgb)?;
+
+                let full_path = context.base_dir.join(path);
+                let image = context
+                    .images
+                    .load(&full_path, encoding)
+                    .map_err(|e| format!("failed to load image {}: {}", full_path.display(), e))?;
+                Ok(Box::new(ImageTexture::new(image).with_filter(filter).with_address(address)))
+            }
+            TextureDescription::Gradient { start, end, axis, from, to } => {
+                let axis = match (axis.as_deref(), from, to) {
+                    (Some("u"), None, None) => GradientAxis::U,
+                    (Some("v"), None, None) => GradientAxis::V,
+                    (None, Some(from), Some(to)) => GradientAxis::Between(vec3(*from), vec3(*to)),
+                    _ => return Err("a gradient needs either axis = \"u\" or \"v\", or from and to points".to_string()),
+                };
+                Ok(Box::new(GradientTexture::new(vec3(*start), vec3(*end), axis)))
+            }
+            TextureDescription::Noise { pattern, seed, scale, octaves, lacunarity, gain, low, high } => {
+                let pattern = match pattern.as_str() {
+                    "perlin" => NoisePattern::Perlin,
+                    "turbulence" => NoisePattern::Turbulence { octaves: *octaves },
+                    "fbm" => NoisePattern::Fbm {
+                        octaves: *octaves,
+                        lacunarity: *lacunarity,
+                        gain: *gain,
+                    },
+                    "worley" => NoisePattern::Worley,
+                    "worley_edges" => NoisePattern::WorleyEdges,
+                    _ => {
+                        return Err(format!(
+                            "unknown noise pattern '{}', expected one of: perlin, turbulence, fbm, worley, worley_edges",
+                            pattern
+                        ))
+                    }
+                };
+
+                let mut texture = NoiseTexture::new(pattern, *seed).with_scale(*scale);
+                if let Some(low) = low {
+                    texture = texture.with_low(low.build(context, depth + 1)?);
+                }
+                if let Some(high) = high {
+                    texture = texture.with_high(high.build(context, depth + 1)?);
+                }
+                Ok(Box::new(texture))
+            }
+            TextureDescription::Marble { seed, scale, turbulence, octaves, vein, base } => {
+                let mut texture = MarbleTexture::new(*seed);
+                if let Some(scale) = scale {
+                    texture = texture.with_scale(*scale);
+                }
+                if let Some(turbulence) = turbulence {
+                    texture = texture.with_turbulence(*turbulence);
+                }
+                if let Some(octaves) = octaves {
+                    texture = texture.with_octaves(*octaves);
+                }
+                if let Some(vein) = vein {
+                    texture = texture.with_vein(vein.build(context, depth + 1)?);
+                }
+                if let Some(base) = base {
+                    texture = texture.with_base(base.build(context, depth + 1)?);
+                }
+                Ok(Box::new(texture))
+            }
+            TextureDescription::Wood { seed, ring_frequency, distortion, octaves, light, dark } => {
+                let mut texture = WoodTexture::new(*seed);
+                if let Some(ring_frequency) = ring_frequency {
+                    texture = texture.with_ring_frequency(*ring_frequency);
+                }
+                if let Some(distortion) = distortion {
+                    texture = texture.with_distortion(*distortion);
+                }
+                if let Some(octaves) = octaves {
+                    texture = texture.with_octaves(*octaves);
+                }
+                if let Some(light) = light {
+                    texture = texture.with_light(light.build(context, depth + 1)?);
+                }
+                if let Some(dark) = dark {
+                    texture = texture.with_dark(dark.build(context, depth + 1)?);
+                }
+                Ok(Box::new(texture))
+            }
+        };
+    }
+}
+
+fn lookup_texture(context: &mut TextureContext, name: &str, depth: u32) -> Result<Box<dyn Texture>, String> {
+    if depth > MAX_TEXTURE_DEPTH {
+        return Err(format!("texture '{}' refers back to itself", name));
+    }
+
+    let textures = context.textures;
+    return match textures.get(name) {
+        Some(texture) => texture.build(context, depth + 1),
+        None => {
+            let mut known: Vec<&String> = textures.keys().collect();
+            known.sort();
+            Err(format!("unknown texture '{}', defined textures are {:?}", name, known))
+        }
+    };
+}
+
+impl MaterialDescription {
+    fn build(&self, context: &mut TextureContext) -> Result<Box<dyn Material>, String> {
+        return match self {
+            MaterialDescription::Lambertian { albedo } => Ok(Box::new(Lambertian::textured(albedo.build(context, 0)?))),
+            MaterialDescription::Metal { albedo, fuzz } => {
+                Ok(Box::new(Metal::textured(albedo.build(context, 0)?, fuzz.build(context, 0)?)))
+            }
+            MaterialDescription::Dielectric { refraction_index } => {
+                Ok(Box::new(Dielectric::textured(refraction_index.build(context, 0)?)))
+            }
+            MaterialDescription::DiffuseLight { emit, one_sided } => {
+                let light = DiffuseLight::textured(emit.build(context, 0)?);
+                if *one_sided {
+                    Ok(Box::new(light.one_sided()))
+                } else {
+                    Ok(Box::new(light))
+                }
+            }
+            MaterialDescription::Conductor { metal, eta, k, reflectance, roughness, roughness_u, roughness_v, tangent } => {
+                //built once and shared by the sides that don't have their own
+                let mut shared: Option<Arc<dyn Texture>> = None;
+                let mut side = |input: &Option<TextureInput>| -> Result<Box<dyn Texture>, String> {
+                    if let Some(input) = input {
+                        return input.build(context, 0);
+                    }
+                    let texture = match &shared {
+                        Some(texture) => texture.clone(),
+                        None => Arc::from(roughness.build(context, 0)?),
+                    };
+                    shared = Some(texture.clone());
+                    return Ok(Box::new(texture));
+                };
+                let roughness_u = side(roughness_u)?;
+                let roughness_v = side(roughness_v)?;
+                let conductor = match (metal, eta, k, reflectance) {
+                    (Some(metal), None, None, None) => Conductor::textured(metal.parse::<ConductorPreset>()?.ior(), roughness_u, roughness_v),
+                    (None, Some(eta), Some(k), None) => Conductor::textured(ComplexIor::new(vec3(*eta), vec3(*k)), roughness_u, roughness_v),
+                    (None, None, None, Some(reflectance)) => Conductor::reflective(reflectance.build(context, 0)?, roughness_u, roughness_v),
+                    _ => return Err("conductor needs exactly one of metal, eta with k, or reflectance".to_string()),
+                };
+                match tangent {
+                    Some(tangent) if vec3(*tangent).length_squared() == 0_f64 => Err("conductor tangent must not be zero".to_string()),
+                    Some(tangent) => Ok(Box::new(conductor.with_tangent(vec3(*tangent)))),
+                    None => Ok(Box::new(conductor)),
+                }
+            }
+            MaterialDescription::Isotropic { albedo } => Ok(Box::new(Isotropic::textured(albedo.build(context, 0)?))),
+            MaterialDescription::HenyeyGreenstein { albedo, g } => {
+                if !(*g > -1_f64 && *g < 1_f64) {
+                    return Err(format!("henyey_greenstein g must be between -1 and 1, got {}", g));
+                }
+                Ok(Box::new(HenyeyGreenstein::textured(albedo.build(context, 0)?, *g)))
+            }
+        };
+    }
+}
+
+impl ObjectDescription {
+    fn build(&self, materials: &HashMap<String, MaterialDescription>, context: &mut TextureContext) -> Result<Box<dyn Renderable>, String> {
+        return match self {
+            ObjectDescription::Sphere { center, radius, material } => {
+                let material = lookup_material(materials, context, material)?;
+                Ok(Box::new(Sphere::new(vec3(*center), *radius, material)))
+            }
+            ObjectDescription::Plane { point, normal, material } => {
+                if vec3(*normal).length_squared() == 0_f64 {
+                    return Err("plane normal must not be zero".to_string());
+                }
+                let material = lookup_material(materials, context, material)?;
+                Ok(Box::new(Plane::new(vec3(*point), vec3(*normal), material)))
+            }
+            ObjectDescription::XyRect { x, y, z, material, flip } => {
+                check_range("x", x)?;
+                check_range("y", y)?;
+                let rect = XYRect::new(x[0], x[1], y[0], y[1], *z, lookup_material(materials, context, material)?);
+                Ok(if *flip { Box::new(rect.flipped()) } else { Box::new(rect) })
+            }
+            ObjectDescription::XzRect { x, z, y, material, flip } => {
+                check_range("x", x)?;
+                check_range("z", z)?;
+                let rect = XZRect::new(x[0], x[1], z[0], z[1], *y, lookup_material(materials, context, material)?);
+                Ok(if *flip { Box::new(rect.flipped()) } else { Box::new(rect) })
+            }
+            ObjectDescription::YzRect { y, z, x, material, flip } => {
+                check_range("y", y)?;
+                check_range("z", z)?;
+                let rect = YZRect::new(y[0], y[1], z[0], z[1], *x, lookup_material(materials, context, material)?);
+                Ok(if *flip { Box::new(rect.flipped()) } else { Box::new(rect) })
+            }
+            ObjectDescription::Quad { corner, u, v, material, flip } => {
+                if vec3(*u).cross(vec3(*v)).length_squared() == 0_f64 {
+                    return Err("quad edges u and v must not be parallel or zero".to_string());
+                }
+                let quad = Quad::new(vec3(*corner), vec3(*u), vec3(*v), lookup_material(materials, context, material)?);
+                Ok(if *flip { Box::new(quad.flipped()) } else { Box::new(quad) })
+            }
+            ObjectDescription::Obj { path, material, position, scale } => {
+                if *scale <= 0_f64 {
+                    return Err(format!("obj scale must be positive, got {}", scale));
+                }
+
+                let full_path = context.base_dir.join(path);
+                let mut model = match material {
+                    Some(_) => ObjModel::load_geometry(&full_path),
+                    None => ObjModel::load(&full_path),
+                }
+                .map_err(|e| e.to_string())?;
+                for mesh in &mut model.meshes {
+                    for p in &mut mesh.buffers.positions {
+                        *p = *scale * *p + vec3(*position);
+                    }
+                }
+
+                let meshes = match material {
+                    Some(name) => {
+                        let mut meshes = Vec::new();
+                        for mesh in model.meshes {
+                            meshes.push(TriangleMesh::new(mesh.buffers, lookup_material(materials, context, name)?)?);
+                        }
+                        meshes
+                    }
+                    None => model.build(&mut context.images).map_err(|e| e.to_string())?,
+                };
+                if meshes.is_empty() {
+                    return Err(format!("{} has no faces", full_path.display()));
+                }
+                Ok(Box::new(Bvh::new(meshes)))
+            }
+            ObjectDescription::MovingSphere { center0, center1, time0, time1, radius, material } => {
+                if time1 < time0 {
+                    return Err(format!("moving_sphere time1 {} is before time0 {}", time1, time0));
+                }
+                let material = lookup_material(materials, context, material)?;
+                Ok(Box::new(MovingSphere::new(vec3(*center0), vec3(*center1), *time0, *time1, *radius, material)))
+            }
+            ObjectDescription::Keyframed { object, keyframes } => {
+                let shared: Arc<dyn Renderable> = Arc::from(object.build(materials, context)?);
+                let keyframes = keyframes.iter().map(|k| k.build()).collect::<Result<Vec<Keyframe>, String>>()?;
+                Ok(Box::new(Keyframed::new(shared, keyframes)?))
+            }
+            ObjectDescription::Instance { object, transforms } => {
+                if transforms.is_empty() {
+                    return Err("instance needs at least one transform".to_string());
+                }
+
+                let shared: Arc<dyn Renderable> = Arc::from(object.build(materials, context)?);
+                let mut copies = Vec::new();
+                for (i, transform) in transforms.iter().enumerate() {
+                    let transform = transform.build().and_then(|matrix| Transformed::new(shared.clone(), matrix));
+                    copies.push(transform.map_err(|e| format!("transform {}: {}", i + 1, e))?);
+                }
+                Ok(Box::new(Bvh::new(copies)))
+            }
+            ObjectDescription::ConstantMedium { boundary, density, material } => {
+                if *density <= 0_f64 {
+                    return Err(format!("constant_medium density must be positive, got {}", density));
+                }
+                let boundary = boundary.build(materials, context)?;
+                let material = lookup_material(materials, context, material)?;
+                Ok(Box::new(ConstantMedium::new(boundary, *density, material)))
+            }
+            ObjectDescription::GridMedium { path, resolution, min, max, density, material } => {
+                if *density <= 0_f64 {
+                    return Err(format!("grid_medium density must be positive, got {}", density));
+                }
+                if (0..3).any(|axis| min[axis] >= max[axis]) {
+                    return Err(format!("grid_medium min {:?} must be below max {:?} on every axis", min, max));
+                }
+                let grid = load_density_grid(context.base_dir.join(path), *resolution).map_err(|e| e.to_string())?;
+                let material = lookup_material(materials, context, material)?;
+                Ok(Box::new(GridMedium::new(grid, Aabb::new(vec3(*min), vec3(*max)), *density, material)))
+            }
+            ObjectDescription::Gltf { path, light_radius } => {
+                if let Some(radius) = light_radius.filter(|&r| r <= 0_f64) {
+                    return Err(format!("gltf light_radius must be positive, got {}", radius));
+                }
+
+                let full_path = context.base_dir.join(path);
+                let objects = GltfDocument::load(&full_path)
+                    .and_then(|document| document.build(&mut context.images, *light_radius))
+                    .map_err(|e| e.to_string())?;
+                if objects.is_empty() {
+                    return Err(format!("{} has no meshes or lights", full_path.display()));
+                }
+                Ok(Box::new(Bvh::new(objects)))
+            }
+            ObjectDescription::Triangle { vertices, normals, uvs, material } => {
+                let [p0, p1, p2] = [vec3(vertices[0]), vec3(vertices[1]), vec3(vertices[2])];
+                if (p1 - p0).cross(p2 - p0).length_squared() == 0_f64 {
+                    return Err("triangle vertices must not lie on one line".to_string());
+                }
+                let mut triangle = Triangle::new(p0, p1, p2, lookup_material(materials, context, material)?);
+                if let Some(normals) = normals {
+                    if normals.iter().any(|n| vec3(*n).length_squared() == 0_f64) {
+                        return Err("triangle normals must not be zero".to_string());
+                    }
+                    triangle = triangle.with_normals([vec3(normals[0]), vec3(normals[1]), vec3(normals[2])]);
+                }
+                if let Some(uvs) = uvs {
+                    triangle = triangle.with_uvs([(uvs[0][0], uvs[0][1]), (uvs[1][0], uvs[1][1]), (uvs[2][0], uvs[2][1])]);
+                }
+                Ok(Box::new(triangle))
+            }
+        };
+    }
+}
+
+//a rectangle of zero width could never be hit and divides by zero for its uvs
+fn check_range(axis: &str, range: &[f64; 2]) -> Result<(), String> {
+    if range[0] == range[1] {
+        return Err(format!("{} range [{}, {}] is empty", axis, range[0], range[1]));
+    }
+    return Ok(());
+}
+
+fn lookup_material(materials: &HashMap<String, MaterialDescription>, context: &mut TextureContext, name: &str) -> Result<Box<dyn Material>, String> {
+    return match materials.get(name) {
+        Some(material) => material.build(context).map_err(|e| format!("material '{}': {}", name, e)),
+        None => {
+            let mut known: Vec<&String> = materials.keys().collect();
+            known.sort();
+            Err(format!("unknown material '{}', defined materials are {:?}", name, known))
+        }
+    };
+}
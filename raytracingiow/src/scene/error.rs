@@ -0,0 +1,33 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum SceneError {
+    //the scene file could not be read
+    Io(String, io::Error),
+    //the file is not valid toml or does not match the scene schema
+    Parse(toml::de::Error),
+    //the file parsed but refers to something that does not exist or is out of range
+    Invalid { line: usize, message: String },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            SceneError::Io(path, e) => write!(f, "could not read scene file {}: {}", path, e),
+            SceneError::Parse(e) => write!(f, "invalid scene file: {}", e),
+            SceneError::Invalid { line, message } => write!(f, "invalid scene file at line {}: {}", line, message),
+        };
+    }
+}
+
+impl Error for SceneError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        return match self {
+            SceneError::Io(_, e) => Some(e),
+            SceneError::Parse(e) => Some(e),
+            SceneError::Invalid { .. } => None,
+        };
+    }
+}
@@ -0,0 +1,612 @@
+mod description;
+mod error;
+
+pub use self::error::*;
+
+use crate::output::ToneMap;
+use crate::render::Camera;
+use crate::render::Renderable;
+use crate::render::RenderSettings;
+
+use std::fs;
+use std::path::Path;
+
+pub struct Scene {
+    pub world: Box<dyn Renderable>,
+    pub camera: Camera,
+    pub settings: RenderSettings,
+    //how the linear render is turned into png and ppm pixels
+    pub tone_map: ToneMap,
+}
+
+impl Scene {
+    //relative paths in the source are taken from the current directory
+    pub fn from_toml(source: &str) -> Result<Scene, SceneError> {
+        return Scene::from_toml_in(source, Path::new(""));
+    }
+
+    fn from_toml_in(source: &str, base_dir: &Path) -> Result<Scene, SceneError> {
+        let description: description::SceneDescription = toml::from_str(source).map_err(SceneError::Parse)?;
+        return description.build(source, base_dir);
+    }
+
+    //changes the output size, keeping the camera's aspect ratio in sync
+    pub fn set_resolution(&mut self, width: usize, height: usize) {
+        self.settings.width = width;
+        self.settings.height = height;
+        self.camera.set_aspect_ratio(width as f64 / height as f64);
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Scene, SceneError> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path).map_err(|e| SceneError::Io(path.display().to_string(), e))?;
+        return Scene::from_toml_in(&source, path.parent().unwrap_or_else(|| Path::new("")));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Ray;
+    use crate::math::Vec3;
+    use crate::output::save_image;
+    use crate::output::Image;
+    use crate::output::ImageFormat;
+    use crate::output::ToneMap;
+    use crate::output::ToneMapOperator;
+    use crate::output::TransferFunction;
+    use crate::render::Integrator;
+    use crate::render::LightList;
+    use crate::scene::Scene;
+
+    const SCENE: &str = r#"
+[render]
+width = 40
+height = 20
+integrator = "normals"
+
+[materials.red]
+type = "lambertian"
+albedo = [0.8, 0.3, 0.3]
+
+[[objects]]
+type = "sphere"
+center = [0.0, 0.0, -1.0]
+radius = 0.5
+material = "red"
+"#;
+
+    #[test]
+    fn test_load_scene() {
+        let scene = Scene::from_toml(SCENE).unwrap();
+        assert_eq!(scene.settings.width, 40);
+        assert_eq!(scene.settings.height, 20);
+        assert_eq!(scene.settings.samples_per_pixel, 100);
+        assert!(matches!(scene.settings.integrator, Integrator::Normals));
+
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = scene.world.hit(&ray, 0.0, f64::MAX).unwrap();
+        assert!((hit.t - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_unknown_material_reports_line() {
+        let source = SCENE.replace("material = \"red\"", "material = \"blue\"");
+        let message = Scene::from_toml(&source).err().unwrap().to_string();
+        assert!(message.contains("line 11"), "{}", message);
+        assert!(message.contains("blue"), "{}", message);
+    }
+
+    #[test]
+    fn test_missing_field_reports_field() {
+        let source = SCENE.replace("radius = 0.5\n", "");
+        let message = Scene::from_toml(&source).err().unwrap().to_string();
+        assert!(message.contains("radius"), "{}", message);
+    }
+
+    #[test]
+    fn test_invalid_settings_report_field() {
+        let error = |from: &str, to: &str| Scene::from_toml(&SCENE.replace(from, to)).err().unwrap().to_string();
+
+        let message = error("width = 40", "width = 0");
+        assert!(message.contains("line 3") && message.contains("width"), "{}", message);
+        let message = error("height = 20", "height = 20\nsamples_per_pixel = 0");
+        assert!(message.contains("line 5") && message.contains("samples_per_pixel"), "{}", message);
+        let message = error("height = 20", "height = 20\nbackground = [0.5, nan, 0.5]");
+        assert!(message.contains("line 5") && message.contains("background") && message.contains("finite"), "{}", message);
+
+        let camera = |lines: &str| Scene::from_toml(&format!("{}\n[camera]\n{}\n", SCENE, lines)).err().unwrap().to_string();
+        let message = camera("look_from = [1.0, 2.0, 3.0]\nlook_at = [1.0, 2.0, 3.0]");
+        assert!(message.contains("line 19") && message.contains("look_at"), "{}", message);
+        let message = camera("look_from = [0.0, 0.0, 0.0]\nlook_at = [0.0, 5.0, 0.0]");
+        assert!(message.contains("line 19") && message.contains("vup"), "{}", message);
+        let message = camera("vfov = inf");
+        assert!(message.contains("line 18") && message.contains("vfov"), "{}", message);
+        let message = camera("vfov = 180.0");
+        assert!(message.contains("vfov"), "{}", message);
+        let message = camera("focus_distance = 0.0");
+        assert!(message.contains("focus_distance"), "{}", message);
+
+        //misspelled keys are not silently ignored
+        let message = error("radius = 0.5", "radius = 0.5\nflipp = true");
+        assert!(message.contains("flipp"), "{}", message);
+        let message = error("albedo = [0.8, 0.3, 0.3]", "albedo = [0.8, 0.3, 0.3]\nfuzzz = 0.1");
+        assert!(message.contains("fuzzz"), "{}", message);
+    }
+
+    #[test]
+    fn test_tone_map() {
+        let scene = Scene::from_toml(SCENE).unwrap();
+        assert_eq!(scene.tone_map.operator, ToneMapOperator::Clamp);
+        assert_eq!(scene.tone_map.transfer, TransferFunction::Srgb);
+
+        let source = format!("{}\n[tone_map]\noperator = \"aces\"\nexposure = -1.5\ntransfer = \"gamma2\"\n", SCENE);
+        let scene = Scene::from_toml(&source).unwrap();
+        assert_eq!(scene.tone_map.operator, ToneMapOperator::Aces);
+        assert_eq!(scene.tone_map.exposure, -1.5);
+        assert_eq!(scene.tone_map.transfer, TransferFunction::Gamma2);
+
+        let source = source.replace("\"aces\"", "\"drago\"");
+        let message = Scene::from_toml(&source).err().unwrap().to_string();
+        assert!(message.contains("line 18"), "{}", message);
+    }
+
+    #[test]
+    fn test_textures() {
+        let source = r#"
+[textures.tiles]
+type = "solid_checker"
+even = [1.0, 1.0, 1.0]
+odd = { type = "gradient", start = [0.0, 0.0, 0.0], end = [0.0, 0.0, 1.0], axis = "v" }
+size = 10.0
+
+[materials.tiled]
+type = "lambertian"
+albedo = "tiles"
+
+[[objects]]
+type = "sphere"
+center = [0.0, 0.0, -3.0]
+radius = 1.0
+material = "tiled"
+"#;
+        let scene = Scene::from_toml(source).unwrap();
+        //at the equator of the sphere, in the odd cube below and left of the origin
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = scene.world.hit(&ray, 0.0, f64::MAX).unwrap();
+        let albedo = std::f64::consts::PI * hit.material.eval(&ray, &hit, hit.normal);
+        assert!((albedo - Vec3::new(0.0, 0.0, 0.5)).length() < 1e-9, "{:?}", albedo);
+
+        let message = Scene::from_toml(&source.replace("albedo = \"tiles\"", "albedo = \"stone\"")).err().unwrap().to_string();
+        assert!(message.contains("stone") && message.contains("line 12"), "{}", message);
+
+        let message = Scene::from_toml(&source.replace("axis = \"v\"", "axis = \"w\"")).err().unwrap().to_string();
+        assert!(message.contains("gradient"), "{}", message);
+    }
+
+    #[test]
+    fn test_image_texture_relative_to_scene() {
+        let dir = std::env::temp_dir().join(format!("raytracing_iow_scene_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let linear = ToneMap {
+            transfer: TransferFunction::Linear,
+            ..ToneMap::default()
+        };
+        let image = Image::from_pixels(1, 1, vec![Vec3::new(0.25, 0.5, 1.0)]);
+        save_image(dir.join("paint.png"), &image, Some(ImageFormat::Png16), linear).unwrap();
+
+        let source = r#"
+[materials.painted]
+type = "lambertian"
+albedo = { type = "image", path = "paint.png", encoding = "linear", filter = "nearest" }
+
+[[objects]]
+type = "sphere"
+center = [0.0, 0.0, -3.0]
+radius = 1.0
+material = "painted"
+"#;
+        std::fs::write(dir.join("scene.toml"), source).unwrap();
+        std::fs::write(dir.join("broken.toml"), source.replace("paint.png", "missing.png")).unwrap();
+        let scene = Scene::load(dir.join("scene.toml"));
+        let broken = Scene::load(dir.join("broken.toml"));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let scene = scene.unwrap();
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = scene.world.hit(&ray, 0.0, f64::MAX).unwrap();
+        let albedo = std::f64::consts::PI * hit.material.eval(&ray, &hit, hit.normal);
+        assert!((albedo - Vec3::new(0.25, 0.5, 1.0)).length() < 1e-4, "{:?}", albedo);
+
+        let message = broken.err().unwrap().to_string();
+        assert!(message.contains("missing.png"), "{}", message);
+    }
+
+    #[test]
+    fn test_obj_placed_in_scene() {
+        let dir = std::env::temp_dir().join(format!("raytracing_iow_scene_obj_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("quad.obj"), "mtllib quad.mtl\nv 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nusemtl lamp\nf 1 2 3 4\n").unwrap();
+        std::fs::write(dir.join("quad.mtl"), "newmtl lamp\nKe 2 2 2\n").unwrap();
+
+        let source = r#"
+[materials.grey]
+type = "lambertian"
+albedo = 0.5
+
+[[objects]]
+type = "obj"
+path = "quad.obj"
+position = [-1.0, -1.0, -3.0]
+scale = 2.0
+
+[[objects]]
+type = "obj"
+path = "quad.obj"
+material = "grey"
+position = [5.0, 0.0, 0.0]
+"#;
+        std::fs::write(dir.join("scene.toml"), source).unwrap();
+        std::fs::write(dir.join("broken.toml"), source.replace("scale = 2.0", "scale = -2.0")).unwrap();
+        std::fs::write(dir.join("missing.toml"), source.replace("\"quad.obj\"\nposition", "\"nothing.obj\"\nposition")).unwrap();
+        let scene = Scene::load(dir.join("scene.toml"));
+        let broken = Scene::load(dir.join("broken.toml"));
+        let missing = Scene::load(dir.join("missing.toml"));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let scene = scene.unwrap();
+        let ray = Ray::new(Vec3::new(0.5, 0.5, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = scene.world.hit(&ray, 0.0, f64::MAX).unwrap();
+        assert!((hit.t - 3.0).abs() < 1e-9 && hit.material.is_emissive());
+        assert_eq!(LightList::new(&*scene.world).len(), 2);
+
+        let message = broken.err().unwrap().to_string();
+        assert!(message.contains("line 6") && message.contains("scale"), "{}", message);
+        let message = missing.err().unwrap().to_string();
+        assert!(message.contains("nothing.obj"), "{}", message);
+    }
+
+    #[test]
+    fn test_procedural_textures() {
+        let source = r#"
+[textures.stone]
+type = "marble"
+seed = 3
+vein = [0.1, 0.1, 0.2]
+
+[materials.stone]
+type = "lambertian"
+albedo = "stone"
+
+[materials.clouds]
+type = "lambertian"
+albedo = { type = "noise", pattern = "fbm", seed = 3, scale = 2.0, high = { type = "wood" } }
+
+[[objects]]
+type = "sphere"
+center = [0.0, 0.0, -3.0]
+radius = 1.0
+material = "stone"
+
+[[objects]]
+type = "sphere"
+center = [0.0, 3.0, -3.0]
+radius = 1.0
+material = "clouds"
+"#;
+        assert!(Scene::from_toml(source).is_ok());
+
+        let message = Scene::from_toml(&source.replace("\"fbm\"", "\"simplex\"")).err().unwrap().to_string();
+        assert!(message.contains("simplex") && message.contains("line 21"), "{}", message);
+    }
+
+    #[test]
+    fn test_flat_objects() {
+        let source = format!(
+            "{}{}",
+            SCENE,
+            r#"
+[[objects]]
+type = "plane"
+point = [0.0, -0.5, 0.0]
+normal = [0.0, 1.0, 0.0]
+material = "red"
+
+[[objects]]
+type = "xz_rect"
+x = [-1.0, 1.0]
+z = [-2.0, 0.0]
+y = 1.0
+flip = true
+material = "red"
+
+[[objects]]
+type = "quad"
+corner = [2.0, 0.0, -1.0]
+u = [1.0, 0.0, 0.0]
+v = [0.0, 1.0, 0.0]
+material = "red"
+"#
+        );
+        let scene = Scene::from_toml(&source).unwrap();
+
+        let down = Ray::new(Vec3::new(0.0, 0.0, -3.0), Vec3::new(0.0, -1.0, 0.0));
+        assert!((scene.world.hit(&down, 0.0, f64::MAX).unwrap().t - 0.5).abs() < 1e-9);
+        let up = Ray::new(Vec3::new(0.5, 0.0, -1.5), Vec3::new(0.0, 1.0, 0.0));
+        let hit = scene.world.hit(&up, 0.0, f64::MAX).unwrap();
+        assert!((hit.t - 1.0).abs() < 1e-9 && hit.normal.y() < 0.0);
+        let forward = Ray::new(Vec3::new(2.5, 0.5, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!((scene.world.hit(&forward, 0.0, f64::MAX).unwrap().t - 1.0).abs() < 1e-9);
+
+        let message = Scene::from_toml(&source.replace("z = [-2.0, 0.0]", "z = [0.0, 0.0]")).err().unwrap().to_string();
+        assert!(message.contains("z range") && message.contains("line 23"), "{}", message);
+        let message = Scene::from_toml(&source.replace("u = [1.0, 0.0, 0.0]", "u = [0.0, 2.0, 0.0]")).err().unwrap().to_string();
+        assert!(message.contains("parallel"), "{}", message);
+    }
+
+    #[test]
+    fn test_triangle() {
+        let source = format!(
+            "{}{}",
+            SCENE,
+            r#"
+[[objects]]
+type = "triangle"
+vertices = [[-1.0, -1.0, -2.0], [1.0, -1.0, -2.0], [0.0, 1.0, -2.0]]
+uvs = [[0.0, 0.0], [1.0, 0.0], [0.5, 1.0]]
+material = "red"
+"#
+        );
+        let scene = Scene::from_toml(&source).unwrap();
+        let ray = Ray::new(Vec3::new(0.0, -0.9, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = scene.world.hit(&ray, 0.0, f64::MAX).unwrap();
+        assert!((hit.t - 2.0).abs() < 1e-9 && (hit.v - 0.05).abs() < 1e-9);
+
+        let message = Scene::from_toml(&source.replace("[0.0, 1.0, -2.0]", "[3.0, -1.0, -2.0]")).err().unwrap().to_string();
+        assert!(message.contains("one line"), "{}", message);
+    }
+
+    #[test]
+    fn test_gltf_in_scene() {
+        use rand::SeedableRng;
+
+        let dir = std::env::temp_dir().join(format!("raytracing_iow_scene_gltf_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        //a triangle (0 0 0) (1 0 0) (0 1 0) moved to z = -3, seen by a camera at the origin turned to look down -x
+        let gltf = r#"{
+  "asset": { "version": "2.0" },
+  "nodes": [
+    { "mesh": 0, "translation": [0, 0, -3] },
+    { "camera": 0, "rotation": [0, 0.7071067812, 0, 0.7071067812] }
+  ],
+  "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 }, "material": 0 }] }],
+  "materials": [{ "emissiveFactor": [1, 1, 1] }],
+  "accessors": [{ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3" }],
+  "bufferViews": [{ "buffer": 0, "byteLength": 36 }],
+  "buffers": [{ "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAA", "byteLength": 36 }],
+  "cameras": [{ "type": "perspective", "perspective": { "yfov": 1.0, "znear": 0.1 } }]
+}"#;
+        std::fs::write(dir.join("model.gltf"), gltf).unwrap();
+        let source = r#"
+[camera.gltf]
+path = "model.gltf"
+
+[[objects]]
+type = "gltf"
+path = "model.gltf"
+"#;
+        std::fs::write(dir.join("scene.toml"), source).unwrap();
+        std::fs::write(dir.join("no_camera.toml"), source.replace("path = \"model.gltf\"\n\n", "path = \"model.gltf\"\nindex = 1\n\n")).unwrap();
+        let scene = Scene::load(dir.join("scene.toml"));
+        let no_camera = Scene::load(dir.join("no_camera.toml"));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let scene = scene.unwrap();
+        let ray = Ray::new(Vec3::new(0.2, 0.2, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = scene.world.hit(&ray, 0.0, f64::MAX).unwrap();
+        assert!((hit.t - 3.0).abs() < 1e-9 && hit.material.is_emissive());
+        assert_eq!(LightList::new(&*scene.world).len(), 1);
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(2);
+        let center = scene.camera.get_ray(0.5, 0.5, &mut rng);
+        assert!(center.origin().length() < 1e-9);
+        assert!((center.direction().unit_vector() - Vec3::new(-1.0, 0.0, 0.0)).length() < 1e-6);
+
+        let message = no_camera.err().unwrap().to_string();
+        assert!(message.contains("line 2") && message.contains("no camera 1"), "{}", message);
+    }
+
+    #[test]
+    fn test_instances() {
+        let source = format!(
+            "{}{}",
+            SCENE,
+            r#"
+[[objects]]
+type = "instance"
+object = { type = "sphere", center = [0.0, 0.0, 0.0], radius = 1.0, material = "red" }
+transforms = [
+    { translate = [4.0, 0.0, -5.0], scale = [2.0, 0.5, 1.0] },
+    { translate = [-4.0, 0.0, -5.0], rotate = [0.0, 0.0, 90.0], scale = [2.0, 0.5, 1.0] },
+]
+"#
+        );
+        let scene = Scene::from_toml(&source).unwrap();
+        //the first copy is stretched along x, the second one turned to stand along y
+        let hit = scene.world.hit(&Ray::new(Vec3::new(5.9, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0)), 0.0, f64::MAX);
+        assert!(hit.is_some());
+        let hit = scene.world.hit(&Ray::new(Vec3::new(-4.0, 1.9, 0.0), Vec3::new(0.0, 0.0, -1.0)), 0.0, f64::MAX);
+        assert!(hit.is_some());
+        let hit = scene.world.hit(&Ray::new(Vec3::new(-5.9, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0)), 0.0, f64::MAX);
+        assert!(hit.is_none());
+
+        let message = Scene::from_toml(&source.replace("[-4.0, 0.0, -5.0], rotate = [0.0, 0.0, 90.0], scale = [2.0, 0.5, 1.0]", "[0.0, 0.0, 0.0], scale = 0.0"))
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(message.contains("line 17") && message.contains("transform 2") && message.contains("singular"), "{}", message);
+
+        let message = Scene::from_toml(&source.replace("[4.0, 0.0, -5.0], scale = [2.0, 0.5, 1.0]", "[0.0, 0.0, -3.0], scale = nan"))
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(message.contains("transform 1") && message.contains("finite"), "{}", message);
+    }
+
+    #[test]
+    fn test_motion() {
+        use rand::SeedableRng;
+
+        let source = format!(
+            "{}{}",
+            SCENE,
+            r#"
+[camera]
+shutter = [0.0, 1.0]
+
+[[objects]]
+type = "moving_sphere"
+center0 = [0.0, 5.0, -5.0]
+center1 = [4.0, 5.0, -5.0]
+radius = 1.0
+material = "red"
+
+[[objects]]
+type = "keyframed"
+object = { type = "sphere", center = [0.0, 0.0, 0.0], radius = 1.0, material = "red" }
+keyframes = [
+    { time = 0.0, translate = [0.0, -5.0, -5.0] },
+    { time = 1.0, translate = [0.0, -5.0, -5.0], scale = [3.0, 1.0, 1.0] },
+]
+"#
+        );
+        let scene = Scene::from_toml(&source).unwrap();
+        let ray = |x: f64, y: f64, time: f64| Ray::new(Vec3::new(x, y, 0.0), Vec3::new(0.0, 0.0, -1.0)).with_time(time);
+        assert!(scene.world.hit(&ray(4.0, 5.0, 0.0), 0.0, f64::MAX).is_none());
+        assert!(scene.world.hit(&ray(4.0, 5.0, 1.0), 0.0, f64::MAX).is_some());
+        assert!(scene.world.hit(&ray(2.5, -5.0, 0.0), 0.0, f64::MAX).is_none());
+        assert!(scene.world.hit(&ray(2.5, -5.0, 1.0), 0.0, f64::MAX).is_some());
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(3);
+        let times: Vec<f64> = (0..100).map(|_| scene.camera.get_ray(0.5, 0.5, &mut rng).time()).collect();
+        assert!(times.iter().all(|&t| (0.0..1.0).contains(&t)) && times.iter().any(|&t| t > 0.5));
+
+        let message = Scene::from_toml(&source.replace("[0.0, 1.0]", "[1.0, 0.5]")).err().unwrap().to_string();
+        assert!(message.contains("line 18") && message.contains("shutter"), "{}", message);
+        let message = Scene::from_toml(&source.replace("time = 1.0", "time = 0.0")).err().unwrap().to_string();
+        assert!(message.contains("increase"), "{}", message);
+    }
+
+    #[test]
+    fn test_media() {
+        let source = format!(
+            "{}{}",
+            SCENE,
+            r#"
+[materials.smoke]
+type = "henyey_greenstein"
+albedo = 0.9
+g = 0.5
+
+[[objects]]
+type = "constant_medium"
+boundary = { type = "sphere", center = [0.0, 0.0, -5.0], radius = 1.0, material = "red" }
+density = 1000.0
+material = "smoke"
+
+[fog]
+density = 0.001
+"#
+        );
+        let scene = Scene::from_toml(&source).unwrap();
+        //so dense the smoke scatters right where a ray enters it
+        let hit = scene.world.hit(&Ray::new(Vec3::new(0.0, 0.0, -3.0), Vec3::new(0.0, 0.0, -1.0)), 0.001, f64::MAX).unwrap();
+        assert!(hit.t >= 1.0 && hit.t < 1.05);
+        assert!(!hit.material.is_specular());
+        //the fog is too thin to stop many rays, but the sphere around the camera bounds it
+        assert!(scene.world.hit(&Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)), 0.001, f64::MAX).is_none());
+        assert!(scene.world.bounding_box().is_some());
+
+        let message = Scene::from_toml(&source.replace("density = 1000.0", "density = 0.0")).err().unwrap().to_string();
+        assert!(message.contains("line 22") && message.contains("density"), "{}", message);
+        let message = Scene::from_toml(&source.replace("density = 0.001", "density = 0.001\ng = 1.0")).err().unwrap().to_string();
+        assert!(message.contains("fog g"), "{}", message);
+        let message = Scene::from_toml(&source.replace("g = 0.5", "g = -1.5")).err().unwrap().to_string();
+        assert!(message.contains("henyey_greenstein"), "{}", message);
+    }
+
+    #[test]
+    fn test_grid_medium() {
+        let dir = std::env::temp_dir().join(format!("raytracing_iow_scene_grid_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        //4 voxels, only the last one along z is dense
+        std::fs::write(dir.join("puff.raw"), [0_u8, 0, 0, 255]).unwrap();
+        let source = format!(
+            "{}{}",
+            SCENE,
+            r#"
+[materials.smoke]
+type = "isotropic"
+albedo = 0.8
+
+[[objects]]
+type = "grid_medium"
+path = "puff.raw"
+resolution = [1, 1, 4]
+min = [-1.0, -1.0, -9.0]
+max = [1.0, 1.0, -5.0]
+density = 1000.0
+material = "smoke"
+"#
+        );
+        let scene = Scene::from_toml_in(&source, &dir).unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+
+        //the ray enters at z -5, the voxel centres are at -8.5, -7.5, -6.5 and -5.5
+        let hit = scene.world.hit(&Ray::new(Vec3::new(0.8, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0)), 0.001, f64::MAX).unwrap();
+        assert!(hit.t > 5.0 && hit.t < 5.6, "{}", hit.t);
+        assert_eq!(scene.world.transmittance(&Ray::new(Vec3::new(0.8, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0)), 0.001, 4.9), 1.0);
+        assert_eq!(scene.world.transmittance(&Ray::new(Vec3::new(0.8, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0)), 0.001, f64::MAX), 0.0);
+
+        let message = Scene::from_toml_in(&source.replace("max = [1.0, 1.0, -5.0]", "max = [1.0, 1.0, -10.0]"), &dir)
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(message.contains("line 21") && message.contains("below max"), "{}", message);
+        let message = Scene::from_toml_in(&source, &dir).err().unwrap().to_string();
+        assert!(message.contains("puff.raw"), "{}", message);
+    }
+
+    #[test]
+    fn test_conductor() {
+        let source = format!(
+            "{}{}",
+            SCENE,
+            r#"
+[materials.gold]
+type = "conductor"
+metal = "gold"
+roughness_u = 0.1
+roughness_v = 0.5
+
+[[objects]]
+type = "sphere"
+center = [0.0, 0.0, -5.0]
+radius = 1.0
+material = "gold"
+"#
+        );
+        let scene = Scene::from_toml(&source).unwrap();
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = scene.world.hit(&ray, 0.001, f64::MAX).unwrap();
+        assert!(!hit.material.is_specular());
+        //gold reflects red far better than blue
+        let mirror = hit.material.eval(&ray, &hit, Vec3::new(0.0, 0.0, 1.0));
+        assert!(mirror.x() > 2.0 * mirror.z(), "{:?}", mirror);
+
+        let message = Scene::from_toml(&source.replace("\"gold\"\nroughness", "\"brass\"\nroughness")).err().unwrap().to_string();
+        assert!(message.contains("unknown metal 'brass'"), "{}", message);
+        let message = Scene::from_toml(&source.replace("metal = \"gold\"", "metal = \"gold\"\nreflectance = 0.9")).err().unwrap().to_string();
+        assert!(message.contains("exactly one"), "{}", message);
+        assert!(Scene::from_toml(&source.replace("metal = \"gold\"", "eta = [0.2, 0.9, 1.1]\nk = [3.9, 2.5, 2.1]")).is_ok());
+    }
+}